@@ -1,7 +1,7 @@
 // pub mod gdt;
 // pub mod interrupts;
 // pub mod memory;
-// pub mod serial;
+pub mod serial;
 // pub mod vga_buffer;
 // pub mod allocator; // Legacy allocator disabled for UEFI
 
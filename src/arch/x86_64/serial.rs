@@ -2,6 +2,8 @@ use uart_16550::SerialPort;
 use spin::Mutex;
 use lazy_static::lazy_static;
 
+use crate::console::{Console, Style};
+
 lazy_static! {
     pub static ref SERIAL1: Mutex<SerialPort> = {
         let mut serial_port = unsafe { SerialPort::new(0x3F8) };
@@ -20,6 +22,23 @@ pub fn _print(args: ::core::fmt::Arguments) {
     });
 }
 
+/// ANSI escape sequences, for terminals attached to the serial line.
+impl Console for SerialPort {
+    fn set_style(&mut self, style: Style) {
+        let sgr = match style {
+            Style::Normal => "\x1b[0m",
+            Style::Error => "\x1b[31m",
+            Style::Prompt => "\x1b[92m",
+            Style::Banner => "\x1b[96m",
+        };
+        let _ = core::fmt::Write::write_str(self, sgr);
+    }
+
+    fn clear(&mut self) {
+        let _ = core::fmt::Write::write_str(self, "\x1b[2J\x1b[H");
+    }
+}
+
 #[macro_export]
 macro_rules! serial_print {
     ($($arg:tt)*) => {
@@ -0,0 +1,75 @@
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+
+use super::BlockDevice;
+use crate::vfs::OpenFile;
+
+const BLOCK_SIZE: usize = 512;
+
+/// A disk image file used as a block device, so the file system inside it
+/// can be mounted.
+pub struct ImageDisk {
+    file: Box<dyn OpenFile>,
+    blocks: u64,
+    read_only: bool,
+}
+
+impl ImageDisk {
+    /// `read_only` if `file` was opened for reading only.
+    pub fn new(mut file: Box<dyn OpenFile>, read_only: bool) -> Result<Self, String> {
+        let blocks = file.size()? / BLOCK_SIZE as u64;
+        Ok(Self {
+            file,
+            blocks,
+            read_only,
+        })
+    }
+
+    fn check_range(&self, lba: u64, len: usize) -> Result<(), String> {
+        let count = (len / BLOCK_SIZE) as u64;
+        if lba.checked_add(count).is_none_or(|end| end > self.blocks) {
+            return Err(format!("Block {} is beyond the end of the image", lba));
+        }
+        Ok(())
+    }
+}
+
+impl BlockDevice for ImageDisk {
+    fn block_size(&self) -> usize {
+        BLOCK_SIZE
+    }
+
+    fn block_count(&self) -> u64 {
+        self.blocks
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn read_blocks(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), String> {
+        self.check_range(lba, buf.len())?;
+        self.file.seek(lba * BLOCK_SIZE as u64)?;
+        let mut done = 0;
+        while done < buf.len() {
+            let n = self.file.read(&mut buf[done..])?;
+            if n == 0 {
+                return Err(String::from("Unexpected end of image"));
+            }
+            done += n;
+        }
+        Ok(())
+    }
+
+    fn write_blocks(&mut self, lba: u64, buf: &[u8]) -> Result<(), String> {
+        if self.read_only {
+            return Err(String::from("The image is open for reading only"));
+        }
+        self.check_range(lba, buf.len())?;
+        self.file.seek(lba * BLOCK_SIZE as u64)?;
+        self.file.write(buf)
+    }
+}
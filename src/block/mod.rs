@@ -0,0 +1,64 @@
+extern crate alloc;
+
+pub mod image;
+pub mod partition;
+pub mod uefi_disk;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+
+use self::partition::PartitionDevice;
+use self::uefi_disk::UefiDisk;
+
+/// A device read and written in fixed-size blocks: a disk, a partition or
+/// a disk image. File system drivers such as FAT sit on top of it.
+pub trait BlockDevice: Send {
+    /// Bytes per block, usually 512.
+    fn block_size(&self) -> usize;
+    fn block_count(&self) -> u64;
+    /// True if writes will be refused.
+    fn read_only(&self) -> bool;
+    /// Read whole blocks starting at `lba`; `buf.len()` is a multiple of
+    /// the block size.
+    fn read_blocks(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), String>;
+    /// Write whole blocks starting at `lba`.
+    fn write_blocks(&mut self, lba: u64, buf: &[u8]) -> Result<(), String>;
+}
+
+/// Split a device name into the disk and partition number: `disk0` or
+/// `disk0p2`. `None` if it is not a device name.
+fn parse_name(name: &str) -> Option<(&str, Option<u32>)> {
+    let rest = name.strip_prefix("disk")?;
+    let (index, part) = match rest.split_once('p') {
+        Some((index, part)) => (index, Some(part.parse().ok()?)),
+        None => (rest, None),
+    };
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some((&name[..4 + index.len()], part))
+}
+
+/// True if `name` names a disk or partition, as accepted by `open`.
+pub fn is_device_name(name: &str) -> bool {
+    parse_name(name).is_some()
+}
+
+/// Open a disk (`diskN`, see `disks`) or one of its partitions (`diskNpM`,
+/// see `parts`).
+pub fn open(name: &str) -> Result<Box<dyn BlockDevice>, String> {
+    let (disk_name, part) =
+        parse_name(name).ok_or_else(|| format!("Invalid device name '{}' (see disks)", name))?;
+    let mut disk = Box::new(UefiDisk::open(disk_name)?);
+    let Some(number) = part else {
+        return Ok(disk);
+    };
+    let table = partition::read_table(disk.as_mut())?;
+    let partition = table
+        .partitions
+        .iter()
+        .find(|p| p.number == number)
+        .ok_or_else(|| format!("No partition '{}' (see parts)", name))?;
+    Ok(Box::new(PartitionDevice::new(disk, partition)))
+}
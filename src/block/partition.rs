@@ -0,0 +1,698 @@
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use uefi::{guid, Guid};
+
+use super::BlockDevice;
+
+/// Partition table schemes `read_table` understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    Gpt,
+    Mbr,
+}
+
+/// A partition as found in a partition table.
+pub struct Partition {
+    /// 1-based; `diskNpM` names partition `M`. MBR logical partitions
+    /// start at 5.
+    pub number: u32,
+    pub first_lba: u64,
+    /// Inclusive.
+    pub last_lba: u64,
+    /// Readable name of the partition type.
+    pub type_name: String,
+    /// GPT only.
+    pub uuid: Option<Guid>,
+    /// GPT only.
+    pub label: String,
+}
+
+impl Partition {
+    pub fn blocks(&self) -> u64 {
+        self.last_lba - self.first_lba + 1
+    }
+}
+
+pub struct Table {
+    pub scheme: Scheme,
+    /// GPT only.
+    pub disk_guid: Option<Guid>,
+    pub partitions: Vec<Partition>,
+    /// Problems that did not stop the table being read, such as a damaged
+    /// primary GPT header.
+    pub warning: Option<String>,
+}
+
+fn le16(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+fn le32(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+fn le64(b: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(b[at..at + 8].try_into().unwrap_or([0; 8]))
+}
+
+fn guid_at(b: &[u8], at: usize) -> Guid {
+    Guid::from_bytes(b[at..at + 16].try_into().unwrap_or([0; 16]))
+}
+
+/// CRC-32 as used by GPT (IEEE 802.3, reflected, polynomial 0xEDB88320).
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Read `count` blocks starting at `lba`.
+fn read(dev: &mut dyn BlockDevice, lba: u64, count: usize) -> Result<Vec<u8>, String> {
+    let mut buf = vec![0u8; count * dev.block_size()];
+    dev.read_blocks(lba, &mut buf)?;
+    Ok(buf)
+}
+
+/// Read the partition table of a disk: GPT if the MBR is protective,
+/// otherwise the MBR with any logical partitions.
+pub fn read_table(dev: &mut dyn BlockDevice) -> Result<Table, String> {
+    let mbr = read(dev, 0, 1)?;
+    if mbr[510] != 0x55 || mbr[511] != 0xAA {
+        return Err(String::from("No partition table"));
+    }
+    if mbr_entries(&mbr)
+        .iter()
+        .any(|e| e.kind == MBR_GPT_PROTECTIVE)
+    {
+        read_gpt(dev)
+    } else {
+        read_mbr(dev, &mbr)
+    }
+}
+
+// ── GPT ──
+
+/// The smallest GPT header and entry sizes.
+const GPT_HEADER_SIZE: usize = 92;
+const GPT_ENTRY_SIZE: usize = 128;
+/// More entries than any real table has, to bound what a corrupt header
+/// makes us read.
+const GPT_MAX_ENTRIES: usize = 4096;
+
+/// Names of well-known GPT partition types.
+const GPT_TYPES: &[(Guid, &str)] = &[
+    (guid!("c12a7328-f81f-11d2-ba4b-00a0c93ec93b"), "EFI System"),
+    (guid!("21686148-6449-6e6f-744e-656564454649"), "BIOS boot"),
+    (
+        guid!("e3c9e316-0b5c-4db8-817d-f92df00215ae"),
+        "Microsoft reserved",
+    ),
+    (
+        guid!("ebd0a0a2-b9e5-4433-87c0-68b6b72699c7"),
+        "Microsoft basic data",
+    ),
+    (
+        guid!("de94bba4-06d1-4d40-a16a-bfd50179d6ac"),
+        "Windows recovery",
+    ),
+    (
+        guid!("0fc63daf-8483-4772-8e79-3d69d8477de4"),
+        "Linux filesystem",
+    ),
+    (
+        guid!("4f68bce3-e8cd-4db1-96e7-fbcaf984b709"),
+        "Linux root (x86-64)",
+    ),
+    (
+        guid!("b921b045-1df0-41c3-af44-4c6f280d3fae"),
+        "Linux root (ARM64)",
+    ),
+    (guid!("933ac7e1-2eb4-4f13-b844-0e14e2aef915"), "Linux home"),
+    (guid!("0657fd6d-a4ab-43c4-84e5-0933c84b4f4f"), "Linux swap"),
+    (guid!("e6d6d379-f507-44c2-a23c-238f2a3df928"), "Linux LVM"),
+    (guid!("a19d880f-05fc-4d3b-a006-743f0f84911e"), "Linux RAID"),
+    (
+        guid!("bc13c2ff-59e6-4262-a352-b275fd6f7172"),
+        "Linux extended boot",
+    ),
+    (guid!("48465300-0000-11aa-aa11-00306543ecac"), "Apple HFS+"),
+    (guid!("7c3457ef-0000-11aa-aa11-00306543ecac"), "Apple APFS"),
+    (guid!("516e7cb4-6ecf-11d6-8ff8-00022d09712b"), "FreeBSD"),
+];
+
+fn gpt_type_name(guid: Guid) -> String {
+    match GPT_TYPES.iter().find(|(known, _)| *known == guid) {
+        Some((_, name)) => String::from(*name),
+        None => format!("{}", guid),
+    }
+}
+
+struct GptHeader {
+    alternate_lba: u64,
+    disk_guid: Guid,
+    entries_lba: u64,
+    entry_count: usize,
+    entry_size: usize,
+    entries_crc: u32,
+}
+
+/// Read and check the GPT header at `lba`.
+fn gpt_header(dev: &mut dyn BlockDevice, lba: u64) -> Result<GptHeader, String> {
+    let block = read(dev, lba, 1)?;
+    if &block[..8] != b"EFI PART" {
+        return Err(format!("No GPT header at block {}", lba));
+    }
+    let size = le32(&block, 12) as usize;
+    if !(GPT_HEADER_SIZE..=block.len()).contains(&size) {
+        return Err(format!("Bad GPT header size at block {}", lba));
+    }
+    let mut header = Vec::from(&block[..size]);
+    // The CRC covers the header with its own CRC field zeroed
+    header[16..20].fill(0);
+    if crc32(&header) != le32(&block, 16) {
+        return Err(format!("GPT header at block {} fails its CRC check", lba));
+    }
+    if le64(&block, 24) != lba {
+        return Err(format!("GPT header at block {} is misplaced", lba));
+    }
+    let entry_count = le32(&block, 80) as usize;
+    let entry_size = le32(&block, 84) as usize;
+    if entry_size < GPT_ENTRY_SIZE || !entry_size.is_multiple_of(8) || entry_count > GPT_MAX_ENTRIES
+    {
+        return Err(format!("Bad GPT entry layout at block {}", lba));
+    }
+    Ok(GptHeader {
+        alternate_lba: le64(&block, 32),
+        disk_guid: guid_at(&block, 56),
+        entries_lba: le64(&block, 72),
+        entry_count,
+        entry_size,
+        entries_crc: le32(&block, 88),
+    })
+}
+
+/// Read and check the entry array a header points to.
+fn gpt_partitions(dev: &mut dyn BlockDevice, header: &GptHeader) -> Result<Vec<Partition>, String> {
+    let bytes = header.entry_count * header.entry_size;
+    let blocks = bytes.div_ceil(dev.block_size());
+    let array = read(dev, header.entries_lba, blocks)?;
+    if crc32(&array[..bytes]) != header.entries_crc {
+        return Err(String::from("GPT partition entries fail their CRC check"));
+    }
+    let mut partitions = Vec::new();
+    for (i, entry) in array[..bytes].chunks_exact(header.entry_size).enumerate() {
+        let kind = guid_at(entry, 0);
+        if kind.is_zero() {
+            continue;
+        }
+        let (first_lba, last_lba) = (le64(entry, 32), le64(entry, 40));
+        if last_lba < first_lba || last_lba >= dev.block_count() {
+            return Err(format!("GPT partition {} lies outside the disk", i + 1));
+        }
+        // UTF-16 name, NUL-padded
+        let units = (0..36)
+            .map(|j| le16(entry, 56 + 2 * j))
+            .take_while(|&u| u != 0);
+        partitions.push(Partition {
+            number: i as u32 + 1,
+            first_lba,
+            last_lba,
+            type_name: gpt_type_name(kind),
+            uuid: Some(guid_at(entry, 16)),
+            label: char::decode_utf16(units)
+                .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                .collect(),
+        });
+    }
+    Ok(partitions)
+}
+
+/// Read the GPT from its primary header, falling back to the backup at the
+/// end of the disk.
+fn read_gpt(dev: &mut dyn BlockDevice) -> Result<Table, String> {
+    let last = dev.block_count().saturating_sub(1);
+    let primary = gpt_header(dev, 1);
+    if let Ok(header) = &primary {
+        if let Ok(partitions) = gpt_partitions(dev, header) {
+            return Ok(Table {
+                scheme: Scheme::Gpt,
+                disk_guid: Some(header.disk_guid),
+                partitions,
+                warning: None,
+            });
+        }
+    }
+    let backup_lba = match &primary {
+        Ok(header) if header.alternate_lba <= last => header.alternate_lba,
+        _ => last,
+    };
+    let header = gpt_header(dev, backup_lba)
+        .map_err(|e| format!("Damaged GPT, and the backup is unusable: {}", e))?;
+    let partitions = gpt_partitions(dev, &header)?;
+    Ok(Table {
+        scheme: Scheme::Gpt,
+        disk_guid: Some(header.disk_guid),
+        partitions,
+        warning: Some(String::from(
+            "The primary GPT is damaged; using the backup at the end of the disk",
+        )),
+    })
+}
+
+// ── MBR ──
+
+const MBR_GPT_PROTECTIVE: u8 = 0xEE;
+/// Logical partitions found by following EBRs, a bound against loops.
+const MBR_MAX_LOGICAL: u32 = 128;
+
+/// Names of common MBR partition types.
+const MBR_TYPES: &[(u8, &str)] = &[
+    (0x01, "FAT12"),
+    (0x04, "FAT16 <32M"),
+    (0x05, "Extended"),
+    (0x06, "FAT16"),
+    (0x07, "NTFS/exFAT"),
+    (0x0B, "FAT32"),
+    (0x0C, "FAT32 (LBA)"),
+    (0x0E, "FAT16 (LBA)"),
+    (0x0F, "Extended (LBA)"),
+    (0x27, "Windows recovery"),
+    (0x82, "Linux swap"),
+    (0x83, "Linux"),
+    (0x85, "Linux extended"),
+    (0x8E, "Linux LVM"),
+    (0xA5, "FreeBSD"),
+    (0xAF, "Apple HFS+"),
+    (0xEE, "GPT protective"),
+    (0xEF, "EFI System"),
+    (0xFD, "Linux RAID"),
+];
+
+fn mbr_type_name(kind: u8) -> String {
+    match MBR_TYPES.iter().find(|(known, _)| *known == kind) {
+        Some((_, name)) => format!("{} (0x{:02x})", name, kind),
+        None => format!("0x{:02x}", kind),
+    }
+}
+
+fn is_extended(kind: u8) -> bool {
+    matches!(kind, 0x05 | 0x0F | 0x85)
+}
+
+struct MbrEntry {
+    kind: u8,
+    /// Relative to the table's own base.
+    start: u64,
+    count: u64,
+}
+
+fn mbr_entries(sector: &[u8]) -> Vec<MbrEntry> {
+    (0..4)
+        .map(|i| {
+            let at = 446 + 16 * i;
+            MbrEntry {
+                kind: sector[at + 4],
+                start: le32(sector, at + 8) as u64,
+                count: le32(sector, at + 12) as u64,
+            }
+        })
+        .collect()
+}
+
+fn mbr_partition(number: u32, kind: u8, first_lba: u64, count: u64) -> Partition {
+    Partition {
+        number,
+        first_lba,
+        last_lba: first_lba + count - 1,
+        type_name: mbr_type_name(kind),
+        uuid: None,
+        label: String::new(),
+    }
+}
+
+/// The four primary partitions, then the logical ones in the chain of
+/// extended boot records.
+fn read_mbr(dev: &mut dyn BlockDevice, mbr: &[u8]) -> Result<Table, String> {
+    let blocks = dev.block_count();
+    let mut partitions = Vec::new();
+    // The first problem found is the one reported
+    let mut warning = None;
+    for (i, entry) in mbr_entries(mbr).into_iter().enumerate() {
+        if entry.kind == 0 || entry.count == 0 {
+            continue;
+        }
+        if entry.start + entry.count > blocks {
+            warning.get_or_insert(format!(
+                "Partition {} extends past the end of the disk",
+                i + 1
+            ));
+            continue;
+        }
+        if !is_extended(entry.kind) {
+            partitions.push(mbr_partition(
+                i as u32 + 1,
+                entry.kind,
+                entry.start,
+                entry.count,
+            ));
+            continue;
+        }
+
+        // Each EBR holds one logical partition, relative to the EBR, and a
+        // link to the next EBR, relative to the extended partition
+        let mut ebr_lba = entry.start;
+        let mut visited = Vec::new();
+        for number in 5..5 + MBR_MAX_LOGICAL {
+            if visited.contains(&ebr_lba) {
+                warning.get_or_insert(format!(
+                    "Logical partition chain loops at block {}",
+                    ebr_lba
+                ));
+                break;
+            }
+            visited.push(ebr_lba);
+            let ebr = read(dev, ebr_lba, 1).unwrap_or_default();
+            if ebr.len() < 512 || ebr[510] != 0x55 || ebr[511] != 0xAA {
+                warning.get_or_insert(format!(
+                    "Broken logical partition chain at block {}",
+                    ebr_lba
+                ));
+                break;
+            }
+            let links = mbr_entries(&ebr);
+            let logical = &links[0];
+            if logical.kind != 0 && logical.count != 0 {
+                let first = ebr_lba + logical.start;
+                if first + logical.count > blocks {
+                    warning.get_or_insert(format!(
+                        "Partition {} extends past the end of the disk",
+                        number
+                    ));
+                } else {
+                    partitions.push(mbr_partition(number, logical.kind, first, logical.count));
+                }
+            }
+            let next = &links[1];
+            if !is_extended(next.kind) || next.start == 0 {
+                break;
+            }
+            ebr_lba = entry.start + next.start;
+        }
+    }
+    Ok(Table {
+        scheme: Scheme::Mbr,
+        disk_guid: None,
+        partitions,
+        warning,
+    })
+}
+
+// ── Partitions as block devices ──
+
+/// One partition of a disk, addressed from its own first block.
+pub struct PartitionDevice {
+    disk: Box<dyn BlockDevice>,
+    first_lba: u64,
+    blocks: u64,
+}
+
+impl PartitionDevice {
+    pub fn new(disk: Box<dyn BlockDevice>, partition: &Partition) -> Self {
+        Self {
+            disk,
+            first_lba: partition.first_lba,
+            blocks: partition.blocks(),
+        }
+    }
+
+    fn check_range(&self, lba: u64, len: usize) -> Result<(), String> {
+        let count = (len / self.disk.block_size()) as u64;
+        if lba.checked_add(count).is_none_or(|end| end > self.blocks) {
+            return Err(format!("Block {} is beyond the end of the partition", lba));
+        }
+        Ok(())
+    }
+}
+
+impl BlockDevice for PartitionDevice {
+    fn block_size(&self) -> usize {
+        self.disk.block_size()
+    }
+
+    fn block_count(&self) -> u64 {
+        self.blocks
+    }
+
+    fn read_only(&self) -> bool {
+        self.disk.read_only()
+    }
+
+    fn read_blocks(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), String> {
+        self.check_range(lba, buf.len())?;
+        self.disk.read_blocks(self.first_lba + lba, buf)
+    }
+
+    fn write_blocks(&mut self, lba: u64, buf: &[u8]) -> Result<(), String> {
+        self.check_range(lba, buf.len())?;
+        self.disk.write_blocks(self.first_lba + lba, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EFI_SYSTEM: Guid = guid!("c12a7328-f81f-11d2-ba4b-00a0c93ec93b");
+    const LINUX: Guid = guid!("0fc63daf-8483-4772-8e79-3d69d8477de4");
+    const DISK_GUID: Guid = guid!("5ee1a3c4-1b2f-4a7e-9d3c-0123456789ab");
+
+    /// A disk in memory with 512-byte blocks.
+    struct MemDisk {
+        data: Vec<u8>,
+    }
+
+    impl MemDisk {
+        fn new(blocks: usize) -> Self {
+            Self {
+                data: vec![0; blocks * 512],
+            }
+        }
+
+        fn block(&mut self, lba: u64) -> &mut [u8] {
+            let at = lba as usize * 512;
+            &mut self.data[at..at + 512]
+        }
+    }
+
+    impl BlockDevice for MemDisk {
+        fn block_size(&self) -> usize {
+            512
+        }
+
+        fn block_count(&self) -> u64 {
+            (self.data.len() / 512) as u64
+        }
+
+        fn read_only(&self) -> bool {
+            false
+        }
+
+        fn read_blocks(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), String> {
+            let at = lba as usize * 512;
+            let block = self
+                .data
+                .get(at..at + buf.len())
+                .ok_or_else(|| format!("Cannot read block {}", lba))?;
+            buf.copy_from_slice(block);
+            Ok(())
+        }
+
+        fn write_blocks(&mut self, lba: u64, buf: &[u8]) -> Result<(), String> {
+            let at = lba as usize * 512;
+            self.data
+                .get_mut(at..at + buf.len())
+                .ok_or_else(|| format!("Cannot write block {}", lba))?
+                .copy_from_slice(buf);
+            Ok(())
+        }
+    }
+
+    /// Set MBR entry `index` of `sector` and its boot signature.
+    fn set_mbr_entry(sector: &mut [u8], index: usize, kind: u8, start: u32, count: u32) {
+        let at = 446 + 16 * index;
+        sector[at + 4] = kind;
+        sector[at + 8..at + 12].copy_from_slice(&start.to_le_bytes());
+        sector[at + 12..at + 16].copy_from_slice(&count.to_le_bytes());
+        sector[510] = 0x55;
+        sector[511] = 0xAA;
+    }
+
+    /// A 128-block GPT disk with an EFI system partition at 34..=63 and a
+    /// Linux one at 64..=94, with the backup table in the last 33 blocks.
+    fn gpt_disk() -> MemDisk {
+        let mut disk = MemDisk::new(128);
+        set_mbr_entry(disk.block(0), 0, MBR_GPT_PROTECTIVE, 1, 127);
+
+        let mut entries = vec![0u8; 128 * GPT_ENTRY_SIZE];
+        let parts = [(EFI_SYSTEM, 34u64, 63u64, "EFI"), (LINUX, 64, 94, "root")];
+        for (i, (kind, first, last, name)) in parts.into_iter().enumerate() {
+            let entry = &mut entries[i * GPT_ENTRY_SIZE..(i + 1) * GPT_ENTRY_SIZE];
+            entry[..16].copy_from_slice(&kind.to_bytes());
+            entry[16] = i as u8 + 1;
+            entry[32..40].copy_from_slice(&first.to_le_bytes());
+            entry[40..48].copy_from_slice(&last.to_le_bytes());
+            for (j, unit) in name.encode_utf16().enumerate() {
+                entry[56 + 2 * j..58 + 2 * j].copy_from_slice(&unit.to_le_bytes());
+            }
+        }
+
+        for (header_lba, alternate, entries_lba) in [(1u64, 127u64, 2u64), (127, 1, 95)] {
+            disk.write_blocks(entries_lba, &entries).unwrap();
+            let header = disk.block(header_lba);
+            header[..8].copy_from_slice(b"EFI PART");
+            header[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+            header[12..16].copy_from_slice(&(GPT_HEADER_SIZE as u32).to_le_bytes());
+            header[24..32].copy_from_slice(&header_lba.to_le_bytes());
+            header[32..40].copy_from_slice(&alternate.to_le_bytes());
+            header[40..48].copy_from_slice(&34u64.to_le_bytes());
+            header[48..56].copy_from_slice(&94u64.to_le_bytes());
+            header[56..72].copy_from_slice(&DISK_GUID.to_bytes());
+            header[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+            header[80..84].copy_from_slice(&128u32.to_le_bytes());
+            header[84..88].copy_from_slice(&(GPT_ENTRY_SIZE as u32).to_le_bytes());
+            header[88..92].copy_from_slice(&crc32(&entries).to_le_bytes());
+            let crc = crc32(&header[..GPT_HEADER_SIZE]);
+            header[16..20].copy_from_slice(&crc.to_le_bytes());
+        }
+        disk
+    }
+
+    fn check_gpt_partitions(table: &Table) {
+        assert_eq!(table.scheme, Scheme::Gpt);
+        assert_eq!(table.disk_guid, Some(DISK_GUID));
+        let found: Vec<(u32, u64, u64, &str, &str)> = table
+            .partitions
+            .iter()
+            .map(|p| {
+                let (type_name, label) = (p.type_name.as_str(), p.label.as_str());
+                (p.number, p.first_lba, p.last_lba, type_name, label)
+            })
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (1, 34, 63, "EFI System", "EFI"),
+                (2, 64, 94, "Linux filesystem", "root"),
+            ]
+        );
+        assert_eq!(table.partitions[1].blocks(), 31);
+    }
+
+    #[test]
+    fn crc32_matches_the_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+    }
+
+    #[test]
+    fn gpt_is_read_behind_a_protective_mbr() {
+        let table = read_table(&mut gpt_disk()).unwrap();
+        check_gpt_partitions(&table);
+        assert!(table.warning.is_none());
+    }
+
+    #[test]
+    fn a_damaged_primary_gpt_falls_back_to_the_backup() {
+        let mut disk = gpt_disk();
+        // Breaks the primary header's CRC
+        disk.block(1)[60] ^= 0xFF;
+        let table = read_table(&mut disk).unwrap();
+        check_gpt_partitions(&table);
+        assert!(table.warning.is_some());
+
+        // A damaged primary entry array does too
+        let mut disk = gpt_disk();
+        disk.block(2)[40] ^= 0xFF;
+        let table = read_table(&mut disk).unwrap();
+        check_gpt_partitions(&table);
+        assert!(table.warning.is_some());
+
+        disk.block(127)[60] ^= 0xFF;
+        assert!(read_table(&mut disk).is_err());
+    }
+
+    #[test]
+    fn logical_partitions_follow_the_ebr_chain() {
+        let mut disk = MemDisk::new(256);
+        set_mbr_entry(disk.block(0), 0, 0x0C, 1, 99);
+        set_mbr_entry(disk.block(0), 1, 0x0F, 100, 156);
+        // Logical partitions are relative to their EBR, links to the
+        // extended partition
+        set_mbr_entry(disk.block(100), 0, 0x83, 1, 10);
+        set_mbr_entry(disk.block(100), 1, 0x05, 20, 30);
+        set_mbr_entry(disk.block(120), 0, 0x07, 2, 5);
+
+        let table = read_table(&mut disk).unwrap();
+        assert_eq!(table.scheme, Scheme::Mbr);
+        let found: Vec<(u32, u64, u64, &str)> = table
+            .partitions
+            .iter()
+            .map(|p| (p.number, p.first_lba, p.last_lba, p.type_name.as_str()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (1, 1, 99, "FAT32 (LBA) (0x0c)"),
+                (5, 101, 110, "Linux (0x83)"),
+                (6, 122, 126, "NTFS/exFAT (0x07)"),
+            ]
+        );
+        assert!(table.warning.is_none());
+
+        // A link back to the same EBR ends the chain
+        set_mbr_entry(disk.block(120), 1, 0x05, 20, 30);
+        let table = read_table(&mut disk).unwrap();
+        assert_eq!(table.partitions.len(), 3);
+        assert_eq!(
+            table.warning.as_deref(),
+            Some("Logical partition chain loops at block 120")
+        );
+    }
+
+    #[test]
+    fn partition_devices_stay_within_the_partition() {
+        let mut disk = MemDisk::new(16);
+        for lba in 0..16 {
+            disk.block(lba).fill(lba as u8);
+        }
+        let partition = mbr_partition(1, 0x0C, 4, 4);
+        let mut dev = PartitionDevice::new(Box::new(disk), &partition);
+        assert_eq!(dev.block_count(), 4);
+
+        let mut buf = vec![0u8; 1024];
+        dev.read_blocks(2, &mut buf).unwrap();
+        assert_eq!((buf[0], buf[1023]), (6, 7));
+        assert!(dev.read_blocks(3, &mut buf).is_err());
+        assert!(dev.read_blocks(4, &mut buf[..512]).is_err());
+        assert!(dev.read_blocks(u64::MAX, &mut buf[..512]).is_err());
+
+        dev.write_blocks(0, &[0xAB; 512]).unwrap();
+        dev.read_blocks(0, &mut buf[..512]).unwrap();
+        assert_eq!(buf[0], 0xAB);
+        assert!(dev.write_blocks(3, &buf).is_err());
+    }
+}
@@ -0,0 +1,204 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use uefi::boot::{self, OpenProtocolAttributes, OpenProtocolParams, ScopedProtocol, SearchType};
+use uefi::proto::media::block::BlockIO;
+use uefi::{Handle, Identify};
+
+use super::BlockDevice;
+use crate::vfs::uefi_fs::device_path_text;
+
+/// Open `BlockIO` without taking it away from the drivers (partitions,
+/// file systems) already stacked on it.
+fn open_block_io(handle: Handle) -> Result<ScopedProtocol<BlockIO>, String> {
+    unsafe {
+        boot::open_protocol::<BlockIO>(
+            OpenProtocolParams {
+                handle,
+                agent: boot::image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+    }
+    .map_err(|_| String::from("Cannot open the block device"))
+}
+
+/// Every `BlockIO` handle for a whole disk, in firmware order; handle `N`
+/// is `diskN`. The partitions firmware exposes as block devices of their
+/// own are left out.
+fn disk_handles() -> Result<Vec<Handle>, String> {
+    let handles = boot::locate_handle_buffer(SearchType::ByProtocol(&BlockIO::GUID))
+        .map_err(|_| String::from("No block devices found"))?;
+    Ok(handles
+        .iter()
+        .copied()
+        .filter(|&handle| {
+            open_block_io(handle).is_ok_and(|block| !block.media().is_logical_partition())
+        })
+        .collect())
+}
+
+fn disk_handle(name: &str) -> Result<Handle, String> {
+    let index = name
+        .strip_prefix("disk")
+        .and_then(|n| n.parse::<usize>().ok())
+        .ok_or_else(|| format!("Invalid disk name '{}' (see disks)", name))?;
+    disk_handles()?
+        .get(index)
+        .copied()
+        .ok_or_else(|| format!("No disk '{}' (see disks)", name))
+}
+
+/// A disk as listed by `disks`.
+pub struct Disk {
+    /// `diskN`, as accepted by `readblk`.
+    pub name: String,
+    /// Changes whenever removable media is swapped.
+    pub media_id: u32,
+    pub block_size: usize,
+    pub blocks: u64,
+    pub removable: bool,
+    pub present: bool,
+    pub read_only: bool,
+    pub device: String,
+}
+
+/// Describe every whole disk.
+pub fn disks() -> Result<Vec<Disk>, String> {
+    let mut list = Vec::new();
+    for (index, handle) in disk_handles()?.into_iter().enumerate() {
+        let block = open_block_io(handle)?;
+        let media = block.media();
+        list.push(Disk {
+            name: format!("disk{}", index),
+            media_id: media.media_id(),
+            block_size: media.block_size() as usize,
+            blocks: media.last_block() + 1,
+            removable: media.is_removable_media(),
+            present: media.is_media_present(),
+            read_only: media.is_read_only(),
+            device: device_path_text(handle),
+        });
+    }
+    Ok(list)
+}
+
+/// A disk read and written through the firmware's `BlockIO` protocol.
+pub struct UefiDisk {
+    handle: Handle,
+    /// For error messages.
+    name: String,
+    media_id: u32,
+    block_size: usize,
+    blocks: u64,
+    read_only: bool,
+    /// Buffer alignment the controller needs; 0 or 1 for none.
+    io_align: usize,
+}
+
+// SAFETY: boot services run on a single processor, and the handle is only
+// used through them.
+unsafe impl Send for UefiDisk {}
+
+impl UefiDisk {
+    /// Open the disk named `diskN`.
+    pub fn open(name: &str) -> Result<Self, String> {
+        let handle = disk_handle(name)?;
+        let block = open_block_io(handle)?;
+        let media = block.media();
+        if !media.is_media_present() {
+            return Err(format!("No media in '{}'", name));
+        }
+        Ok(Self {
+            handle,
+            name: String::from(name),
+            media_id: media.media_id(),
+            block_size: media.block_size() as usize,
+            blocks: media.last_block() + 1,
+            read_only: media.is_read_only(),
+            io_align: media.io_align() as usize,
+        })
+    }
+
+    fn check_range(&self, lba: u64, len: usize) -> Result<(), String> {
+        if !len.is_multiple_of(self.block_size) {
+            return Err(format!("Transfer of {} bytes is not whole blocks", len));
+        }
+        let count = (len / self.block_size) as u64;
+        if lba.checked_add(count).is_none_or(|end| end > self.blocks) {
+            return Err(format!(
+                "Block {} is beyond the end of '{}'",
+                lba, self.name
+            ));
+        }
+        Ok(())
+    }
+
+    /// A zeroed buffer of `len` bytes meeting the controller's alignment:
+    /// the storage and the offset the buffer starts at.
+    fn bounce_buffer(&self, len: usize) -> (Vec<u8>, usize) {
+        let align = self.io_align.max(1);
+        let storage = vec![0u8; len + align];
+        let offset = storage.as_ptr().align_offset(align);
+        (storage, offset)
+    }
+
+    fn is_aligned(&self, buf: &[u8]) -> bool {
+        self.io_align <= 1 || buf.as_ptr().align_offset(self.io_align) == 0
+    }
+}
+
+impl BlockDevice for UefiDisk {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn block_count(&self) -> u64 {
+        self.blocks
+    }
+
+    fn read_only(&self) -> bool {
+        self.read_only
+    }
+
+    fn read_blocks(&mut self, lba: u64, buf: &mut [u8]) -> Result<(), String> {
+        self.check_range(lba, buf.len())?;
+        let block = open_block_io(self.handle)?;
+        let fail = |_| format!("Cannot read block {} of '{}'", lba, self.name);
+        if self.is_aligned(buf) {
+            return block.read_blocks(self.media_id, lba, buf).map_err(fail);
+        }
+        let (mut storage, offset) = self.bounce_buffer(buf.len());
+        let aligned = &mut storage[offset..offset + buf.len()];
+        block
+            .read_blocks(self.media_id, lba, aligned)
+            .map_err(fail)?;
+        buf.copy_from_slice(aligned);
+        Ok(())
+    }
+
+    fn write_blocks(&mut self, lba: u64, buf: &[u8]) -> Result<(), String> {
+        if self.read_only {
+            return Err(format!("'{}' is read-only", self.name));
+        }
+        self.check_range(lba, buf.len())?;
+        let mut block = open_block_io(self.handle)?;
+        let fail = |_| format!("Cannot write block {} of '{}'", lba, self.name);
+        if self.is_aligned(buf) {
+            block.write_blocks(self.media_id, lba, buf).map_err(fail)?;
+        } else {
+            let (mut storage, offset) = self.bounce_buffer(buf.len());
+            let aligned = &mut storage[offset..offset + buf.len()];
+            aligned.copy_from_slice(buf);
+            block
+                .write_blocks(self.media_id, lba, aligned)
+                .map_err(fail)?;
+        }
+        // Writes go straight through, as the FAT driver expects
+        block.flush_blocks().map_err(fail)
+    }
+}
@@ -0,0 +1,463 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use uefi::boot::{self, LoadImageSource, SearchType};
+use uefi::proto::device_path::build::{self, DevicePathBuilder};
+use uefi::proto::device_path::text::{AllowShortcuts, DisplayOnly};
+use uefi::proto::device_path::{DevicePath, DevicePathNode, DeviceSubType, DeviceType};
+use uefi::proto::media::fs::SimpleFileSystem;
+use uefi::proto::BootPolicy;
+use uefi::runtime::{self, VariableAttributes, VariableVendor};
+use uefi::{cstr16, CString16, Handle, Identify, Status};
+
+// ── Load options ──
+
+/// `LOAD_OPTION_ACTIVE`: the firmware may boot the option.
+const LOAD_OPTION_ACTIVE: u32 = 0x1;
+/// `LOAD_OPTION_HIDDEN`: the firmware's own boot menu leaves it out.
+const LOAD_OPTION_HIDDEN: u32 = 0x8;
+
+/// Size of the end node closing a device path.
+const END_NODE_SIZE: usize = 4;
+
+/// Where removable media keep their loader when an option names only the
+/// disk or volume.
+#[cfg(target_arch = "x86_64")]
+const DEFAULT_LOADER: &uefi::CStr16 = cstr16!("\\EFI\\BOOT\\BOOTX64.EFI");
+#[cfg(target_arch = "aarch64")]
+const DEFAULT_LOADER: &uefi::CStr16 = cstr16!("\\EFI\\BOOT\\BOOTAA64.EFI");
+
+/// A `Boot####` variable: an `EFI_LOAD_OPTION`.
+pub struct LoadOption {
+    pub number: u16,
+    pub attributes: u32,
+    pub description: String,
+    /// The first device path of the option's file path list, which names
+    /// the loader.
+    file_path: Vec<u8>,
+    /// Passed to the loader as its load options.
+    pub optional_data: Vec<u8>,
+}
+
+impl LoadOption {
+    /// Decode the contents of the variable `Boot{number:04X}`.
+    pub fn parse(number: u16, data: &[u8]) -> Result<Self, String> {
+        let malformed = || format!("{} is malformed", option_name(number));
+        if data.len() < 6 {
+            return Err(malformed());
+        }
+        let attributes = u32::from_le_bytes([data[0], data[1], data[2], data[3]]);
+        let path_len = u16::from_le_bytes([data[4], data[5]]) as usize;
+
+        // The description is a NUL-terminated UCS-2 string
+        let mut units = Vec::new();
+        let mut at = 6;
+        loop {
+            let unit = data.get(at..at + 2).ok_or_else(malformed)?;
+            at += 2;
+            match u16::from_le_bytes([unit[0], unit[1]]) {
+                0 => break,
+                unit => units.push(unit),
+            }
+        }
+        let file_paths = data.get(at..at + path_len).ok_or_else(malformed)?;
+        if !is_device_path(file_paths) {
+            return Err(malformed());
+        }
+        let first = <&DevicePath>::try_from(file_paths).map_err(|_| malformed())?;
+        Ok(Self {
+            number,
+            attributes,
+            description: String::from_utf16_lossy(&units),
+            file_path: first.as_bytes().to_vec(),
+            optional_data: data[at + path_len..].to_vec(),
+        })
+    }
+
+    /// `Boot####`.
+    pub fn name(&self) -> String {
+        option_name(self.number)
+    }
+
+    pub fn active(&self) -> bool {
+        self.attributes & LOAD_OPTION_ACTIVE != 0
+    }
+
+    pub fn hidden(&self) -> bool {
+        self.attributes & LOAD_OPTION_HIDDEN != 0
+    }
+
+    pub fn device_path(&self) -> Option<&DevicePath> {
+        <&DevicePath>::try_from(self.file_path.as_slice()).ok()
+    }
+
+    /// The loader's device path as text, e.g.
+    /// `HD(1,GPT,...)/\EFI\ubuntu\shimx64.efi`.
+    pub fn device_text(&self) -> String {
+        self.device_path()
+            .and_then(|path| path.to_string(DisplayOnly(true), AllowShortcuts(true)).ok())
+            .map(|text| String::from(&text))
+            .unwrap_or_default()
+    }
+}
+
+/// True if `bytes` start with a whole device path: nodes of at least a
+/// header's length up to an end-of-path node. The `uefi` crate trusts node
+/// lengths, and a corrupt variable must not make it panic.
+fn is_device_path(bytes: &[u8]) -> bool {
+    let mut at = 0;
+    while let Some(header) = bytes.get(at..at + END_NODE_SIZE) {
+        let len = u16::from_le_bytes([header[2], header[3]]) as usize;
+        if len < END_NODE_SIZE || at + len > bytes.len() {
+            return false;
+        }
+        if header[0] == 0x7F && header[1] == 0xFF {
+            return true;
+        }
+        at += len;
+    }
+    false
+}
+
+fn option_name(number: u16) -> String {
+    format!("Boot{:04X}", number)
+}
+
+/// Parse a load option number as `0003` or `Boot0003`.
+pub fn parse_option_number(text: &str) -> Result<u16, String> {
+    let digits = match text.get(..4) {
+        Some(prefix) if prefix.eq_ignore_ascii_case("boot") => &text[4..],
+        _ => text,
+    };
+    let valid = (1..=4).contains(&digits.len()) && digits.bytes().all(|b| b.is_ascii_hexdigit());
+    valid
+        .then(|| u16::from_str_radix(digits, 16).ok())
+        .flatten()
+        .ok_or_else(|| format!("Invalid boot option '{}' (see bootorder)", text))
+}
+
+// ── Variables ──
+
+/// Attributes of the boot manager's variables.
+fn boot_attributes() -> VariableAttributes {
+    VariableAttributes::NON_VOLATILE
+        | VariableAttributes::BOOTSERVICE_ACCESS
+        | VariableAttributes::RUNTIME_ACCESS
+}
+
+/// Read a global variable; `None` if it is not set.
+fn read_var(name: &str) -> Result<Option<Vec<u8>>, String> {
+    let name16 = CString16::try_from(name).map_err(|_| String::from("Invalid variable name"))?;
+    match runtime::get_variable_boxed(&name16, &VariableVendor::GLOBAL_VARIABLE) {
+        Ok((data, _)) => Ok(Some(data.into_vec())),
+        Err(e) if e.status() == Status::NOT_FOUND => Ok(None),
+        Err(e) => Err(format!("Cannot read {}: {}", name, e.status())),
+    }
+}
+
+fn write_var(name: &str, data: &[u8]) -> Result<(), String> {
+    let name16 = CString16::try_from(name).map_err(|_| String::from("Invalid variable name"))?;
+    runtime::set_variable(
+        &name16,
+        &VariableVendor::GLOBAL_VARIABLE,
+        boot_attributes(),
+        data,
+    )
+    .map_err(|e| format!("Cannot set {}: {}", name, e.status()))
+}
+
+fn read_u16s(name: &str) -> Result<Vec<u16>, String> {
+    Ok(read_var(name)?
+        .unwrap_or_default()
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect())
+}
+
+fn u16_bytes(values: &[u16]) -> Vec<u8> {
+    values.iter().flat_map(|v| v.to_le_bytes()).collect()
+}
+
+/// The option numbers in `BootOrder`.
+pub fn boot_order() -> Result<Vec<u16>, String> {
+    read_u16s("BootOrder")
+}
+
+pub fn set_boot_order(order: &[u16]) -> Result<(), String> {
+    write_var("BootOrder", &u16_bytes(order))
+}
+
+/// The option booted this time, from `BootCurrent`.
+pub fn boot_current() -> Option<u16> {
+    read_u16s("BootCurrent").ok()?.first().copied()
+}
+
+/// The option the firmware boots once at the next start, from `BootNext`.
+pub fn boot_next() -> Option<u16> {
+    read_u16s("BootNext").ok()?.first().copied()
+}
+
+/// Set `BootNext`, or delete it with `None`.
+pub fn set_boot_next(number: Option<u16>) -> Result<(), String> {
+    match number {
+        Some(number) => write_var("BootNext", &number.to_le_bytes()),
+        None => {
+            match runtime::delete_variable(cstr16!("BootNext"), &VariableVendor::GLOBAL_VARIABLE) {
+                Ok(()) => Ok(()),
+                Err(e) if e.status() == Status::NOT_FOUND => Ok(()),
+                Err(e) => Err(format!("Cannot delete BootNext: {}", e.status())),
+            }
+        }
+    }
+}
+
+/// Read and decode `Boot####`.
+pub fn load_option(number: u16) -> Result<LoadOption, String> {
+    let name = option_name(number);
+    let data = read_var(&name)?.ok_or_else(|| format!("No boot option {}", name))?;
+    LoadOption::parse(number, &data)
+}
+
+/// Every `Boot####` variable: those in `BootOrder` in its order, then the
+/// rest by number. Options that cannot be decoded are skipped.
+pub fn load_options() -> Result<Vec<LoadOption>, String> {
+    let mut numbers = boot_order()?;
+    let mut others: Vec<u16> = runtime::variable_keys()
+        .flatten()
+        .filter(|key| key.vendor == VariableVendor::GLOBAL_VARIABLE)
+        .filter_map(|key| {
+            let name = String::from(&key.name);
+            let digits = name.strip_prefix("Boot")?;
+            if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            u16::from_str_radix(digits, 16).ok()
+        })
+        .filter(|number| !numbers.contains(number))
+        .collect();
+    others.sort_unstable();
+    numbers.extend(others);
+    Ok(numbers
+        .into_iter()
+        .filter_map(|number| load_option(number).ok())
+        .collect())
+}
+
+// ── Chainloading ──
+
+fn device_path_of(handle: Handle) -> Option<Vec<u8>> {
+    boot::open_protocol_exclusive::<DevicePath>(handle)
+        .ok()
+        .map(|path| path.as_bytes().to_vec())
+}
+
+/// Device paths of every volume the firmware can read files from.
+fn volume_paths() -> Vec<Vec<u8>> {
+    boot::locate_handle_buffer(SearchType::ByProtocol(&SimpleFileSystem::GUID))
+        .map(|handles| handles.iter().filter_map(|&h| device_path_of(h)).collect())
+        .unwrap_or_default()
+}
+
+fn same_node(a: &DevicePathNode, b: &DevicePathNode) -> bool {
+    a.full_type() == b.full_type() && a.data() == b.data()
+}
+
+/// Complete a short-form path that starts at a partition (`HD(...)/File`)
+/// with the path of the volume on that partition.
+fn expand_partition(path: &DevicePath) -> Option<Vec<u8>> {
+    let first = path.node_iter().next()?;
+    if first.full_type() != (DeviceType::MEDIA, DeviceSubType::MEDIA_HARD_DRIVE) {
+        return None;
+    }
+    volume_paths().into_iter().find_map(|volume| {
+        let volume_path = <&DevicePath>::try_from(volume.as_slice()).ok()?;
+        let mut prefix = 0;
+        for node in volume_path.node_iter() {
+            prefix += node.length() as usize;
+            if same_node(node, first) {
+                let mut full = volume[..prefix].to_vec();
+                full.extend_from_slice(&path.as_bytes()[first.length() as usize..]);
+                return Some(full);
+            }
+        }
+        None
+    })
+}
+
+fn load_path(path: &[u8]) -> Result<Handle, Status> {
+    let device_path = <&DevicePath>::try_from(path).map_err(|_| Status::INVALID_PARAMETER)?;
+    boot::load_image(
+        boot::image_handle(),
+        LoadImageSource::FromDevicePath {
+            device_path,
+            boot_policy: BootPolicy::BootSelection,
+        },
+    )
+    .map_err(|e| e.status())
+}
+
+/// `volume` with the default loader's file path appended.
+fn default_loader(volume: &[u8]) -> Option<Vec<u8>> {
+    let volume_path = <&DevicePath>::try_from(volume).ok()?;
+    let mut storage = Vec::new();
+    let mut builder = DevicePathBuilder::with_vec(&mut storage);
+    for node in volume_path.node_iter() {
+        builder = builder.push(&node).ok()?;
+    }
+    builder
+        .push(&build::media::FilePath {
+            path_name: DEFAULT_LOADER,
+        })
+        .ok()?
+        .finalize()
+        .ok()?;
+    Some(storage)
+}
+
+/// True if the volume's device path starts with `device`, the nodes an
+/// option names before its end node. An option that names no device at
+/// all matches no volume.
+fn is_on_device(volume: &[u8], device: &[u8]) -> bool {
+    !device.is_empty() && volume.starts_with(device)
+}
+
+/// Load the option's loader much as the firmware's boot manager would:
+/// from its device path, completing a short-form `HD(...)` path, and from
+/// the default loader path of removable media when the option names a
+/// disk or volume rather than a file.
+pub fn load(option: &LoadOption) -> Result<Handle, String> {
+    let path = option
+        .device_path()
+        .ok_or_else(|| format!("{} has no device path", option.name()))?;
+    let full = expand_partition(path).unwrap_or_else(|| path.as_bytes().to_vec());
+    let status = match load_path(&full) {
+        Ok(image) => return Ok(image),
+        Err(status) => status,
+    };
+
+    let device = &full[..full.len().saturating_sub(END_NODE_SIZE)];
+    for volume in volume_paths() {
+        if !is_on_device(&volume, device) {
+            continue;
+        }
+        if let Some(image) = default_loader(&volume).and_then(|p| load_path(&p).ok()) {
+            return Ok(image);
+        }
+    }
+    Err(format!(
+        "Cannot load {} ({}); try bootnext {:04X} and reboot",
+        option.name(),
+        status,
+        option.number
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Little-endian UCS-2 with a NUL.
+    fn ucs2(text: &str) -> Vec<u8> {
+        text.encode_utf16()
+            .chain([0])
+            .flat_map(|unit| unit.to_le_bytes())
+            .collect()
+    }
+
+    /// A device path of one file path node naming `file`.
+    fn file_path(file: &str) -> Vec<u8> {
+        let name = ucs2(file);
+        let mut path = vec![0x04, 0x04];
+        path.extend_from_slice(&(4 + name.len() as u16).to_le_bytes());
+        path.extend_from_slice(&name);
+        path.extend_from_slice(&[0x7F, 0xFF, 0x04, 0x00]);
+        path
+    }
+
+    /// An `EFI_LOAD_OPTION` with the given description, file path list and
+    /// optional data.
+    fn load_option(description: &[u8], paths: &[u8], optional_data: &[u8]) -> Vec<u8> {
+        let mut data = Vec::from((LOAD_OPTION_ACTIVE | LOAD_OPTION_HIDDEN).to_le_bytes());
+        data.extend_from_slice(&(paths.len() as u16).to_le_bytes());
+        data.extend_from_slice(description);
+        data.extend_from_slice(paths);
+        data.extend_from_slice(optional_data);
+        data
+    }
+
+    #[test]
+    fn load_options_are_parsed() {
+        let path = file_path("\\EFI\\vos\\vos.efi");
+        let data = load_option(&ucs2("VOS"), &path, b"-v\0");
+        let option = LoadOption::parse(3, &data).unwrap();
+        assert_eq!(option.name(), "Boot0003");
+        assert_eq!(option.description, "VOS");
+        assert!(option.active() && option.hidden());
+        assert_eq!(option.file_path, path);
+        assert_eq!(option.device_path().unwrap().node_iter().count(), 1);
+        assert_eq!(option.optional_data, b"-v\0");
+
+        let option = LoadOption::parse(0x10, &load_option(&ucs2(""), &path, &[])).unwrap();
+        assert_eq!(option.description, "");
+        assert!(option.optional_data.is_empty());
+    }
+
+    #[test]
+    fn malformed_load_options_are_refused() {
+        let path = file_path("\\a.efi");
+        let malformed = Err(String::from("Boot0001 is malformed"));
+        let parse = |data: &[u8]| LoadOption::parse(1, data).map(|o| o.description);
+        assert_eq!(parse(&[1, 0, 0, 0, 0]), malformed);
+
+        // No NUL after the description
+        let mut data = load_option(b"V\0O\0S\0", &[], &[]);
+        assert_eq!(parse(&data), malformed);
+        data.push(0);
+        assert_eq!(parse(&data), malformed);
+
+        // FilePathListLength past the end of the variable
+        let mut data = load_option(&ucs2("VOS"), &path, &[]);
+        data[4..6].copy_from_slice(&(path.len() as u16 + 1).to_le_bytes());
+        assert_eq!(parse(&data), malformed);
+
+        // File path lists that are not device paths
+        assert_eq!(parse(&load_option(&ucs2("VOS"), &[0; 4], &[])), malformed);
+        assert_eq!(
+            parse(&load_option(&ucs2("VOS"), &path[..path.len() - 4], &[])),
+            malformed
+        );
+        let mut long_node = path.clone();
+        long_node[2] = 0xFF;
+        assert_eq!(
+            parse(&load_option(&ucs2("VOS"), &long_node, &[])),
+            malformed
+        );
+    }
+
+    #[test]
+    fn option_numbers() {
+        assert_eq!(parse_option_number("Boot0003"), Ok(3));
+        assert_eq!(parse_option_number("boot001A"), Ok(0x1A));
+        assert_eq!(parse_option_number("0003"), Ok(3));
+        assert_eq!(parse_option_number("3"), Ok(3));
+        assert_eq!(parse_option_number("ffff"), Ok(0xFFFF));
+        for text in ["boot000g", "Boot", "", "10000", "Boot00003", "+3", "Boot 3"] {
+            assert_eq!(
+                parse_option_number(text),
+                Err(format!("Invalid boot option '{}' (see bootorder)", text))
+            );
+        }
+    }
+
+    #[test]
+    fn only_the_named_device_falls_back_to_its_volumes() {
+        let volume = [0x02, 0x01, 0x0C, 0x00, 1, 2, 3, 4, 5, 6, 7, 8];
+        assert!(is_on_device(&volume, &volume[..4]));
+        assert!(is_on_device(&volume, &volume));
+        assert!(!is_on_device(&volume, &[0x02, 0x01, 0x0C, 0x01]));
+        // An option whose path is only the end node names no device
+        assert!(!is_on_device(&volume, &[]));
+    }
+}
@@ -0,0 +1,145 @@
+extern crate alloc;
+
+use alloc::string::String;
+use core::fmt::Write;
+use uefi::proto::console::text::Color;
+use uefi::system;
+
+/// Semantic output styles; each console maps them to its own colors.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Style {
+    Normal,
+    Error,
+    Prompt,
+    Banner,
+}
+
+/// A character output device commands can write to.
+pub trait Console: Write {
+    fn set_style(&mut self, style: Style);
+
+    fn clear(&mut self);
+
+    /// Make pending output visible (e.g. redraw the GUI terminal).
+    fn flush(&mut self) {}
+
+    /// Size in character cells as `(columns, rows)`.
+    fn size(&self) -> (usize, usize) {
+        (80, 25)
+    }
+
+    /// Cursor position as `(column, row)`.
+    fn cursor(&self) -> (usize, usize) {
+        (0, 0)
+    }
+
+    fn set_cursor(&mut self, _col: usize, _row: usize) {}
+
+    /// False for consoles that capture output instead of showing it, where
+    /// paging makes no sense.
+    fn is_terminal(&self) -> bool {
+        true
+    }
+
+    /// Hand the screen over to another program, such as one run by `exec`.
+    fn release_screen(&mut self) {}
+
+    /// Take the screen back after `release_screen` and repaint it.
+    fn restore_screen(&mut self) {}
+
+    /// Write `s` in `style`, then switch back to `Style::Normal`.
+    fn write_styled(&mut self, style: Style, s: &str) {
+        self.set_style(style);
+        let _ = self.write_str(s);
+        self.set_style(Style::Normal);
+    }
+}
+
+/// The UEFI text console (`ConOut`).
+pub struct TextConsole;
+
+impl TextConsole {
+    fn colors(style: Style) -> (Color, Color) {
+        match style {
+            Style::Normal => (Color::White, Color::Black),
+            Style::Error => (Color::Red, Color::Black),
+            Style::Prompt => (Color::LightGreen, Color::Black),
+            Style::Banner => (Color::LightCyan, Color::Black),
+        }
+    }
+}
+
+impl Write for TextConsole {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        system::with_stdout(|stdout| stdout.write_str(s))
+    }
+}
+
+impl Console for TextConsole {
+    fn set_style(&mut self, style: Style) {
+        let (fg, bg) = Self::colors(style);
+        system::with_stdout(|stdout| {
+            let _ = stdout.set_color(fg, bg);
+        });
+    }
+
+    fn clear(&mut self) {
+        system::with_stdout(|stdout| {
+            let _ = stdout.clear();
+        });
+    }
+
+    fn size(&self) -> (usize, usize) {
+        system::with_stdout(|stdout| match stdout.current_mode() {
+            Ok(Some(mode)) => (mode.columns(), mode.rows()),
+            _ => (80, 25),
+        })
+    }
+
+    fn cursor(&self) -> (usize, usize) {
+        system::with_stdout(|stdout| stdout.cursor_position())
+    }
+
+    fn set_cursor(&mut self, col: usize, row: usize) {
+        system::with_stdout(|stdout| {
+            let _ = stdout.set_cursor_position(col, row);
+        });
+    }
+
+    /// The other program may have left its colors behind or hidden the cursor.
+    fn restore_screen(&mut self) {
+        self.set_style(Style::Normal);
+        system::with_stdout(|stdout| {
+            let _ = stdout.enable_cursor(true);
+        });
+    }
+}
+
+/// Collects output in memory, e.g. to feed it into the next command of a pipeline.
+#[derive(Default)]
+pub struct BufferConsole {
+    pub text: String,
+}
+
+impl BufferConsole {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Write for BufferConsole {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.text.push_str(s);
+        Ok(())
+    }
+}
+
+impl Console for BufferConsole {
+    fn set_style(&mut self, _style: Style) {}
+
+    fn clear(&mut self) {}
+
+    fn is_terminal(&self) -> bool {
+        false
+    }
+}
@@ -1,142 +1,777 @@
 extern crate alloc;
 
+use alloc::boxed::Box;
 use alloc::format;
-use alloc::string::String;
+use alloc::string::{String, ToString};
 use alloc::vec;
-use core::fmt::Write;
+use alloc::vec::Vec;
 use uefi::boot;
-use uefi::proto::media::file::{Directory, File, FileAttribute, FileMode};
-use uefi::CString16;
+use uefi::proto::device_path::DevicePathNodeEnum;
+use uefi::proto::loaded_image::LoadedImage;
+use uefi::proto::media::file::FileAttribute;
+use uefi::runtime::{self, Time};
 
-fn open_volume() -> Result<Directory, String> {
-    let handle = boot::image_handle();
-    let mut fs =
-        boot::get_image_file_system(handle).map_err(|_| String::from("Failed to open FS"))?;
-    fs.open_volume()
-        .map_err(|_| String::from("Failed to open volume"))
+use crate::block::image::ImageDisk;
+use crate::block::{self, BlockDevice};
+use crate::console::Console;
+use crate::path;
+use crate::shell::Shell;
+use crate::vfs::fat::FatFs;
+use crate::vfs::{self, FileSystem, OpenFile, OpenMode};
+
+/// `path` as a path on the boot volume, without any `fsN:` prefix naming
+/// it, or `None` if it is on another volume.
+fn on_boot_volume(path: &str) -> Option<String> {
+    match path::split_volume(path) {
+        (None, _) => Some(path::normalize(path)),
+        (Some(name), rest) => vfs::uefi_fs::is_boot_volume(name).then(|| path::normalize(rest)),
+    }
 }
 
-fn to_uefi_path(path: &str) -> Result<CString16, String> {
-    let converted: String = path.replace('/', "\\");
-    CString16::try_from(converted.as_str()).map_err(|_| String::from("Invalid path"))
+/// Read a whole file.
+pub fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    let mut file = vfs::open(path, OpenMode::Read)?;
+    let mut data = Vec::new();
+    let mut buf = vec![0u8; 4096];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        data.extend_from_slice(&buf[..n]);
+    }
+    Ok(data)
 }
 
-pub fn cmd_ls(args: &str) -> Result<String, String> {
-    let mut root = open_volume()?;
-    let path = if args.is_empty() { "\\" } else { args };
-    let path_cstr = to_uefi_path(path)?;
+/// Open a file for writing, creating it if needed, and truncate it so
+/// shorter data leaves no stale bytes.
+fn create_file(path: &str) -> Result<Box<dyn OpenFile>, String> {
+    let mut file = vfs::open(path, OpenMode::Create)?;
+    file.set_len(0)?;
+    Ok(file)
+}
 
-    let handle = root
-        .open(&path_cstr, FileMode::Read, FileAttribute::empty())
-        .map_err(|_| format!("Cannot open '{}'", path))?;
+/// Replace the contents of a file, creating it if needed.
+pub fn write_file(path: &str, data: &[u8]) -> Result<(), String> {
+    create_file(path)?.write(data)
+}
 
-    let mut dir = handle
-        .into_directory()
-        .ok_or_else(|| format!("'{}' is not a directory", path))?;
+/// Append to a file, creating it if needed.
+pub fn append_file(path: &str, data: &[u8]) -> Result<(), String> {
+    let mut file = vfs::open(path, OpenMode::Create)?;
+    let end = file.size()?;
+    file.seek(end)?;
+    file.write(data)
+}
 
-    let mut output = String::new();
-    loop {
-        match dir.read_entry_boxed() {
-            Ok(Some(info)) => {
-                let name = info.file_name();
-                if info.is_directory() {
-                    let _ = writeln!(output, "  <DIR>  {}", name);
-                } else {
-                    let _ = writeln!(output, "  {:>8}  {}", info.file_size(), name);
-                }
-            }
-            Ok(None) => break,
-            Err(_) => return Err(String::from("Error reading directory")),
+/// Create a directory and any missing parents.
+pub fn create_dir_all(path: &str) -> Result<(), String> {
+    let path = path::normalize(path);
+    let (volume, rest) = path::split_volume(&path);
+    let mut prefix = volume.map(|v| format!("{}:", v)).unwrap_or_default();
+    for part in rest.split(path::SEPARATOR).filter(|p| !p.is_empty()) {
+        prefix.push(path::SEPARATOR);
+        prefix.push_str(part);
+        if !vfs::stat(&prefix).is_ok_and(|meta| meta.is_dir) {
+            vfs::mkdir(&prefix)?;
         }
     }
-    Ok(output)
+    Ok(())
 }
 
-pub fn cmd_cat(args: &str) -> Result<String, String> {
-    if args.is_empty() {
-        return Err(String::from("Usage: cat <file>"));
-    }
-    let mut root = open_volume()?;
-    let path_cstr = to_uefi_path(args)?;
+// ── Copy, move and delete ──
 
-    let handle = root
-        .open(&path_cstr, FileMode::Read, FileAttribute::empty())
-        .map_err(|_| format!("Cannot open '{}'", args))?;
+/// Size of the buffer files are streamed through when copied.
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
 
-    let mut file = handle
-        .into_regular_file()
-        .ok_or_else(|| format!("'{}' is a directory", args))?;
+/// Copy a file, replacing `to` if it exists. Returns the number of bytes copied.
+pub fn copy_file(from: &str, to: &str) -> Result<u64, String> {
+    if vfs::same_file(from, to) {
+        return Err(format!("'{}' and '{}' are the same file", from, to));
+    }
+    let mut src = vfs::open(from, OpenMode::Read)?;
+    let mut dst = create_file(to)?;
 
-    let mut buf = vec![0u8; 4096];
-    let mut content = String::new();
+    let mut buf = vec![0u8; COPY_BUFFER_SIZE];
+    let mut total = 0;
     loop {
-        let n = file.read(&mut buf).map_err(|_| String::from("Read error"))?;
+        let n = src.read(&mut buf)?;
         if n == 0 {
             break;
         }
-        content.push_str(&String::from_utf8_lossy(&buf[..n]));
+        dst.write(&buf[..n])?;
+        total += n as u64;
+    }
+    Ok(total)
+}
+
+/// Copy a directory tree. Returns the number of files copied.
+pub fn copy_dir_all(from: &str, to: &str) -> Result<usize, String> {
+    if path::is_within(&path::normalize(to), &path::normalize(from)) {
+        return Err(format!("Cannot copy '{}' into itself", from));
+    }
+    create_dir_all(to)?;
+    let mut count = 0;
+    for entry in vfs::read_dir(from)? {
+        if entry.is_dot() {
+            continue;
+        }
+        let src = path::join(from, &entry.name);
+        let dst = path::join(to, &entry.name);
+        if entry.is_dir {
+            count += copy_dir_all(&src, &dst)?;
+        } else {
+            copy_file(&src, &dst)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+/// Create an empty file if `path` does not exist, otherwise set its
+/// modification and access times to now.
+pub fn touch(path: &str) -> Result<(), String> {
+    if vfs::stat(path).is_err() {
+        return create_file(path).map(|_| ());
+    }
+    let now = runtime::get_time().map_err(|_| String::from("Cannot read the clock"))?;
+    vfs::set_times(path, now)
+}
+
+/// Delete a file, or a directory and everything in it.
+pub fn remove_all(path: &str) -> Result<(), String> {
+    if vfs::stat(path)?.is_dir {
+        for entry in vfs::read_dir(path)? {
+            if !entry.is_dot() {
+                remove_all(&path::join(path, &entry.name))?;
+            }
+        }
+    }
+    vfs::unlink(path)
+}
+
+// ── Commands ──
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum SortKey {
+    Name,
+    Time,
+    Size,
+}
+
+/// Attribute letters as shown by `ls -l`, `stat` and `attrib`: directory,
+/// read-only, hidden, system, archive.
+fn attribute_string(attribute: FileAttribute) -> String {
+    [
+        (FileAttribute::DIRECTORY, 'd'),
+        (FileAttribute::READ_ONLY, 'r'),
+        (FileAttribute::HIDDEN, 'h'),
+        (FileAttribute::SYSTEM, 's'),
+        (FileAttribute::ARCHIVE, 'a'),
+    ]
+    .iter()
+    .map(|&(flag, c)| if attribute.contains(flag) { c } else { '-' })
+    .collect()
+}
+
+/// `YYYY-MM-DD HH:MM:SS`, or `-` if the firmware left the time unset.
+fn format_time(time: &Time) -> String {
+    if time.year() == 0 {
+        return String::from("-");
+    }
+    format!(
+        "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+        time.year(),
+        time.month(),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second()
+    )
+}
+
+fn time_key(time: &Time) -> (u16, u8, u8, u8, u8, u8, u32) {
+    (
+        time.year(),
+        time.month(),
+        time.day(),
+        time.hour(),
+        time.minute(),
+        time.second(),
+        time.nanosecond(),
+    )
+}
+
+/// `ls [-1alStr] [path]`: list a directory (or a single file).
+///
+/// `-l` long format, `-a` include hidden entries and `.`/`..`, `-1` bare
+/// names, `-t` newest first, `-S` largest first, `-r` reverse the order.
+pub fn cmd_ls(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let (mut long, mut all, mut bare, mut reverse) = (false, false, false, false);
+    let mut sort = SortKey::Name;
+    let mut target = None;
+    for arg in args {
+        match arg.strip_prefix('-').filter(|f| !f.is_empty()) {
+            Some(flags) => {
+                for flag in flags.chars() {
+                    match flag {
+                        'l' => long = true,
+                        'a' => all = true,
+                        '1' => bare = true,
+                        't' => sort = SortKey::Time,
+                        'S' => sort = SortKey::Size,
+                        'r' => reverse = true,
+                        _ => return Err(format!("ls: unknown option '-{}'", flag)),
+                    }
+                }
+            }
+            None if target.is_none() => target = Some(arg.as_str()),
+            None => return Err(String::from("Usage: ls [-1alStr] [path]")),
+        }
+    }
+
+    let path = sh.resolve_path(target.unwrap_or("."));
+    let mut entries = if vfs::stat(&path)?.is_dir {
+        vfs::read_dir(&path)?
+    } else {
+        vec![vfs::stat(&path)?]
+    };
+    if !all {
+        entries.retain(|e| !e.is_dot() && !e.attribute.contains(FileAttribute::HIDDEN));
+    }
+    entries.sort_by(|a, b| match sort {
+        SortKey::Name => a
+            .name
+            .to_ascii_lowercase()
+            .cmp(&b.name.to_ascii_lowercase()),
+        SortKey::Time => time_key(&b.modified).cmp(&time_key(&a.modified)),
+        SortKey::Size => b.size.cmp(&a.size),
+    });
+    if reverse {
+        entries.reverse();
+    }
+
+    for entry in &entries {
+        if bare {
+            let _ = writeln!(out, "{}", entry.name);
+        } else if long {
+            let size = if entry.is_dir {
+                String::from("<DIR>")
+            } else {
+                entry.size.to_string()
+            };
+            let _ = writeln!(
+                out,
+                "{}  {:>10}  {}  {}",
+                attribute_string(entry.attribute),
+                size,
+                format_time(&entry.modified),
+                entry.name
+            );
+        } else if entry.is_dir {
+            let _ = writeln!(out, "  <DIR>  {}", entry.name);
+        } else {
+            let _ = writeln!(out, "  {:>8}  {}", entry.size, entry.name);
+        }
     }
-    Ok(content)
+    Ok(())
 }
 
-pub fn cmd_write(args: &str) -> Result<String, String> {
-    let (filename, text) = args
-        .split_once(' ')
-        .ok_or_else(|| String::from("Usage: write <file> <text>"))?;
+/// A byte count in MB, or KB below one megabyte.
+pub fn size_text(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!("{} MB", bytes / (1024 * 1024))
+    } else {
+        format!("{} KB", bytes / 1024)
+    }
+}
 
-    let mut root = open_volume()?;
-    let path_cstr = to_uefi_path(filename)?;
+/// `vol`: rescan the firmware's volumes and list each with its label, size
+/// and device path. `*` marks the boot volume, which paths without an
+/// `fsN:` prefix refer to.
+pub fn cmd_vol(_sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    if !args.is_empty() {
+        return Err(String::from("Usage: vol"));
+    }
+    vfs::uefi_fs::mount_volumes()?;
+    let _ = writeln!(
+        out,
+        "  {:<6} {:<12} {:>8} {:>8}     Device",
+        "Volume", "Label", "Size", "Free"
+    );
+    for vol in vfs::uefi_fs::volumes()? {
+        let _ = writeln!(
+            out,
+            "{} {:<6} {:<12} {:>8} {:>8} {}  {}",
+            if vol.boot { '*' } else { ' ' },
+            format!("{}:", vol.name),
+            vol.label,
+            size_text(vol.size),
+            size_text(vol.free),
+            if vol.read_only { "ro" } else { "rw" },
+            vol.device
+        );
+    }
+    Ok(())
+}
 
-    let handle = root
-        .open(
-            &path_cstr,
-            FileMode::CreateReadWrite,
-            FileAttribute::empty(),
-        )
-        .map_err(|_| format!("Cannot create '{}'", filename))?;
+/// `mounts`: list the mount table with each file system's backend, size
+/// and source.
+pub fn cmd_mounts(_sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    if !args.is_empty() {
+        return Err(String::from("Usage: mounts"));
+    }
+    let _ = writeln!(
+        out,
+        "{:<10} {:<6} {:>8} {:>8}  Source",
+        "Mount", "Type", "Size", "Free"
+    );
+    for mount in vfs::mounts() {
+        let (size, free) = match vfs::stats(&mount.point) {
+            Ok(stats) => (size_text(stats.size), size_text(stats.free)),
+            Err(_) => (String::from("-"), String::from("-")),
+        };
+        let _ = writeln!(
+            out,
+            "{:<10} {:<6} {:>8} {:>8}  {}",
+            mount.point, mount.kind, size, free, mount.source
+        );
+    }
+    Ok(())
+}
 
-    let mut file = handle
-        .into_regular_file()
-        .ok_or_else(|| format!("'{}' is a directory", filename))?;
+/// `mount [<image|diskNpM> <dir>]`: mount the FAT file system in a disk
+/// image or partition at the directory `dir`, read-only if the source
+/// cannot be written. Without arguments, list the mounts.
+pub fn cmd_mount(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let [image, dir] = args else {
+        if args.is_empty() {
+            return cmd_mounts(sh, args, out);
+        }
+        return Err(String::from("Usage: mount [<image|diskNpM> <dir>]"));
+    };
+    let image = if block::is_device_name(image) {
+        image.clone()
+    } else {
+        sh.resolve_path(image)
+    };
+    let point = sh.resolve_path(dir);
+    if path::is_root(&point) {
+        return Err(format!("Cannot mount over the volume root '{}'", point));
+    }
+    if vfs::mounts()
+        .iter()
+        .any(|m| m.point.eq_ignore_ascii_case(&point))
+    {
+        return Err(format!("'{}' is already a mount point", point));
+    }
+    if !vfs::stat(&point)?.is_dir {
+        return Err(format!("'{}' is not a directory", point));
+    }
 
-    file.write(text.as_bytes())
-        .map_err(|_| String::from("Write error"))?;
+    let device: Box<dyn BlockDevice> = if block::is_device_name(&image) {
+        block::open(&image)?
+    } else {
+        match vfs::open(&image, OpenMode::ReadWrite) {
+            Ok(file) => Box::new(ImageDisk::new(file, false)?),
+            Err(_) => Box::new(ImageDisk::new(vfs::open(&image, OpenMode::Read)?, true)?),
+        }
+    };
+    let read_only = device.read_only();
+    let fs = FatFs::mount(device, &image)?;
+    let _ = writeln!(
+        out,
+        "Mounted {} ({}{}) at {}",
+        image,
+        fs.kind(),
+        if read_only { ", read-only" } else { "" },
+        point
+    );
+    vfs::mount(&point, Box::new(fs));
+    Ok(())
+}
 
-    Ok(format!("Wrote {} bytes to {}", text.len(), filename))
+/// `umount <dir>`: remove a mount made with `mount`. Volume roots stay.
+pub fn cmd_umount(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let [dir] = args else {
+        return Err(String::from("Usage: umount <dir>"));
+    };
+    let point = sh.resolve_path(dir);
+    if path::is_root(&point) {
+        return Err(format!("Cannot unmount the volume root '{}'", point));
+    }
+    vfs::unmount(&point)?;
+    let _ = writeln!(out, "Unmounted {}", point);
+    Ok(())
 }
 
-pub fn cmd_mkdir(args: &str) -> Result<String, String> {
+/// `stat <path...>`: show size, attributes and timestamps.
+pub fn cmd_stat(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
     if args.is_empty() {
+        return Err(String::from("Usage: stat <path...>"));
+    }
+    for name in args {
+        let target = sh.resolve_path(name);
+        let entry = vfs::stat(&target)?;
+        let _ = writeln!(out, "Path:       {}", target);
+        let _ = writeln!(
+            out,
+            "Type:       {}",
+            if entry.is_dir { "directory" } else { "file" }
+        );
+        let _ = writeln!(out, "Size:       {} bytes", entry.size);
+        let _ = writeln!(out, "Attributes: {}", attribute_string(entry.attribute));
+        let _ = writeln!(out, "Created:    {}", format_time(&entry.created));
+        let _ = writeln!(out, "Modified:   {}", format_time(&entry.modified));
+        let _ = writeln!(out, "Accessed:   {}", format_time(&entry.accessed));
+    }
+    Ok(())
+}
+
+/// `attrib [+r|-r|+h|-h|+s|-s|+a|-a...] <path...>`: show or change attributes.
+pub fn cmd_attrib(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let usage = || String::from("Usage: attrib [+r|-r|+h|-h|+s|-s|+a|-a...] <path...>");
+    let mut set = FileAttribute::empty();
+    let mut clear = FileAttribute::empty();
+    let mut paths = Vec::new();
+    for arg in args {
+        let (add, flags) = match arg.chars().next() {
+            Some('+') => (true, &arg[1..]),
+            Some('-') if arg.len() > 1 => (false, &arg[1..]),
+            _ => {
+                paths.push(arg);
+                continue;
+            }
+        };
+        for flag in flags.chars() {
+            let attribute = match flag {
+                'r' => FileAttribute::READ_ONLY,
+                'h' => FileAttribute::HIDDEN,
+                's' => FileAttribute::SYSTEM,
+                'a' => FileAttribute::ARCHIVE,
+                _ => return Err(usage()),
+            };
+            if add {
+                set |= attribute;
+            } else {
+                clear |= attribute;
+            }
+        }
+    }
+    if paths.is_empty() {
+        return Err(usage());
+    }
+
+    for name in paths {
+        let target = sh.resolve_path(name);
+        let mut attribute = vfs::stat(&target)?.attribute;
+        if !(set | clear).is_empty() {
+            attribute = (attribute | set) - clear;
+            vfs::set_attributes(&target, attribute)?;
+        }
+        let _ = writeln!(out, "{}  {}", attribute_string(attribute), name);
+    }
+    Ok(())
+}
+
+/// `touch <path...>`: create empty files or update modification times.
+pub fn cmd_touch(sh: &mut Shell, args: &[String], _out: &mut dyn Console) -> Result<(), String> {
+    if args.is_empty() {
+        return Err(String::from("Usage: touch <path...>"));
+    }
+    for name in args {
+        touch(&sh.resolve_path(name))?;
+    }
+    Ok(())
+}
+
+/// Decode `\\`, `\n`, `\r`, `\t`, `\0` and `\xHH` escapes into bytes;
+/// other backslashes are kept.
+fn unescape(text: &str) -> Result<Vec<u8>, String> {
+    let mut data = Vec::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut utf8 = [0u8; 4];
+            data.extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => data.push(b'\\'),
+            Some('n') => data.push(b'\n'),
+            Some('r') => data.push(b'\r'),
+            Some('t') => data.push(b'\t'),
+            Some('0') => data.push(0),
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = Some(&hex)
+                    .filter(|h| h.len() == 2 && h.bytes().all(|b| b.is_ascii_hexdigit()))
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    .ok_or_else(|| format!("invalid escape '\\x{}'", hex))?;
+                data.push(byte);
+            }
+            Some(other) => {
+                data.push(b'\\');
+                let mut utf8 = [0u8; 4];
+                data.extend_from_slice(other.encode_utf8(&mut utf8).as_bytes());
+            }
+            None => data.push(b'\\'),
+        }
+    }
+    Ok(data)
+}
+
+/// Parse `[-e] <file> [text...]` for `write` and `append`. The data is the
+/// text joined by spaces, or piped input or a here-document when no text
+/// is given; `-e` decodes escapes such as `\x00`.
+fn write_args<'a>(
+    sh: &mut Shell,
+    cmd: &str,
+    args: &'a [String],
+) -> Result<(&'a str, Vec<u8>), String> {
+    let usage = || format!("Usage: {} [-e] <file> [text...]", cmd);
+    let (escapes, args) = match args.split_first() {
+        Some((flag, rest)) if flag == "-e" => (true, rest),
+        _ => (false, args),
+    };
+    let (filename, words) = args.split_first().ok_or_else(usage)?;
+    let data = if words.is_empty() {
+        sh.take_stdin().ok_or_else(usage)?
+    } else {
+        words.join(" ").into_bytes()
+    };
+    let data = if escapes {
+        unescape(&String::from_utf8_lossy(&data))?
+    } else {
+        data
+    };
+    Ok((filename, data))
+}
+
+/// `write [-e] <file> [text...]`: replace a file's contents.
+pub fn cmd_write(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let (filename, data) = write_args(sh, "write", args)?;
+    write_file(&sh.resolve_path(filename), &data)?;
+    let _ = writeln!(out, "Wrote {} bytes to {}", data.len(), filename);
+    Ok(())
+}
+
+/// `append [-e] <file> [text...]`: add to the end of a file.
+pub fn cmd_append(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let (filename, data) = write_args(sh, "append", args)?;
+    append_file(&sh.resolve_path(filename), &data)?;
+    let _ = writeln!(out, "Appended {} bytes to {}", data.len(), filename);
+    Ok(())
+}
+
+pub fn cmd_mkdir(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let [path] = args else {
         return Err(String::from("Usage: mkdir <dir>"));
+    };
+    let target = sh.resolve_path(path);
+    vfs::mkdir(&target)?;
+    let _ = writeln!(out, "Created directory: {}", path);
+    Ok(())
+}
+
+/// Directory holding the fallback boot loader (`BOOTX64.EFI` and friends).
+const BOOT_DIR: &str = "\\EFI\\BOOT";
+
+/// Path of the running image on the boot volume, taken from the file-path
+/// nodes of its loaded-image device path.
+pub fn image_path() -> Option<String> {
+    let loaded = boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle()).ok()?;
+    let mut image = String::new();
+    for node in loaded.file_path()?.node_iter() {
+        if let Ok(DevicePathNodeEnum::MediaFilePath(file)) = node.as_enum() {
+            let name = file.path_name().to_cstring16().ok()?;
+            image = path::join(&image, &String::from(&name));
+        }
     }
-    let mut root = open_volume()?;
-    let path_cstr = to_uefi_path(args)?;
+    (!image.is_empty()).then(|| path::normalize(&image))
+}
 
-    let _handle = root
-        .open(
-            &path_cstr,
-            FileMode::CreateReadWrite,
-            FileAttribute::DIRECTORY,
-        )
-        .map_err(|_| format!("Cannot create directory '{}'", args))?;
+/// The protected path that `path`, on the boot volume, is, contains or
+/// lies within: the boot directory or the running `image`.
+fn protected_by<'a>(path: &str, image: Option<&'a str>) -> Option<&'a str> {
+    core::iter::once(BOOT_DIR)
+        .chain(image)
+        .find(|protected| path::is_within(path, protected) || path::is_within(protected, path))
+}
 
-    Ok(format!("Created directory: {}", args))
+/// Refuse to delete or move the boot directory, the running image, or
+/// anything containing them. `action` is what was asked, e.g. `delete`,
+/// and `force` how to do it anyway.
+fn check_protected(target: &str, action: &str, force: &str) -> Result<(), String> {
+    let Some(on_boot) = on_boot_volume(target) else {
+        return Ok(());
+    };
+    match protected_by(&on_boot, image_path().as_deref()) {
+        Some(protected) => Err(format!(
+            "Refusing to {} '{}': it would remove {} ({})",
+            action, target, protected, force
+        )),
+        None => Ok(()),
+    }
 }
 
-pub fn cmd_rm(args: &str) -> Result<String, String> {
+/// `rm [-r] [-f] <path...>`: delete files, or directory trees with `-r`.
+/// `-f` skips missing paths, the protection check and the confirmation,
+/// which scripts and redirected output cannot answer and so need `-f`.
+pub fn cmd_rm(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let mut recursive = false;
+    let mut force = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.strip_prefix('-') {
+            Some(flags) if !flags.is_empty() && flags.chars().all(|c| c == 'r' || c == 'f') => {
+                recursive |= flags.contains('r');
+                force |= flags.contains('f');
+            }
+            _ => paths.push(arg),
+        }
+    }
+    if paths.is_empty() {
+        return Err(String::from("Usage: rm [-r] [-f] <path...>"));
+    }
+
+    for name in paths {
+        let target = sh.resolve_path(name);
+        let meta = match vfs::stat(&target) {
+            Ok(meta) => meta,
+            Err(_) if force => continue,
+            Err(e) => return Err(e),
+        };
+        if !force {
+            check_protected(&target, "delete", "use rm -f to force")?;
+        }
+        if meta.is_dir {
+            if !recursive {
+                return Err(format!("'{}' is a directory (use rm -r or rmdir)", name));
+            }
+            if !force {
+                // Scripts and redirected output leave no one to answer
+                if sh.position.is_some() || !out.is_terminal() {
+                    return Err(format!(
+                        "Refusing to delete directory '{}' without asking (use rm -rf)",
+                        name
+                    ));
+                }
+                let question = format!("Delete '{}' and everything in it?", target);
+                if !crate::shell::confirm(out, &question) {
+                    continue;
+                }
+            }
+        }
+        remove_all(&target)?;
+        let _ = writeln!(out, "Deleted: {}", name);
+    }
+    Ok(())
+}
+
+/// `rmdir <dir...>`: delete empty directories.
+pub fn cmd_rmdir(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
     if args.is_empty() {
-        return Err(String::from("Usage: rm <file>"));
+        return Err(String::from("Usage: rmdir <dir...>"));
     }
-    let mut root = open_volume()?;
-    let path_cstr = to_uefi_path(args)?;
+    for name in args {
+        let target = sh.resolve_path(name);
+        if !vfs::stat(&target)?.is_dir {
+            return Err(format!("'{}' is not a directory", name));
+        }
+        if vfs::read_dir(&target)?.iter().any(|e| !e.is_dot()) {
+            return Err(format!("'{}' is not empty", name));
+        }
+        check_protected(&target, "delete", "use rm -rf to force")?;
+        remove_all(&target)?;
+        let _ = writeln!(out, "Removed directory: {}", name);
+    }
+    Ok(())
+}
 
-    let handle = root
-        .open(&path_cstr, FileMode::ReadWrite, FileAttribute::empty())
-        .map_err(|_| format!("Cannot open '{}'", args))?;
+/// Destination for `cp`/`mv`: inside `to` if it is an existing directory
+/// other than `from` itself, whose name may differ only in case.
+fn destination(from: &str, to: &str) -> String {
+    match vfs::stat(to) {
+        Ok(meta) if meta.is_dir && !vfs::same_file(from, to) => {
+            path::join(to, path::file_name(from))
+        }
+        _ => String::from(to),
+    }
+}
 
-    handle
-        .delete()
-        .map_err(|_| format!("Cannot delete '{}'", args))?;
+pub fn cmd_cp(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let (recursive, args) = match args.split_first() {
+        Some((flag, rest)) if flag == "-r" => (true, rest),
+        _ => (false, args),
+    };
+    let [from, to] = args else {
+        return Err(String::from("Usage: cp [-r] <src> <dst>"));
+    };
+    let src = sh.resolve_path(from);
+    let dst = destination(&src, &sh.resolve_path(to));
 
-    Ok(format!("Deleted: {}", args))
+    if vfs::stat(&src)?.is_dir {
+        if !recursive {
+            return Err(format!("'{}' is a directory (use cp -r)", from));
+        }
+        let count = copy_dir_all(&src, &dst)?;
+        let _ = writeln!(out, "Copied {} files to {}", count, dst);
+    } else {
+        let bytes = copy_file(&src, &dst)?;
+        let _ = writeln!(out, "Copied {} bytes to {}", bytes, dst);
+    }
+    Ok(())
+}
+
+/// `mv [-f] <src> <dst>`: rename within a volume; between volumes, copy
+/// and delete. `-f` allows moving the boot loader away.
+pub fn cmd_mv(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let (force, args) = match args.split_first() {
+        Some((flag, rest)) if flag == "-f" => (true, rest),
+        _ => (false, args),
+    };
+    let [from, to] = args else {
+        return Err(String::from("Usage: mv [-f] <src> <dst>"));
+    };
+    let src = sh.resolve_path(from);
+    if !force {
+        check_protected(&src, "move", "use mv -f to force")?;
+    }
+    let dst = destination(&src, &sh.resolve_path(to));
+    // Changing only the case of the name is a rename
+    if vfs::same_file(&src, &dst) && path::file_name(&src) == path::file_name(&dst) {
+        return Err(format!("'{}' and '{}' are the same file", from, to));
+    }
+
+    if vfs::same_volume(&src, &dst) {
+        vfs::rename(&src, &dst)?;
+    } else {
+        if vfs::stat(&src)?.is_dir {
+            copy_dir_all(&src, &dst)?;
+        } else {
+            copy_file(&src, &dst)?;
+        }
+        remove_all(&src)?;
+    }
+    let _ = writeln!(out, "Moved {} -> {}", src, dst);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boot_loader_is_protected() {
+        let image = Some("\\vos\\vos.efi");
+        for path in ["\\", "\\EFI", "\\efi\\boot", "\\EFI\\BOOT\\BOOTX64.EFI"] {
+            assert_eq!(protected_by(path, image), Some(BOOT_DIR), "{}", path);
+        }
+        assert_eq!(protected_by("\\VOS", image), image);
+        assert_eq!(protected_by("\\vos\\vos.efi", image), image);
+        assert_eq!(protected_by("\\vos\\history", image), None);
+        assert_eq!(protected_by("\\EFI\\BOOTX", image), None);
+        assert_eq!(protected_by("\\vos", None), None);
+    }
 }
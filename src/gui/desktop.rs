@@ -1,5 +1,6 @@
 extern crate alloc;
 
+use alloc::string::String;
 use alloc::vec;
 use alloc::vec::Vec;
 use uefi::proto::console::gop::BltPixel;
@@ -14,6 +15,8 @@ const TITLE_BAR_HEIGHT: usize = 24;
 const START_BTN_WIDTH: usize = 80;
 const MENU_WIDTH: usize = 200;
 const MENU_ITEM_HEIGHT: usize = 28;
+const MENU_ITEMS: [&str; 4] = ["Terminal", "Info", "Boot", "Reboot"];
+const BOOT_MENU_WIDTH: usize = 320;
 
 #[derive(Clone, Copy)]
 pub struct Rect {
@@ -39,7 +42,10 @@ pub enum ClickAction {
     ToggleStartMenu,
     MenuTerminal,
     MenuInfo,
+    MenuBoot,
     MenuReboot,
+    /// A boot target picked from the boot menu, by load option number.
+    Boot(u16),
 }
 
 pub struct Desktop {
@@ -59,8 +65,11 @@ pub struct Desktop {
     // Clickable regions
     pub close_button_rect: Rect,
     pub start_button_rect: Rect,
-    menu_rects: [Rect; 3],
+    menu_rects: [Rect; MENU_ITEMS.len()],
     menu_y: usize,
+    /// Boot menu next to the start menu: load option numbers and names.
+    pub boot_menu_open: bool,
+    boot_targets: Vec<(u16, String)>,
 }
 
 impl Desktop {
@@ -106,13 +115,14 @@ impl Desktop {
             h: TASKBAR_HEIGHT,
         };
 
-        let menu_h = 3 * MENU_ITEM_HEIGHT + 8;
+        let menu_h = MENU_ITEMS.len() * MENU_ITEM_HEIGHT + 8;
         let menu_y = taskbar_y - menu_h;
-        let menu_rects = [
-            Rect { x: 0, y: menu_y + 4, w: MENU_WIDTH, h: MENU_ITEM_HEIGHT },
-            Rect { x: 0, y: menu_y + 4 + MENU_ITEM_HEIGHT, w: MENU_WIDTH, h: MENU_ITEM_HEIGHT },
-            Rect { x: 0, y: menu_y + 4 + 2 * MENU_ITEM_HEIGHT, w: MENU_WIDTH, h: MENU_ITEM_HEIGHT },
-        ];
+        let menu_rects = core::array::from_fn(|i| Rect {
+            x: 0,
+            y: menu_y + 4 + i * MENU_ITEM_HEIGHT,
+            w: MENU_WIDTH,
+            h: MENU_ITEM_HEIGHT,
+        });
 
         Self {
             fb,
@@ -132,6 +142,8 @@ impl Desktop {
             start_button_rect,
             menu_rects,
             menu_y,
+            boot_menu_open: false,
+            boot_targets: Vec::new(),
         }
     }
 
@@ -154,6 +166,9 @@ impl Desktop {
         if self.start_menu_open {
             self.draw_start_menu();
         }
+        if self.boot_menu_open {
+            self.draw_boot_menu();
+        }
         self.fb.flush();
     }
 
@@ -251,7 +266,7 @@ impl Desktop {
     }
 
     fn draw_start_menu(&mut self) {
-        let menu_h = 3 * MENU_ITEM_HEIGHT + 8;
+        let menu_h = MENU_ITEMS.len() * MENU_ITEM_HEIGHT + 8;
 
         // Menu background
         self.fb
@@ -264,8 +279,7 @@ impl Desktop {
         self.fb
             .fill_rect(MENU_WIDTH - 1, self.menu_y, 1, menu_h, Color::new(70, 70, 75));
 
-        let items = ["Terminal", "Info", "Reboot"];
-        for (i, item) in items.iter().enumerate() {
+        for (i, item) in MENU_ITEMS.iter().enumerate() {
             let r = self.menu_rects[i];
             let hover = r.contains(self.mouse_x, self.mouse_y);
             let bg = if hover {
@@ -279,25 +293,111 @@ impl Desktop {
         }
     }
 
+    /// Open the boot menu listing `targets` (load option numbers and
+    /// names), as many as fit above the taskbar.
+    pub fn open_boot_menu(&mut self, mut targets: Vec<(u16, String)>) {
+        let max_items = (self.screen_h - TASKBAR_HEIGHT - 8) / MENU_ITEM_HEIGHT;
+        targets.truncate(max_items.max(1));
+        self.boot_targets = targets;
+        self.boot_menu_open = true;
+        self.needs_full_redraw = true;
+    }
+
+    /// Item `i` of the boot menu, whose bottom lines up with the start menu.
+    fn boot_menu_rect(&self, i: usize) -> Rect {
+        let items = self.boot_targets.len().max(1);
+        let top = self.screen_h - TASKBAR_HEIGHT - items * MENU_ITEM_HEIGHT - 8;
+        Rect {
+            x: MENU_WIDTH,
+            y: top + 4 + i * MENU_ITEM_HEIGHT,
+            w: BOOT_MENU_WIDTH,
+            h: MENU_ITEM_HEIGHT,
+        }
+    }
+
+    fn draw_boot_menu(&mut self) {
+        let items = self.boot_targets.len().max(1);
+        let menu_h = items * MENU_ITEM_HEIGHT + 8;
+        let menu_y = self.screen_h - TASKBAR_HEIGHT - menu_h;
+        self.fb
+            .fill_rect(MENU_WIDTH, menu_y, BOOT_MENU_WIDTH, menu_h, Color::MENU_BG);
+        self.fb
+            .fill_rect(MENU_WIDTH, menu_y, BOOT_MENU_WIDTH, 1, Color::new(70, 70, 75));
+        self.fb.fill_rect(
+            MENU_WIDTH + BOOT_MENU_WIDTH - 1,
+            menu_y,
+            1,
+            menu_h,
+            Color::new(70, 70, 75),
+        );
+
+        if self.boot_targets.is_empty() {
+            let r = self.boot_menu_rect(0);
+            let text_y = r.y + (MENU_ITEM_HEIGHT - CHAR_HEIGHT) / 2;
+            super::font::draw_string(
+                &mut self.fb,
+                "No boot options",
+                r.x + 16,
+                text_y,
+                Color::LIGHT_GRAY,
+                Color::MENU_BG,
+            );
+            return;
+        }
+        let max_chars = (BOOT_MENU_WIDTH - 24) / CHAR_WIDTH;
+        for i in 0..self.boot_targets.len() {
+            let r = self.boot_menu_rect(i);
+            let hover = r.contains(self.mouse_x, self.mouse_y);
+            let bg = if hover {
+                Color::MENU_HOVER
+            } else {
+                Color::MENU_BG
+            };
+            self.fb.fill_rect(r.x, r.y, r.w, r.h, bg);
+            let label: String = self.boot_targets[i].1.chars().take(max_chars).collect();
+            let text_y = r.y + (MENU_ITEM_HEIGHT - CHAR_HEIGHT) / 2;
+            super::font::draw_string(&mut self.fb, &label, r.x + 16, text_y, Color::WHITE, bg);
+        }
+    }
+
+    fn close_menus(&mut self) {
+        self.start_menu_open = false;
+        self.boot_menu_open = false;
+        self.needs_full_redraw = true;
+    }
+
     pub fn handle_click(&mut self, x: i32, y: i32) -> ClickAction {
+        // Boot targets sit over the desktop next to the start menu
+        if self.boot_menu_open {
+            for i in 0..self.boot_targets.len() {
+                if self.boot_menu_rect(i).contains(x, y) {
+                    let number = self.boot_targets[i].0;
+                    self.close_menus();
+                    return ClickAction::Boot(number);
+                }
+            }
+        }
+
         // Start menu items (check first if menu is open)
         if self.start_menu_open {
             for (i, r) in self.menu_rects.iter().enumerate() {
                 if r.contains(x, y) {
-                    self.start_menu_open = false;
-                    self.needs_full_redraw = true;
+                    // The start menu stays open next to the boot menu
+                    if i == 2 {
+                        return ClickAction::MenuBoot;
+                    }
+                    self.close_menus();
                     return match i {
                         0 => ClickAction::MenuTerminal,
                         1 => ClickAction::MenuInfo,
-                        2 => ClickAction::MenuReboot,
+                        3 => ClickAction::MenuReboot,
                         _ => ClickAction::None,
                     };
                 }
             }
             // Click outside menu closes it
             if !self.start_button_rect.contains(x, y) {
-                self.start_menu_open = false;
-                self.needs_full_redraw = true;
+                self.close_menus();
                 return ClickAction::None;
             }
         }
@@ -305,6 +405,7 @@ impl Desktop {
         // Start button
         if self.start_button_rect.contains(x, y) {
             self.start_menu_open = !self.start_menu_open;
+            self.boot_menu_open = false;
             self.needs_full_redraw = true;
             return ClickAction::ToggleStartMenu;
         }
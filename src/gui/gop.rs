@@ -70,6 +70,23 @@ pub fn init_gop() -> Result<ScreenInfo, &'static str> {
     Ok(ScreenInfo { width, height })
 }
 
+/// Switch back to the `screen` resolution if another program changed the
+/// video mode.
+pub fn restore_mode(screen: ScreenInfo) -> Result<(), &'static str> {
+    let handle = boot::get_handle_for_protocol::<GraphicsOutput>()
+        .map_err(|_| "No GOP handle")?;
+    let mut gop = boot::open_protocol_exclusive::<GraphicsOutput>(handle)
+        .map_err(|_| "Failed to open GOP")?;
+    if gop.current_mode_info().resolution() == (screen.width, screen.height) {
+        return Ok(());
+    }
+    let mode = gop
+        .modes()
+        .find(|m| m.info().resolution() == (screen.width, screen.height))
+        .ok_or("Video mode no longer available")?;
+    gop.set_mode(&mode).map_err(|_| "Failed to set video mode")
+}
+
 pub struct Framebuffer {
     pub pixels: Vec<BltPixel>,
     pub width: usize,
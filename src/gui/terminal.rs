@@ -5,6 +5,7 @@ use alloc::vec::Vec;
 
 use super::font::{CHAR_HEIGHT, CHAR_WIDTH};
 use super::gop::{Color, Framebuffer};
+use crate::console::{Console, Style};
 
 #[derive(Clone, Copy)]
 pub struct Cell {
@@ -117,6 +118,15 @@ impl Terminal {
         self.mark_cursor_dirty();
     }
 
+    /// Move the cursor; `col` may equal `cols` to park it after the last column.
+    pub fn set_cursor(&mut self, col: usize, row: usize) {
+        self.prev_cursor_col = self.cursor_col;
+        self.prev_cursor_row = self.cursor_row;
+        self.cursor_col = col.min(self.cols);
+        self.cursor_row = row.min(self.rows - 1);
+        self.mark_cursor_dirty();
+    }
+
     pub fn write_str(&mut self, s: &str) {
         for byte in s.bytes() {
             self.write_byte(byte);
@@ -186,3 +196,31 @@ impl core::fmt::Write for Terminal {
         Ok(())
     }
 }
+
+impl Console for Terminal {
+    fn set_style(&mut self, style: Style) {
+        let fg = match style {
+            Style::Normal => Color::LIGHT_GRAY,
+            Style::Error => Color::RED,
+            Style::Prompt => Color::GREEN,
+            Style::Banner => Color::CYAN,
+        };
+        self.set_color(fg, Color::TERMINAL_BG);
+    }
+
+    fn clear(&mut self) {
+        Terminal::clear(self);
+    }
+
+    fn size(&self) -> (usize, usize) {
+        (self.cols, self.rows)
+    }
+
+    fn cursor(&self) -> (usize, usize) {
+        (self.cursor_col, self.cursor_row)
+    }
+
+    fn set_cursor(&mut self, col: usize, row: usize) {
+        Terminal::set_cursor(self, col, row);
+    }
+}
@@ -1,8 +1,5 @@
-#![no_std]
-#![cfg_attr(test, no_main)]
-#![feature(custom_test_frameworks)]
-#![test_runner(crate::test_runner)]
-#![reexport_test_harness_main = "test_main"]
+// Unit tests run on the host with the standard test harness
+#![cfg_attr(not(test), no_std)]
 #![feature(abi_x86_interrupt)]
 #![feature(alloc_error_handler)]
 #![feature(const_mut_refs)] 
@@ -10,10 +7,16 @@
 extern crate alloc;
 
 pub mod arch;
+pub mod block;
+pub mod bootmgr;
+pub mod console;
 pub mod fs;
 pub mod gui;
 pub mod memory;
+pub mod path;
+pub mod pattern;
 pub mod shell;
+pub mod vfs;
 
 #[cfg(target_arch = "x86_64")]
 pub use arch::x86_64::*;
@@ -25,42 +28,3 @@ pub fn init() {
     #[cfg(target_arch = "x86_64")]
     arch::x86_64::init();
 }
-
-pub trait Testable {
-    fn run(&self);
-}
-
-impl<T> Testable for T
-where
-    T: Fn(),
-{
-    fn run(&self) {
-        // Serial print not generic yet
-        self();
-    }
-}
-
-pub fn test_runner(tests: &[&dyn Testable]) {
-    for test in tests {
-        test.run();
-    }
-    // exit qemu
-}
-
-pub fn test_panic_handler(_info: &core::panic::PanicInfo) -> ! {
-    loop {}
-}
-
-#[cfg(test)]
-#[no_mangle]
-pub extern "C" fn _start() -> ! {
-    init();
-    test_main();
-    loop {}
-}
-
-#[cfg(test)]
-#[panic_handler]
-fn panic(info: &core::panic::PanicInfo) -> ! {
-    test_panic_handler(info)
-}
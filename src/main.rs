@@ -19,6 +19,8 @@ pub extern "C" fn efi_main(handle: Handle, system_table: *mut uefi_raw::table::s
     
     log::info!("UEFI Boot Success (Manual Entry)!");
 
+    vos::vfs::init();
+
     // Try GUI mode, fallback to text shell
     match vos::gui::gop::init_gop() {
         Ok(screen) => {
@@ -32,6 +34,7 @@ pub extern "C" fn efi_main(handle: Handle, system_table: *mut uefi_raw::table::s
     }
 }
 
+// Also holds the files of the ramfs at \tmp (see memory::ramfs_limit)
 #[global_allocator]
 static ALLOCATOR: uefi::allocator::Allocator = uefi::allocator::Allocator;
 
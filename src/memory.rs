@@ -1,11 +1,15 @@
 extern crate alloc;
 
-use alloc::string::String;
-use core::fmt::Write;
 use uefi::boot;
 use uefi::mem::memory_map::MemoryMap;
 use uefi_raw::table::boot::{MemoryType, PAGE_SIZE};
 
+use crate::console::Console;
+
+/// The ramfs at `\tmp` may hold at most this share (1/N) of the memory
+/// free at boot.
+const RAMFS_SHARE: u64 = 4;
+
 pub struct MemoryInfo {
     pub total_bytes: u64,
     pub free_bytes: u64,
@@ -47,29 +51,34 @@ pub fn get_memory_info() -> MemoryInfo {
     }
 }
 
-pub fn format_memory_info(info: &MemoryInfo) -> String {
-    let mut s = String::new();
-    let _ = writeln!(s, "Memory Map ({} entries):", info.entry_count);
+/// Size limit for the ramfs at `\tmp`. Its files live on the heap, which
+/// the global allocator in `main.rs` takes from UEFI pool memory, so the
+/// limit leaves most free memory to everything else.
+pub fn ramfs_limit() -> u64 {
+    get_memory_info().free_bytes / RAMFS_SHARE
+}
+
+pub fn write_memory_info(info: &MemoryInfo, out: &mut dyn Console) {
+    let _ = writeln!(out, "Memory Map ({} entries):", info.entry_count);
     let _ = writeln!(
-        s,
+        out,
         "  Total:          {} MB",
         info.total_bytes / (1024 * 1024)
     );
     let _ = writeln!(
-        s,
+        out,
         "  Free:           {} MB",
         info.free_bytes / (1024 * 1024)
     );
     let _ = writeln!(
-        s,
+        out,
         "  Used:           {} MB",
         (info.total_bytes - info.free_bytes) / (1024 * 1024)
     );
     let _ = writeln!(
-        s,
+        out,
         "  Boot Services:  {} KB",
         info.boot_services_bytes / 1024
     );
-    let _ = writeln!(s, "  Runtime:        {} KB", info.runtime_bytes / 1024);
-    s
+    let _ = writeln!(out, "  Runtime:        {} KB", info.runtime_bytes / 1024);
 }
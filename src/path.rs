@@ -0,0 +1,200 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// Path separator on UEFI volumes; `/` is accepted as an alternative.
+pub const SEPARATOR: char = '\\';
+
+/// The root of the boot volume. Other volumes are addressed with a prefix
+/// such as `fs1:\`.
+pub const ROOT: &str = "\\";
+
+fn is_separator(c: char) -> bool {
+    c == '\\' || c == '/'
+}
+
+/// Split a volume prefix such as `fs1:` off a path, returning the volume
+/// name without the colon.
+pub fn split_volume(path: &str) -> (Option<&str>, &str) {
+    match path.find(':') {
+        Some(i) if i > 0 && path[..i].chars().all(|c| c.is_ascii_alphanumeric()) => {
+            (Some(&path[..i]), &path[i + 1..])
+        }
+        _ => (None, path),
+    }
+}
+
+/// The `fs1:` prefix of a path, or `""` for the boot volume.
+fn volume_prefix(path: &str) -> &str {
+    match split_volume(path) {
+        (Some(volume), _) => &path[..volume.len() + 1],
+        (None, _) => "",
+    }
+}
+
+pub fn is_absolute(path: &str) -> bool {
+    match split_volume(path) {
+        (Some(_), _) => true,
+        (None, rest) => rest.starts_with(is_separator),
+    }
+}
+
+/// True if `path` is the root of a volume.
+pub fn is_root(path: &str) -> bool {
+    let (_, rest) = split_volume(path);
+    rest.chars().all(is_separator)
+}
+
+/// Turn `path` into an absolute path: `/` becomes `\`, repeated
+/// separators collapse, and `.` and `..` components are resolved (`..`
+/// stops at the root). A volume prefix is kept, in lower case.
+pub fn normalize(path: &str) -> String {
+    let (volume, path) = split_volume(path);
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(is_separator) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    let mut out = String::new();
+    for part in &parts {
+        out.push(SEPARATOR);
+        out.push_str(part);
+    }
+    if out.is_empty() {
+        out.push(SEPARATOR);
+    }
+    match volume {
+        Some(volume) => format!("{}:{}", volume.to_ascii_lowercase(), out),
+        None => out,
+    }
+}
+
+/// Resolve `path` against the directory `cwd`. Paths with a volume prefix
+/// ignore `cwd`; other absolute paths stay on the volume of `cwd`.
+pub fn resolve(cwd: &str, path: &str) -> String {
+    if split_volume(path).0.is_some() {
+        normalize(path)
+    } else if is_absolute(path) {
+        normalize(&format!("{}{}", volume_prefix(cwd), path))
+    } else {
+        normalize(&join(cwd, path))
+    }
+}
+
+/// Append `name` to `dir` with exactly one separator between them.
+pub fn join(dir: &str, name: &str) -> String {
+    let mut out = String::from(dir.trim_end_matches(is_separator));
+    out.push(SEPARATOR);
+    out.push_str(name.trim_start_matches(is_separator));
+    out
+}
+
+/// True if the normalized `path` is `dir` or lies inside it (ASCII
+/// case-insensitive, as FAT names are).
+pub fn is_within(path: &str, dir: &str) -> bool {
+    let dir = dir.trim_end_matches(is_separator);
+    path.len() >= dir.len()
+        && path.is_char_boundary(dir.len())
+        && path[..dir.len()].eq_ignore_ascii_case(dir)
+        && (path.len() == dir.len() || path[dir.len()..].starts_with(is_separator))
+}
+
+/// The last component of a path, or the path itself if it has none.
+pub fn file_name(path: &str) -> &str {
+    path.trim_end_matches(is_separator)
+        .rsplit(is_separator)
+        .next()
+        .filter(|name| !name.is_empty())
+        .unwrap_or(path)
+}
+
+/// Everything before the last component of a normalized path; a root's
+/// parent is that root.
+pub fn parent(path: &str) -> &str {
+    let prefix = volume_prefix(path);
+    match path[prefix.len()..]
+        .trim_end_matches(is_separator)
+        .rfind(is_separator)
+    {
+        Some(0) | None if prefix.is_empty() => ROOT,
+        Some(0) | None => &path[..(prefix.len() + 1).min(path.len())],
+        Some(i) => &path[..prefix.len() + i],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_resolves_dots_and_separators() {
+        assert_eq!(normalize("/EFI//BOOT/."), "\\EFI\\BOOT");
+        assert_eq!(normalize("a\\b\\"), "\\a\\b");
+        assert_eq!(normalize(""), "\\");
+        // `..` stops at the root
+        assert_eq!(normalize("\\a\\..\\..\\b"), "\\b");
+        assert_eq!(normalize("FS1:\\a\\..\\.."), "fs1:\\");
+        assert_eq!(normalize("fs0:"), "fs0:\\");
+    }
+
+    #[test]
+    fn volume_prefixes() {
+        assert_eq!(split_volume("fs1:\\a"), (Some("fs1"), "\\a"));
+        assert_eq!(split_volume("\\a:b"), (None, "\\a:b"));
+        assert_eq!(split_volume(":x"), (None, ":x"));
+        assert!(is_absolute("fs1:a"));
+        assert!(!is_absolute("a\\b"));
+        assert!(is_root("fs1:"));
+        assert!(is_root("fs1:\\"));
+        assert!(is_root("\\"));
+        assert!(!is_root("\\a"));
+    }
+
+    #[test]
+    fn resolve_against_the_current_directory() {
+        assert_eq!(resolve("\\EFI", "BOOT\\"), "\\EFI\\BOOT");
+        assert_eq!(resolve("\\a", "\\b"), "\\b");
+        assert_eq!(resolve("\\a", ".."), "\\");
+        // Absolute paths stay on the current volume unless they name one
+        assert_eq!(resolve("fs1:\\dir", "\\x"), "fs1:\\x");
+        assert_eq!(resolve("fs1:\\dir", "..\\..\\y"), "fs1:\\y");
+        assert_eq!(resolve("fs1:\\dir", "FS0:a"), "fs0:\\a");
+    }
+
+    #[test]
+    fn join_uses_one_separator() {
+        assert_eq!(join("\\", "a"), "\\a");
+        assert_eq!(join("\\dir\\", "\\f"), "\\dir\\f");
+        assert_eq!(join("fs1:\\", "x"), "fs1:\\x");
+    }
+
+    #[test]
+    fn parent_and_file_name() {
+        assert_eq!(parent("\\a\\b"), "\\a");
+        assert_eq!(parent("\\a"), "\\");
+        assert_eq!(parent("\\"), "\\");
+        assert_eq!(parent("fs1:\\a"), "fs1:\\");
+        assert_eq!(parent("fs1:\\"), "fs1:\\");
+        assert_eq!(parent("fs1:\\a\\b\\"), "fs1:\\a");
+        assert_eq!(file_name("\\a\\b\\"), "b");
+        assert_eq!(file_name("fs1:\\x.txt"), "x.txt");
+        assert_eq!(file_name("\\"), "\\");
+    }
+
+    #[test]
+    fn is_within_ignores_case() {
+        assert!(is_within("\\EFI\\Boot\\x.efi", "\\efi\\BOOT"));
+        assert!(is_within("\\EFI", "\\EFI\\"));
+        assert!(is_within("\\anything", "\\"));
+        assert!(is_within("fs1:\\a", "fs1:\\"));
+        assert!(!is_within("\\EFI\\BOOTX", "\\EFI\\BOOT"));
+        assert!(!is_within("\\EFI", "\\EFI\\BOOT"));
+    }
+}
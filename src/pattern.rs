@@ -0,0 +1,505 @@
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter::Peekable;
+use core::str::Chars;
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    /// `.` or `?` in a glob: any character.
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    /// `^`
+    Start,
+    /// `$`
+    End,
+    /// `(a|b)`: alternative sequences.
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+const DIGIT: &[(char, char)] = &[('0', '9')];
+const WORD: &[(char, char)] = &[('0', '9'), ('A', 'Z'), ('_', '_'), ('a', 'z')];
+const SPACE: &[(char, char)] = &[(' ', ' '), ('\t', '\r')];
+
+/// Most times a repeated group is matched. Each repetition nests the
+/// backtracking one level deeper, so this bounds the stack it uses; single
+/// characters repeat without a limit.
+const MAX_GROUP_REPEAT: usize = 64;
+
+/// A compiled regular expression or glob.
+///
+/// Supports literals, `.`, `[...]` classes with ranges and `^` negation,
+/// `\d`/`\w`/`\s` (and `\D`/`\W`/`\S`), the anchors `^` and `$`, groups
+/// with `|` alternation, and the greedy `*`, `+` and `?` quantifiers.
+/// Matching backtracks, which is plenty for file names and log lines; a
+/// repeated group matches at most 64 times.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    /// The whole pattern, as a group of its alternatives.
+    root: Node,
+    ignore_case: bool,
+}
+
+impl Regex {
+    /// Compile a regular expression.
+    pub fn new(pattern: &str, ignore_case: bool) -> Result<Self, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().peekable(),
+        };
+        let alternatives = parser.alternation()?;
+        if parser.chars.next().is_some() {
+            return Err(String::from("unmatched ')'"));
+        }
+        Ok(Self {
+            root: Node::Group(alternatives),
+            ignore_case,
+        })
+    }
+
+    /// Compile a shell glob (`*`, `?`, `[...]` with `!` or `^` negation)
+    /// that must match the whole text.
+    pub fn glob(pattern: &str, ignore_case: bool) -> Result<Self, String> {
+        let mut nodes = vec![Node::Start];
+        let mut chars = pattern.chars().peekable();
+        while let Some(c) = chars.next() {
+            nodes.push(match c {
+                '*' => Node::Repeat {
+                    node: Box::new(Node::Any),
+                    min: 0,
+                    max: None,
+                },
+                '?' => Node::Any,
+                '[' => class(&mut chars, true)?,
+                '\\' => Node::Char(chars.next().unwrap_or('\\')),
+                c => Node::Char(c),
+            });
+        }
+        nodes.push(Node::End);
+        Ok(Self {
+            root: Node::Group(vec![nodes]),
+            ignore_case,
+        })
+    }
+
+    /// True if the pattern matches anywhere in `text`.
+    pub fn is_match(&self, text: &str) -> bool {
+        let chars: Vec<char> = text.chars().collect();
+        let root = core::slice::from_ref(&self.root);
+        (0..=chars.len()).any(|start| self.match_here(root, &chars, start, &mut |_| true))
+    }
+
+    /// Match `nodes` at `pos`, then call `next` with the end position;
+    /// backtracks until `next` accepts.
+    fn match_here(
+        &self,
+        nodes: &[Node],
+        text: &[char],
+        pos: usize,
+        next: &mut dyn FnMut(usize) -> bool,
+    ) -> bool {
+        let Some((node, rest)) = nodes.split_first() else {
+            return next(pos);
+        };
+        match node {
+            Node::Group(alternatives) => alternatives.iter().any(|alternative| {
+                self.match_here(alternative, text, pos, &mut |end| {
+                    self.match_here(rest, text, end, next)
+                })
+            }),
+            Node::Repeat { node, min, max } => match **node {
+                Node::Char(_) | Node::Any | Node::Class { .. } => {
+                    self.match_run(node, (*min, *max), rest, text, pos, next)
+                }
+                _ => self.match_repeat(node, (*min, *max), 0, rest, text, pos, next),
+            },
+            _ => self
+                .match_one(node, text, pos)
+                .is_some_and(|end| self.match_here(rest, text, end, next)),
+        }
+    }
+
+    /// Greedy repetition of a single-character node without recursion: take
+    /// as many characters as it matches, then give them back one at a time.
+    fn match_run(
+        &self,
+        node: &Node,
+        (min, max): (usize, Option<usize>),
+        rest: &[Node],
+        text: &[char],
+        pos: usize,
+        next: &mut dyn FnMut(usize) -> bool,
+    ) -> bool {
+        let mut count = 0;
+        while max.is_none_or(|max| count < max) && self.match_one(node, text, pos + count).is_some()
+        {
+            count += 1;
+        }
+        (min..=count)
+            .rev()
+            .any(|count| self.match_here(rest, text, pos + count, next))
+    }
+
+    /// Greedy repetition: try one more `node` first, then the rest.
+    #[allow(clippy::too_many_arguments)]
+    fn match_repeat(
+        &self,
+        node: &Node,
+        (min, max): (usize, Option<usize>),
+        count: usize,
+        rest: &[Node],
+        text: &[char],
+        pos: usize,
+        next: &mut dyn FnMut(usize) -> bool,
+    ) -> bool {
+        if count < MAX_GROUP_REPEAT && max.is_none_or(|max| count < max) {
+            let more = self.match_here(core::slice::from_ref(node), text, pos, &mut |end| {
+                // Repeating an empty match would loop forever, but it may
+                // stand in for any repetitions still required
+                if end == pos {
+                    return self.match_here(rest, text, end, next);
+                }
+                self.match_repeat(node, (min, max), count + 1, rest, text, end, next)
+            });
+            if more {
+                return true;
+            }
+        }
+        count >= min && self.match_here(rest, text, pos, next)
+    }
+
+    /// Match a single-character node or an anchor; returns the end position.
+    fn match_one(&self, node: &Node, text: &[char], pos: usize) -> Option<usize> {
+        match node {
+            Node::Start => (pos == 0).then_some(pos),
+            Node::End => (pos == text.len()).then_some(pos),
+            _ => {
+                let &c = text.get(pos)?;
+                let matched = match node {
+                    Node::Char(expected) if self.ignore_case => c.eq_ignore_ascii_case(expected),
+                    Node::Char(expected) => c == *expected,
+                    Node::Any => true,
+                    Node::Class { ranges, negated } => self.in_class(ranges, c) != *negated,
+                    _ => false,
+                };
+                matched.then_some(pos + 1)
+            }
+        }
+    }
+
+    fn in_class(&self, ranges: &[(char, char)], c: char) -> bool {
+        let contains = |c: char| ranges.iter().any(|&(lo, hi)| lo <= c && c <= hi);
+        contains(c)
+            || (self.ignore_case
+                && (contains(c.to_ascii_lowercase()) || contains(c.to_ascii_uppercase())))
+    }
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    /// Sequences separated by `|`, up to `)` or the end.
+    fn alternation(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.chars.next_if_eq(&'|').is_some() {
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut nodes = Vec::new();
+        while let Some(&c) = self.chars.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            self.chars.next();
+            let node = match c {
+                '*' | '+' | '?' => {
+                    let node = match nodes.pop() {
+                        Some(Node::Start | Node::End) | None => {
+                            return Err(format!("nothing to repeat before '{}'", c))
+                        }
+                        Some(node) => node,
+                    };
+                    let (min, max) = match c {
+                        '*' => (0, None),
+                        '+' => (1, None),
+                        _ => (0, Some(1)),
+                    };
+                    Node::Repeat {
+                        node: Box::new(node),
+                        min,
+                        max,
+                    }
+                }
+                '.' => Node::Any,
+                '^' => Node::Start,
+                '$' => Node::End,
+                '(' => {
+                    let alternatives = self.alternation()?;
+                    if self.chars.next() != Some(')') {
+                        return Err(String::from("unmatched '('"));
+                    }
+                    Node::Group(alternatives)
+                }
+                '[' => class(&mut self.chars, false)?,
+                '\\' => escape(self.chars.next().ok_or("trailing '\\'")?),
+                c => Node::Char(c),
+            };
+            nodes.push(node);
+        }
+        Ok(nodes)
+    }
+}
+
+/// The node for `\c` outside a class.
+fn escape(c: char) -> Node {
+    let (ranges, negated) = match c {
+        'd' | 'D' => (DIGIT, c == 'D'),
+        'w' | 'W' => (WORD, c == 'W'),
+        's' | 'S' => (SPACE, c == 'S'),
+        c => return Node::Char(c),
+    };
+    Node::Class {
+        ranges: ranges.to_vec(),
+        negated,
+    }
+}
+
+/// Every character outside the ASCII `ranges`, for `\D` and the like
+/// inside a class.
+fn complement(ranges: &[(char, char)]) -> Vec<(char, char)> {
+    let mut sorted = ranges.to_vec();
+    sorted.sort();
+    let mut out = Vec::new();
+    let mut next = 0u8;
+    for (lo, hi) in sorted {
+        if lo as u8 > next {
+            out.push((next as char, (lo as u8 - 1) as char));
+        }
+        next = hi as u8 + 1;
+    }
+    out.push((next as char, char::MAX));
+    out
+}
+
+/// Parse a class after its `[`. Globs also accept `!` for negation.
+fn class(chars: &mut Peekable<Chars>, glob: bool) -> Result<Node, String> {
+    let negated = chars.next_if(|&c| c == '^' || (glob && c == '!')).is_some();
+    let mut ranges = Vec::new();
+    let mut first = true;
+    loop {
+        let c = chars.next().ok_or("unmatched '['")?;
+        if c == ']' && !first {
+            break;
+        }
+        first = false;
+        let lo = match c {
+            '\\' => match chars.next().ok_or("unmatched '['")? {
+                e @ ('d' | 'w' | 's' | 'D' | 'W' | 'S') if !glob => {
+                    if let Node::Class {
+                        ranges: more,
+                        negated,
+                    } = escape(e)
+                    {
+                        ranges.extend(if negated { complement(&more) } else { more });
+                    }
+                    continue;
+                }
+                e => e,
+            },
+            c => c,
+        };
+        // `a-z`, but a `-` before the closing `]` is literal
+        let mut ahead = chars.clone();
+        let hi = match (ahead.next(), ahead.next()) {
+            (Some('-'), Some(hi)) if hi != ']' => {
+                chars.next();
+                chars.next();
+                hi
+            }
+            _ => lo,
+        };
+        if hi < lo {
+            return Err(format!("invalid range '{}-{}'", lo, hi));
+        }
+        ranges.push((lo, hi));
+    }
+    Ok(Node::Class { ranges, negated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    fn re(pattern: &str) -> Regex {
+        Regex::new(pattern, false).unwrap()
+    }
+
+    fn glob(pattern: &str) -> Regex {
+        Regex::glob(pattern, false).unwrap()
+    }
+
+    #[test]
+    fn literals_match_anywhere() {
+        assert!(re("boot").is_match("\\EFI\\BOOT\\boot.efi"));
+        assert!(!re("boot").is_match("BOOT"));
+        assert!(Regex::new("boot", true).unwrap().is_match("BOOT"));
+        assert!(re("").is_match(""));
+        assert!(re("a.c").is_match("xabcx"));
+        assert!(!re("a.c").is_match("ac"));
+    }
+
+    #[test]
+    fn classes_and_ranges() {
+        let hex = re("^[0-9a-fA-F]+$");
+        assert!(hex.is_match("00ff7A"));
+        assert!(!hex.is_match("00fg"));
+        assert!(re("^[^0-9]$").is_match("x"));
+        assert!(!re("^[^0-9]$").is_match("5"));
+        // `]` first and `-` last are literal
+        assert!(re("^[]x-]+$").is_match("]-x"));
+        assert!(re("^[\\d_]+$").is_match("1_2"));
+        // Negated shorthands inside a class
+        assert!(re("^[\\D]+$").is_match("ab-Ü"));
+        assert!(!re("[\\D]").is_match("123"));
+        assert!(re("^[\\W\\d]+$").is_match("1-2 3"));
+        assert!(!re("[\\W\\d]").is_match("ab_"));
+        assert!(re("^[^\\S]+$").is_match(" \t"));
+        assert!(!re("^[^\\S]+$").is_match(" x"));
+        assert!(Regex::new("^[a-c]+$", true).unwrap().is_match("AbC"));
+    }
+
+    #[test]
+    fn shorthand_classes() {
+        assert!(re("^\\d\\w\\s$").is_match("1a "));
+        assert!(re("^\\w+$").is_match("File_1"));
+        assert!(!re("\\w").is_match("-+."));
+        assert!(re("^\\D\\W\\S$").is_match("a-x"));
+        assert!(!re("\\D").is_match("123"));
+        assert!(!re("\\S").is_match(" \t"));
+        assert!(re("a\\.b").is_match("a.b"));
+        assert!(!re("a\\.b").is_match("axb"));
+    }
+
+    #[test]
+    fn anchors() {
+        assert!(re("^ls").is_match("ls -l"));
+        assert!(!re("^ls").is_match("cls"));
+        assert!(re("txt$").is_match("a.txt"));
+        assert!(!re("txt$").is_match("a.txt.bak"));
+        assert!(re("^$").is_match(""));
+        assert!(!re("^$").is_match(" "));
+    }
+
+    #[test]
+    fn alternation_and_groups() {
+        let ext = re("\\.(efi|EFI)$");
+        assert!(ext.is_match("BOOTX64.EFI"));
+        assert!(ext.is_match("shell.efi"));
+        assert!(!ext.is_match("shell.efi.txt"));
+        assert!(re("^cat|dog$").is_match("hotdog"));
+        assert!(re("^(ab)+$").is_match("ababab"));
+        assert!(!re("^(ab)+$").is_match("aba"));
+        assert!(re("^(a|bc)*d$").is_match("abcad"));
+    }
+
+    #[test]
+    fn quantifiers() {
+        assert!(re("^ab*c$").is_match("ac"));
+        assert!(re("^ab*c$").is_match("abbbc"));
+        assert!(!re("^ab+c$").is_match("ac"));
+        assert!(re("^ab+c$").is_match("abc"));
+        assert!(re("^colou?r$").is_match("color"));
+        assert!(re("^colou?r$").is_match("colour"));
+        assert!(!re("^colou?r$").is_match("colouur"));
+        // Greedy runs give characters back for the rest of the pattern
+        assert!(re("^.*\\.txt$").is_match("a.txt.txt"));
+        assert!(re("^a*ab$").is_match("aaab"));
+    }
+
+    #[test]
+    fn empty_repeats_terminate() {
+        assert!(re("^(a*)*b$").is_match("aaab"));
+        assert!(!re("^(a*)*b$").is_match("aaac"));
+        assert!(re("^(a?)+$").is_match(""));
+        assert!(re("(x*)*").is_match("yyy"));
+    }
+
+    #[test]
+    fn long_lines() {
+        let line: String = core::iter::repeat_n('a', 200_000).chain(['x']).collect();
+        assert!(re("^a*x$").is_match(&line));
+        assert!(re("a.*x").is_match(&line));
+        assert!(!re("^a*y$").is_match(&line[..1000]));
+        // Groups repeat a bounded number of times
+        let pairs = "ab".repeat(MAX_GROUP_REPEAT);
+        assert!(re("^(ab)*$").is_match(&pairs));
+        assert!(!re("^(ab)*$").is_match(&(pairs + "ab")));
+    }
+
+    #[test]
+    fn globs_match_whole_names() {
+        assert!(glob("*.efi").is_match("shell.efi"));
+        assert!(!glob("*.efi").is_match("shell.efi.bak"));
+        assert!(!glob("efi").is_match("shell.efi"));
+        assert!(glob("*").is_match(""));
+        assert!(glob("boot??.efi").is_match("bootx6.efi"));
+        assert!(!glob("boot?.efi").is_match("boot.efi"));
+        // Regex characters are literal
+        assert!(glob("a.b(c)+").is_match("a.b(c)+"));
+        assert!(!glob("a.b").is_match("axb"));
+        assert!(glob("\\*").is_match("*"));
+        assert!(!glob("\\*").is_match("x"));
+    }
+
+    #[test]
+    fn glob_classes() {
+        assert!(glob("[abc]*").is_match("boot"));
+        assert!(!glob("[abc]*").is_match("efi"));
+        assert!(glob("[!abc]*").is_match("efi"));
+        assert!(glob("[^abc]*").is_match("efi"));
+        assert!(!glob("[!a-z]*").is_match("efi"));
+        assert!(glob("file[0-9].txt").is_match("file7.txt"));
+    }
+
+    #[test]
+    fn iname_folds_case() {
+        let iname = Regex::glob("*.EFI", true).unwrap();
+        assert!(iname.is_match("bootx64.efi"));
+        assert!(iname.is_match("Shell.Efi"));
+        assert!(!glob("*.EFI").is_match("bootx64.efi"));
+        assert!(Regex::glob("[A-C]*", true).unwrap().is_match("boot"));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |pattern: &str| Regex::new(pattern, false).unwrap_err();
+        assert_eq!(error("[abc"), "unmatched '['");
+        assert_eq!(error("a[\\"), "unmatched '['");
+        assert_eq!(Regex::glob("[ab", false).unwrap_err(), "unmatched '['");
+        assert_eq!(error("*a"), "nothing to repeat before '*'");
+        assert_eq!(error("^+"), "nothing to repeat before '+'");
+        assert_eq!(error("(|?)"), "nothing to repeat before '?'");
+        assert_eq!(error("(ab"), "unmatched '('");
+        assert_eq!(error("ab)"), "unmatched ')'");
+        assert_eq!(error("[z-a]"), "invalid range 'z-a'");
+        assert_eq!(error("a\\"), "trailing '\\'".to_string());
+    }
+}
@@ -0,0 +1,127 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::{launch, Shell};
+use crate::bootmgr::{self, LoadOption};
+use crate::console::Console;
+
+/// The boot manager's variables that flag options in `bootorder`.
+struct BootState {
+    order: Vec<u16>,
+    current: Option<u16>,
+    next: Option<u16>,
+}
+
+fn flags(option: &LoadOption, state: &BootState) -> String {
+    let mut flags = String::from(if option.active() { "on" } else { "off" });
+    if option.hidden() {
+        flags.push_str(" hidden");
+    }
+    if !state.order.contains(&option.number) {
+        flags.push_str(" unlisted");
+    }
+    if state.current == Some(option.number) {
+        flags.push_str(" current");
+    }
+    if state.next == Some(option.number) {
+        flags.push_str(" next");
+    }
+    flags
+}
+
+/// `bootorder [option...]`: list the firmware's load options in boot order,
+/// then the ones `BootOrder` leaves out. With options (`0003` or
+/// `Boot0003`), move them to the front in the order given.
+pub fn cmd_bootorder(
+    _sh: &mut Shell,
+    args: &[String],
+    out: &mut dyn Console,
+) -> Result<(), String> {
+    if !args.is_empty() {
+        let mut first = Vec::new();
+        for arg in args {
+            let number = bootmgr::parse_option_number(arg)?;
+            bootmgr::load_option(number)?;
+            if first.contains(&number) {
+                return Err(format!("Boot{:04X} is named twice", number));
+            }
+            first.push(number);
+        }
+        let mut order = first.clone();
+        order.extend(
+            bootmgr::boot_order()?
+                .into_iter()
+                .filter(|n| !first.contains(n)),
+        );
+        bootmgr::set_boot_order(&order)?;
+    }
+
+    let state = BootState {
+        order: bootmgr::boot_order()?,
+        current: bootmgr::boot_current(),
+        next: bootmgr::boot_next(),
+    };
+    let _ = writeln!(
+        out,
+        "{:<8}  {:<20}  {:<24}  Device",
+        "Option", "Flags", "Description"
+    );
+    for option in bootmgr::load_options()? {
+        let _ = writeln!(
+            out,
+            "{:<8}  {:<20}  {:<24}  {}",
+            option.name(),
+            flags(&option, &state),
+            option.description,
+            option.device_text()
+        );
+    }
+    Ok(())
+}
+
+/// `bootnext [option|-d]`: show or set the option the firmware boots once
+/// at the next start, or clear it with `-d`.
+pub fn cmd_bootnext(_sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    match args {
+        [] => match bootmgr::boot_next() {
+            Some(number) => {
+                let description = bootmgr::load_option(number)
+                    .map(|o| o.description)
+                    .unwrap_or_default();
+                let _ = writeln!(out, "BootNext: Boot{:04X} {}", number, description);
+            }
+            None => {
+                let _ = writeln!(out, "BootNext is not set");
+            }
+        },
+        [flag] if flag == "-d" => bootmgr::set_boot_next(None)?,
+        [arg] => {
+            let option = bootmgr::load_option(bootmgr::parse_option_number(arg)?)?;
+            bootmgr::set_boot_next(Some(option.number))?;
+            let _ = writeln!(
+                out,
+                "Next boot: {} {} (reboot to start it)",
+                option.name(),
+                option.description
+            );
+        }
+        _ => return Err(String::from("Usage: bootnext [option|-d]")),
+    }
+    Ok(())
+}
+
+/// `boot <option>`: chainload a load option's loader now, as the
+/// firmware's boot manager would, passing it the option's data.
+pub fn cmd_boot(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let [arg] = args else {
+        return Err(String::from("Usage: boot <option>"));
+    };
+    launch::check_console(out)?;
+    let option = bootmgr::load_option(bootmgr::parse_option_number(arg)?)?;
+    let image = bootmgr::load(&option)?;
+    let name = format!("{} ({})", option.name(), option.description);
+    launch::start(sh, image, &name, &option.optional_data, out)
+}
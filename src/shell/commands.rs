@@ -0,0 +1,434 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use uefi::runtime::ResetType;
+use uefi::Status;
+
+use super::Shell;
+use crate::console::Console;
+
+pub type Handler = fn(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String>;
+
+pub struct Command {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub description: &'static str,
+    pub handler: Handler,
+}
+
+pub static COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        usage: "help [command]",
+        description: "show this message",
+        handler: cmd_help,
+    },
+    Command {
+        name: "echo",
+        usage: "echo [text]",
+        description: "echo text back",
+        handler: cmd_echo,
+    },
+    Command {
+        name: "clear",
+        usage: "clear",
+        description: "clear screen",
+        handler: cmd_clear,
+    },
+    Command {
+        name: "info",
+        usage: "info",
+        description: "show system info",
+        handler: cmd_info,
+    },
+    Command {
+        name: "mem",
+        usage: "mem",
+        description: "show memory info",
+        handler: cmd_mem,
+    },
+    Command {
+        name: "history",
+        usage: "history [-c]",
+        description: "show or clear command history",
+        handler: cmd_history,
+    },
+    Command {
+        name: "cd",
+        usage: "cd [dir]",
+        description: "change directory (default: root)",
+        handler: super::dirs::cmd_cd,
+    },
+    Command {
+        name: "pwd",
+        usage: "pwd",
+        description: "print current directory",
+        handler: super::dirs::cmd_pwd,
+    },
+    Command {
+        name: "pushd",
+        usage: "pushd <dir>",
+        description: "save current directory and change to dir",
+        handler: super::dirs::cmd_pushd,
+    },
+    Command {
+        name: "popd",
+        usage: "popd",
+        description: "return to the directory saved by pushd",
+        handler: super::dirs::cmd_popd,
+    },
+    Command {
+        name: "vol",
+        usage: "vol",
+        description: "list volumes (address them as fsN:\\path)",
+        handler: crate::fs::cmd_vol,
+    },
+    Command {
+        name: "mounts",
+        usage: "mounts",
+        description: "list mounted file systems",
+        handler: crate::fs::cmd_mounts,
+    },
+    Command {
+        name: "mount",
+        usage: "mount [<image|diskNpM> <dir>]",
+        description: "mount a FAT disk image or partition at a directory",
+        handler: crate::fs::cmd_mount,
+    },
+    Command {
+        name: "umount",
+        usage: "umount <dir>",
+        description: "unmount a mounted disk image",
+        handler: crate::fs::cmd_umount,
+    },
+    Command {
+        name: "disks",
+        usage: "disks",
+        description: "list disks with their block size and flags",
+        handler: super::disks::cmd_disks,
+    },
+    Command {
+        name: "parts",
+        usage: "parts [disk...]",
+        description: "list GPT or MBR partitions of disks",
+        handler: super::disks::cmd_parts,
+    },
+    Command {
+        name: "readblk",
+        usage: "readblk <disk|diskNpM> <lba> [count]",
+        description: "hex dump blocks read straight from a disk or partition",
+        handler: super::disks::cmd_readblk,
+    },
+    Command {
+        name: "ls",
+        usage: "ls [-1alStr] [path]",
+        description: "list directory (-l long, -a all, -t/-S sort by time/size, -r reverse)",
+        handler: crate::fs::cmd_ls,
+    },
+    Command {
+        name: "stat",
+        usage: "stat <path...>",
+        description: "show size, attributes and timestamps",
+        handler: crate::fs::cmd_stat,
+    },
+    Command {
+        name: "cat",
+        usage: "cat [-p] [file...]",
+        description: "print files or piped input (-p: page the output)",
+        handler: super::view::cmd_cat,
+    },
+    Command {
+        name: "head",
+        usage: "head [-n lines] [file]",
+        description: "print the first lines (default 10)",
+        handler: super::view::cmd_head,
+    },
+    Command {
+        name: "tail",
+        usage: "tail [-n lines] [file]",
+        description: "print the last lines (default 10)",
+        handler: super::view::cmd_tail,
+    },
+    Command {
+        name: "wc",
+        usage: "wc [-lwc] [file...]",
+        description: "count lines, words and bytes",
+        handler: super::view::cmd_wc,
+    },
+    Command {
+        name: "hexdump",
+        usage: "hexdump [-s offset] [-n length] [file]",
+        description: "hex and text dump of a file or piped input",
+        handler: super::view::cmd_hexdump,
+    },
+    Command {
+        name: "xxd",
+        usage: "xxd [-s offset] [-l length] [file]",
+        description: "hex dump in xxd layout",
+        handler: super::view::cmd_xxd,
+    },
+    Command {
+        name: "find",
+        usage: "find [dir] [-name glob | -iname glob] [-type f|d]",
+        description: "list files below dir matching a name pattern",
+        handler: super::search::cmd_find,
+    },
+    Command {
+        name: "grep",
+        usage: "grep [-inr] <pattern> [path...]",
+        description: "print lines matching a regex (-i ignore case, -n numbers, -r recurse)",
+        handler: super::search::cmd_grep,
+    },
+    Command {
+        name: "write",
+        usage: "write [-e] <file> [text...]",
+        description: "replace file with text or piped input (-e: decode \\xHH escapes)",
+        handler: crate::fs::cmd_write,
+    },
+    Command {
+        name: "append",
+        usage: "append [-e] <file> [text...]",
+        description: "append text or piped input to a file",
+        handler: crate::fs::cmd_append,
+    },
+    Command {
+        name: "mkdir",
+        usage: "mkdir <dir>",
+        description: "create directory",
+        handler: crate::fs::cmd_mkdir,
+    },
+    Command {
+        name: "rm",
+        usage: "rm [-r] [-f] <path...>",
+        description: "delete files, or directories with -r (-f: force)",
+        handler: crate::fs::cmd_rm,
+    },
+    Command {
+        name: "rmdir",
+        usage: "rmdir <dir...>",
+        description: "delete empty directories",
+        handler: crate::fs::cmd_rmdir,
+    },
+    Command {
+        name: "attrib",
+        usage: "attrib [+r|-r|+h|-h|+s|-s|+a|-a...] <path...>",
+        description: "show or change read-only, hidden, system and archive attributes",
+        handler: crate::fs::cmd_attrib,
+    },
+    Command {
+        name: "touch",
+        usage: "touch <path...>",
+        description: "create files or update their modification time",
+        handler: crate::fs::cmd_touch,
+    },
+    Command {
+        name: "cp",
+        usage: "cp [-r] <src> <dst>",
+        description: "copy a file, or a directory with -r",
+        handler: crate::fs::cmd_cp,
+    },
+    Command {
+        name: "mv",
+        usage: "mv [-f] <src> <dst>",
+        description: "move or rename a file or directory",
+        handler: crate::fs::cmd_mv,
+    },
+    Command {
+        name: "set",
+        usage: "set [-e|+e] [NAME=value...]",
+        description: "show or set variables and options (-e: stop scripts on error)",
+        handler: super::vars::cmd_set,
+    },
+    Command {
+        name: "unset",
+        usage: "unset <name...>",
+        description: "remove variables",
+        handler: super::vars::cmd_unset,
+    },
+    Command {
+        name: "export",
+        usage: "export [NAME[=value]...]",
+        description: "pass variables on to scripts started with run",
+        handler: super::vars::cmd_export,
+    },
+    Command {
+        name: "env",
+        usage: "env",
+        description: "show built-in and exported variables",
+        handler: super::vars::cmd_env,
+    },
+    Command {
+        name: "test",
+        usage: "test <expr>",
+        description: "check files (-e -f -d -s), strings (-z -n = !=) or integers (-eq -lt ...)",
+        handler: super::condition::cmd_test,
+    },
+    Command {
+        name: "[",
+        usage: "[ <expr> ]",
+        description: "same as test",
+        handler: super::condition::cmd_bracket,
+    },
+    Command {
+        name: "exists",
+        usage: "exists <path>",
+        description: "succeed if a file or directory exists",
+        handler: super::condition::cmd_exists,
+    },
+    Command {
+        name: "true",
+        usage: "true",
+        description: "succeed",
+        handler: super::condition::cmd_true,
+    },
+    Command {
+        name: "false",
+        usage: "false",
+        description: "fail silently",
+        handler: super::condition::cmd_false,
+    },
+    Command {
+        name: "source",
+        usage: "source <file>",
+        description: "run a script in the current shell",
+        handler: super::script::cmd_source,
+    },
+    Command {
+        name: "run",
+        usage: "run <file>",
+        description: "run a script",
+        handler: super::script::cmd_run,
+    },
+    Command {
+        name: "exec",
+        usage: "exec <path.efi> [args...]",
+        description: "run a UEFI application and return to VOS",
+        handler: super::launch::cmd_exec,
+    },
+    Command {
+        name: "bootorder",
+        usage: "bootorder [option...]",
+        description: "list boot options, or move the given ones first",
+        handler: super::bootopts::cmd_bootorder,
+    },
+    Command {
+        name: "bootnext",
+        usage: "bootnext [option|-d]",
+        description: "show, set or clear the option booted next time",
+        handler: super::bootopts::cmd_bootnext,
+    },
+    Command {
+        name: "boot",
+        usage: "boot <option>",
+        description: "chainload a boot option's loader now",
+        handler: super::bootopts::cmd_boot,
+    },
+    Command {
+        name: "reboot",
+        usage: "reboot",
+        description: "reboot the system",
+        handler: cmd_reboot,
+    },
+];
+
+pub fn find(name: &str) -> Option<&'static Command> {
+    COMMANDS.iter().find(|c| c.name == name)
+}
+
+/// Look up `argv[0]` in the registry and run it with the remaining words.
+pub fn dispatch(sh: &mut Shell, argv: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let Some((name, args)) = argv.split_first() else {
+        return Ok(());
+    };
+    let cmd = find(name).ok_or_else(|| format!("Unknown command: {}", name))?;
+    (cmd.handler)(sh, args, out)
+}
+
+pub fn reboot() -> ! {
+    uefi::runtime::reset(ResetType::COLD, Status::SUCCESS, None);
+}
+
+// ── Built-in commands ──
+
+fn cmd_help(_sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    if let Some(name) = args.first() {
+        let cmd = find(name).ok_or_else(|| format!("No help for '{}'", name))?;
+        let _ = writeln!(out, "Usage: {}", cmd.usage);
+        let _ = writeln!(out, "  {}", cmd.description);
+        return Ok(());
+    }
+
+    let width = COMMANDS.iter().map(|c| c.name.len()).max().unwrap_or(0);
+    let _ = writeln!(out, "Available commands:");
+    for cmd in COMMANDS {
+        let _ = writeln!(
+            out,
+            "  {:<width$} - {}",
+            cmd.name,
+            cmd.description,
+            width = width
+        );
+    }
+    let _ = writeln!(out, "Type 'help <command>' for usage.");
+    Ok(())
+}
+
+fn cmd_echo(_sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let _ = writeln!(out, "{}", args.join(" "));
+    Ok(())
+}
+
+fn cmd_clear(_sh: &mut Shell, _args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    out.clear();
+    Ok(())
+}
+
+fn cmd_info(_sh: &mut Shell, _args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let _ = out.write_str(&super::info_text());
+    Ok(())
+}
+
+fn cmd_mem(_sh: &mut Shell, _args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let info = crate::memory::get_memory_info();
+    crate::memory::write_memory_info(&info, out);
+    for mount in crate::vfs::mounts() {
+        if mount.kind != crate::vfs::ramfs::KIND {
+            continue;
+        }
+        if let Ok(stats) = crate::vfs::stats(&mount.point) {
+            let _ = writeln!(
+                out,
+                "  Ramfs:          {} KB of {} KB ({})",
+                (stats.size - stats.free) / 1024,
+                stats.size / 1024,
+                mount.point
+            );
+        }
+    }
+    Ok(())
+}
+
+fn cmd_history(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    match args {
+        [] => {
+            for (n, line) in sh.history.iter() {
+                let _ = writeln!(out, "  {:>4}  {}", n, line);
+            }
+            Ok(())
+        }
+        [flag] if flag == "-c" => {
+            sh.history.clear();
+            sh.save_history();
+            Ok(())
+        }
+        _ => Err(String::from("Usage: history [-c]")),
+    }
+}
+
+fn cmd_reboot(sh: &mut Shell, _args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    sh.save_history();
+    let _ = writeln!(out, "Rebooting...");
+    out.flush();
+    reboot();
+}
@@ -0,0 +1,194 @@
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+/// A possible completion of the word under the cursor.
+pub struct Candidate {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+pub struct Completion {
+    /// Character index in the line where the replaced text starts; it ends at the cursor.
+    pub start: usize,
+    /// Text to put in place of `start..cursor`.
+    pub replacement: String,
+    /// All matches, for listing on a second Tab.
+    pub candidates: Vec<Candidate>,
+}
+
+/// Characters that must be escaped with a backslash when inserted.
+const SPECIAL: &[char] = &[' ', '\t', '\'', '"', '!', '|', '<', '>', '#', '$', ';'];
+
+fn is_separator(c: char) -> bool {
+    c == '\\' || c == '/'
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        if SPECIAL.contains(&c) {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+fn starts_with_ignore_case(s: &str, prefix: &str) -> bool {
+    let mut chars = s.chars();
+    prefix
+        .chars()
+        .all(|p| chars.next().is_some_and(|c| c.eq_ignore_ascii_case(&p)))
+}
+
+/// Longest common prefix of all names (ASCII case-insensitive), using the
+/// first name's spelling.
+fn common_prefix(names: &[Candidate]) -> String {
+    let Some(first) = names.first() else {
+        return String::new();
+    };
+    let mut len = first.name.chars().count();
+    for other in &names[1..] {
+        len = first
+            .name
+            .chars()
+            .zip(other.name.chars())
+            .take(len)
+            .take_while(|(a, b)| a.eq_ignore_ascii_case(b))
+            .count();
+    }
+    first.name.chars().take(len).collect()
+}
+
+/// The word ending at the cursor, split for completion.
+struct Word {
+    /// True if the word is in command position (line start or after `|` or `;`).
+    is_command: bool,
+    /// Unescaped text up to and including the last path separator.
+    dir: String,
+    /// Unescaped text after the last separator.
+    prefix: String,
+    /// Character index where the text after the last separator starts.
+    prefix_start: usize,
+}
+
+fn current_word(chars: &[char], cursor: usize) -> Option<Word> {
+    let mut word_index = 0;
+    let mut in_word = false;
+    let mut word = Word {
+        is_command: true,
+        dir: String::new(),
+        prefix: String::new(),
+        prefix_start: 0,
+    };
+
+    let mut i = 0;
+    while i < cursor {
+        let c = chars[i];
+        if matches!(c, ' ' | '\t' | '|' | ';' | '<' | '>') {
+            if in_word {
+                in_word = false;
+                word_index += 1;
+            }
+            match c {
+                // A command name follows a pipe or `;`
+                '|' | ';' => word_index = 0,
+                // A redirection target is never a command
+                '<' | '>' => word_index = word_index.max(1),
+                _ => {}
+            }
+            i += 1;
+            continue;
+        }
+        if !in_word {
+            in_word = true;
+            word.dir.clear();
+            word.prefix.clear();
+            word.prefix_start = i;
+        }
+        match c {
+            // Quoted words are not completed
+            '\'' | '"' => return None,
+            '\\' if i + 1 < cursor && SPECIAL.contains(&chars[i + 1]) => {
+                word.prefix.push(chars[i + 1]);
+                i += 2;
+                continue;
+            }
+            c if is_separator(c) => {
+                word.dir.push_str(&word.prefix);
+                word.dir.push(c);
+                word.prefix.clear();
+                word.prefix_start = i + 1;
+            }
+            c => word.prefix.push(c),
+        }
+        i += 1;
+    }
+
+    if !in_word {
+        word.dir.clear();
+        word.prefix.clear();
+        word.prefix_start = cursor;
+    }
+    word.is_command = word_index == 0 && word.dir.is_empty();
+    Some(word)
+}
+
+/// Complete the word ending at `cursor` (a character index into `line`).
+///
+/// The first word completes against `commands`; other words, and any word
+/// containing a path separator, complete against the entries `list_dir`
+/// returns for the directory part of the word.
+pub fn complete(
+    line: &str,
+    cursor: usize,
+    commands: &[&str],
+    list_dir: &dyn Fn(&str) -> Vec<Candidate>,
+) -> Option<Completion> {
+    let chars: Vec<char> = line.chars().collect();
+    let cursor = cursor.min(chars.len());
+    let word = current_word(&chars, cursor)?;
+
+    let mut candidates: Vec<Candidate> = if word.is_command {
+        commands
+            .iter()
+            .filter(|name| name.starts_with(word.prefix.as_str()))
+            .map(|name| Candidate {
+                name: String::from(*name),
+                is_dir: false,
+            })
+            .collect()
+    } else {
+        list_dir(&word.dir)
+            .into_iter()
+            .filter(|c| c.name != "." && c.name != "..")
+            .filter(|c| starts_with_ignore_case(&c.name, &word.prefix))
+            .collect()
+    };
+    candidates.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let replacement = match candidates.as_slice() {
+        [] => return None,
+        [only] => {
+            let mut text = escape(&only.name);
+            text.push(if only.is_dir { '\\' } else { ' ' });
+            text
+        }
+        _ => {
+            let prefix = common_prefix(&candidates);
+            if prefix.chars().count() > word.prefix.chars().count() {
+                escape(&prefix)
+            } else {
+                chars[word.prefix_start..cursor].iter().collect()
+            }
+        }
+    };
+
+    Some(Completion {
+        start: word.prefix_start,
+        replacement,
+        candidates,
+    })
+}
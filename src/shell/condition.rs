@@ -0,0 +1,102 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+
+use super::Shell;
+use crate::console::Console;
+
+/// Fail silently so `if` and `while` see a false condition without an error message.
+fn truth(value: bool) -> Result<(), String> {
+    if value {
+        Ok(())
+    } else {
+        Err(String::new())
+    }
+}
+
+fn integer(s: &str) -> Result<i64, String> {
+    s.trim()
+        .parse()
+        .map_err(|_| format!("test: integer expected: '{}'", s))
+}
+
+fn unary(sh: &Shell, op: &str, operand: &str) -> Result<bool, String> {
+    let metadata = || crate::vfs::stat(&sh.resolve_path(operand));
+    Ok(match op {
+        "-e" => metadata().is_ok(),
+        "-f" => metadata().is_ok_and(|m| !m.is_dir),
+        "-d" => metadata().is_ok_and(|m| m.is_dir),
+        "-s" => metadata().is_ok_and(|m| m.size > 0),
+        "-z" => operand.is_empty(),
+        "-n" => !operand.is_empty(),
+        _ => return Err(format!("test: unknown operator '{}'", op)),
+    })
+}
+
+fn is_binary(op: &str) -> bool {
+    matches!(
+        op,
+        "=" | "==" | "!=" | "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge"
+    )
+}
+
+fn binary(left: &str, op: &str, right: &str) -> Result<bool, String> {
+    Ok(match op {
+        "=" | "==" => left == right,
+        "!=" => left != right,
+        _ => {
+            let (a, b) = (integer(left)?, integer(right)?);
+            match op {
+                "-eq" => a == b,
+                "-ne" => a != b,
+                "-lt" => a < b,
+                "-le" => a <= b,
+                "-gt" => a > b,
+                _ => a >= b,
+            }
+        }
+    })
+}
+
+/// Evaluate a `test` expression, choosing the form by argument count as POSIX does.
+fn evaluate(sh: &Shell, args: &[String]) -> Result<bool, String> {
+    match args {
+        [] => Ok(false),
+        [s] => Ok(!s.is_empty()),
+        [left, op, right] if is_binary(op) => binary(left, op, right),
+        [bang, rest @ ..] if bang == "!" => evaluate(sh, rest).map(|value| !value),
+        [op, operand] => unary(sh, op, operand),
+        [_, op, _] => Err(format!("test: unknown operator '{}'", op)),
+        _ => Err(String::from("test: too many arguments")),
+    }
+}
+
+/// `test <expr>`: succeed if the expression is true.
+pub fn cmd_test(sh: &mut Shell, args: &[String], _out: &mut dyn Console) -> Result<(), String> {
+    truth(evaluate(sh, args)?)
+}
+
+/// `[ <expr> ]`: `test` with a closing bracket.
+pub fn cmd_bracket(sh: &mut Shell, args: &[String], _out: &mut dyn Console) -> Result<(), String> {
+    match args.split_last() {
+        Some((last, expr)) if last == "]" => truth(evaluate(sh, expr)?),
+        _ => Err(String::from("[: missing ']'")),
+    }
+}
+
+/// `exists <path>`: succeed if the file or directory exists.
+pub fn cmd_exists(sh: &mut Shell, args: &[String], _out: &mut dyn Console) -> Result<(), String> {
+    let [path] = args else {
+        return Err(String::from("Usage: exists <path>"));
+    };
+    truth(crate::vfs::stat(&sh.resolve_path(path)).is_ok())
+}
+
+pub fn cmd_true(_sh: &mut Shell, _args: &[String], _out: &mut dyn Console) -> Result<(), String> {
+    Ok(())
+}
+
+pub fn cmd_false(_sh: &mut Shell, _args: &[String], _out: &mut dyn Console) -> Result<(), String> {
+    truth(false)
+}
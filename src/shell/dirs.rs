@@ -0,0 +1,76 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+
+use super::Shell;
+use crate::console::Console;
+use crate::path;
+
+impl Shell {
+    /// Resolve a path typed by the user against the current directory.
+    pub fn resolve_path(&self, p: &str) -> String {
+        path::resolve(&self.cwd, p)
+    }
+
+    /// Change the current directory, checking that the target is a directory.
+    pub fn change_dir(&mut self, p: &str) -> Result<(), String> {
+        let target = self.resolve_path(p);
+        if !path::is_root(&target) {
+            let meta = crate::vfs::stat(&target)
+                .map_err(|_| format!("cd: no such directory: {}", p))?;
+            if !meta.is_dir {
+                return Err(format!("cd: not a directory: {}", p));
+            }
+        } else if path::split_volume(&target).0.is_some() {
+            crate::vfs::read_dir(&target).map_err(|e| format!("cd: {}", e))?;
+        }
+        self.cwd = target;
+        Ok(())
+    }
+}
+
+fn print_stack(sh: &Shell, out: &mut dyn Console) {
+    let _ = write!(out, "{}", sh.cwd);
+    for dir in sh.dir_stack.iter().rev() {
+        let _ = write!(out, " {}", dir);
+    }
+    let _ = writeln!(out);
+}
+
+/// `cd [dir]`: change directory; without an argument go to the root.
+pub fn cmd_cd(sh: &mut Shell, args: &[String], _out: &mut dyn Console) -> Result<(), String> {
+    match args {
+        [] => sh.change_dir(path::ROOT),
+        [dir] => sh.change_dir(dir),
+        _ => Err(String::from("Usage: cd [dir]")),
+    }
+}
+
+pub fn cmd_pwd(sh: &mut Shell, _args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let _ = writeln!(out, "{}", sh.cwd);
+    Ok(())
+}
+
+/// `pushd <dir>`: save the current directory on the stack and change to `dir`.
+pub fn cmd_pushd(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let [dir] = args else {
+        return Err(String::from("Usage: pushd <dir>"));
+    };
+    let previous = sh.cwd.clone();
+    sh.change_dir(dir)?;
+    sh.dir_stack.push(previous);
+    print_stack(sh, out);
+    Ok(())
+}
+
+/// `popd`: return to the directory saved by the last `pushd`.
+pub fn cmd_popd(sh: &mut Shell, _args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let dir = sh
+        .dir_stack
+        .pop()
+        .ok_or_else(|| String::from("popd: directory stack empty"))?;
+    sh.cwd = dir;
+    print_stack(sh, out);
+    Ok(())
+}
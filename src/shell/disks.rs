@@ -0,0 +1,147 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::view::{self, DumpStyle, ROW_SIZE};
+use super::Shell;
+use crate::block::partition::{self, Scheme};
+use crate::block::uefi_disk::{self, UefiDisk};
+use crate::block::{self, BlockDevice};
+use crate::console::{Console, Style};
+use crate::fs::size_text;
+
+const READBLK_USAGE: &str = "readblk <disk|diskNpM> <lba> [count]";
+
+/// `disks`: list the whole disks the firmware can read block by block,
+/// with their media ID, block size, size and flags.
+pub fn cmd_disks(_sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    if !args.is_empty() {
+        return Err(String::from("Usage: disks"));
+    }
+    let _ = writeln!(
+        out,
+        "{:<6} {:>5} {:>5} {:>8}  {:<12}  Device",
+        "Disk", "Media", "Block", "Size", "Flags"
+    );
+    for disk in uefi_disk::disks()? {
+        let size = if disk.present {
+            size_text(disk.blocks * disk.block_size as u64)
+        } else {
+            String::from("-")
+        };
+        let mut flags = String::from(if disk.read_only { "ro" } else { "rw" });
+        if disk.removable {
+            flags.push_str(" removable");
+        }
+        if !disk.present {
+            flags = String::from("no media");
+        }
+        let _ = writeln!(
+            out,
+            "{:<6} {:>5} {:>5} {:>8}  {:<12}  {}",
+            disk.name, disk.media_id, disk.block_size, size, flags, disk.device
+        );
+    }
+    Ok(())
+}
+
+/// `parts [disk...]`: list the partitions of each disk (default all) from
+/// its GPT or MBR. Partition `M` of `diskN` is the block device `diskNpM`.
+pub fn cmd_parts(_sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let names: Vec<String> = if args.is_empty() {
+        uefi_disk::disks()?
+            .into_iter()
+            .filter(|d| d.present)
+            .map(|d| d.name)
+            .collect()
+    } else {
+        args.to_vec()
+    };
+    for name in names {
+        let mut disk = UefiDisk::open(&name)?;
+        let block_size = disk.block_size() as u64;
+        let table = match partition::read_table(&mut disk) {
+            Ok(table) => table,
+            Err(e) => {
+                let _ = writeln!(out, "{}: {}", name, e);
+                continue;
+            }
+        };
+        match (table.scheme, table.disk_guid) {
+            (Scheme::Gpt, Some(guid)) => {
+                let _ = writeln!(out, "{}: GPT, disk GUID {}", name, guid);
+            }
+            _ => {
+                let _ = writeln!(out, "{}: MBR", name);
+            }
+        }
+        if let Some(warning) = &table.warning {
+            out.write_styled(Style::Error, &format!("  {}\n", warning));
+        }
+        let _ = writeln!(
+            out,
+            "  {:<9} {:>10} {:>10} {:>8}  {:<20} {:<12} UUID",
+            "Part", "First", "Last", "Size", "Type", "Label"
+        );
+        for part in &table.partitions {
+            let _ = writeln!(
+                out,
+                "  {:<9} {:>10} {:>10} {:>8}  {:<20} {:<12} {}",
+                format!("{}p{}", name, part.number),
+                part.first_lba,
+                part.last_lba,
+                size_text(part.blocks() * block_size),
+                part.type_name,
+                part.label,
+                part.uuid.map(|u| format!("{}", u)).unwrap_or_default()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// `readblk <disk|diskNpM> <lba> [count]`: hex dump `count` blocks
+/// (default 1) read straight from a disk or partition, with offsets in
+/// bytes from its start. The dump stops at the end of the device, and
+/// Ctrl+C interrupts it.
+pub fn cmd_readblk(_sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    if args.is_empty() || args.len() > 3 {
+        return Err(format!("Usage: {}", READBLK_USAGE));
+    }
+    let lba = view::parse_number(args.get(1), READBLK_USAGE)?;
+    let count = match args.get(2) {
+        Some(_) => view::parse_number(args.get(2), READBLK_USAGE)?,
+        None => 1,
+    };
+    let mut disk = block::open(&args[0])?;
+    let blocks = disk.block_count();
+    if lba >= blocks {
+        return Err(format!(
+            "Block {} is beyond the end of {} ({} blocks)",
+            lba, args[0], blocks
+        ));
+    }
+    // Stop at the end of the device rather than failing part way
+    let count = count.min(blocks - lba);
+    let block_size = disk.block_size();
+    let mut buf = vec![0u8; block_size];
+    for block in lba..lba + count {
+        if super::exec::interrupted() {
+            return Err(String::from("Interrupted"));
+        }
+        disk.read_blocks(block, &mut buf)?;
+        let start = block * block_size as u64;
+        for (row, bytes) in buf.chunks(ROW_SIZE).enumerate() {
+            let offset = start + (row * ROW_SIZE) as u64;
+            let _ = writeln!(
+                out,
+                "{}",
+                view::format_row(DumpStyle::Canonical, offset, bytes)
+            );
+        }
+    }
+    Ok(())
+}
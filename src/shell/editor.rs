@@ -0,0 +1,447 @@
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::console::Console;
+
+/// Keys understood by the line editor, decoupled from UEFI input types so
+/// the editor can be driven by scripted key sequences.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EditKey {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    Tab,
+    /// Control chord, as the lowercase letter (`Ctrl('a')` for Ctrl+A).
+    Ctrl(char),
+}
+
+/// What the caller should do after a key was handled.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum EditAction {
+    /// Nothing visible changed.
+    None,
+    /// The line or cursor changed and must be re-rendered.
+    Redraw,
+    /// Enter was pressed; the line is complete.
+    Submit,
+    /// Ctrl+C: abandon the line.
+    Cancel,
+    /// Ctrl+L: clear the screen and redraw the prompt and line.
+    ClearScreen,
+    HistoryOlder,
+    HistoryNewer,
+    /// First Tab: complete the word under the cursor.
+    Complete,
+    /// Tab pressed again right away: list the possible completions.
+    ListCompletions,
+}
+
+/// Editable line with a cursor, plus the bookkeeping needed to redraw it
+/// in place on a console.
+pub struct LineEditor {
+    buf: Vec<char>,
+    cursor: usize,
+    /// Text removed by the last Ctrl+U/W/K, inserted again by Ctrl+Y.
+    kill: String,
+    /// Console position where the line starts (just after the prompt).
+    origin: (usize, usize),
+    /// Number of characters drawn by the last render.
+    drawn: usize,
+    /// The previous key was Tab.
+    after_tab: bool,
+}
+
+impl LineEditor {
+    pub fn new() -> Self {
+        Self {
+            buf: Vec::new(),
+            cursor: 0,
+            kill: String::new(),
+            origin: (0, 0),
+            drawn: 0,
+            after_tab: false,
+        }
+    }
+
+    pub fn line(&self) -> String {
+        self.buf.iter().collect()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Replace the whole line and put the cursor at its end.
+    pub fn set_line(&mut self, line: &str) {
+        self.buf = line.chars().collect();
+        self.cursor = self.buf.len();
+    }
+
+    /// Insert text at the cursor.
+    pub fn insert_str(&mut self, s: &str) {
+        for c in s.chars() {
+            self.buf.insert(self.cursor, c);
+            self.cursor += 1;
+        }
+    }
+
+    /// Replace the characters in `start..end` and put the cursor after the new text.
+    pub fn replace_range(&mut self, start: usize, end: usize, text: &str) {
+        let end = end.min(self.buf.len());
+        let start = start.min(end);
+        self.buf.drain(start..end);
+        self.cursor = start;
+        self.insert_str(text);
+    }
+
+    /// Start a new empty line at the console's current cursor position.
+    pub fn begin(&mut self, out: &dyn Console) {
+        self.buf.clear();
+        self.cursor = 0;
+        self.drawn = 0;
+        self.after_tab = false;
+        self.origin = out.cursor();
+    }
+
+    /// Keep the current line but redraw it from the console's current position,
+    /// e.g. after the screen was cleared and the prompt printed again.
+    pub fn rebase(&mut self, out: &dyn Console) {
+        self.drawn = 0;
+        self.origin = out.cursor();
+    }
+
+    pub fn handle(&mut self, key: EditKey) -> EditAction {
+        let after_tab = core::mem::replace(&mut self.after_tab, false);
+        match key {
+            EditKey::Char(c) => {
+                self.buf.insert(self.cursor, c);
+                self.cursor += 1;
+                EditAction::Redraw
+            }
+            EditKey::Enter => EditAction::Submit,
+            EditKey::Backspace | EditKey::Ctrl('h') => {
+                if self.cursor == 0 {
+                    return EditAction::None;
+                }
+                self.cursor -= 1;
+                self.buf.remove(self.cursor);
+                EditAction::Redraw
+            }
+            EditKey::Delete | EditKey::Ctrl('d') => {
+                if self.cursor == self.buf.len() {
+                    return EditAction::None;
+                }
+                self.buf.remove(self.cursor);
+                EditAction::Redraw
+            }
+            EditKey::Left | EditKey::Ctrl('b') => self.move_to(self.cursor.saturating_sub(1)),
+            EditKey::Right | EditKey::Ctrl('f') => {
+                self.move_to((self.cursor + 1).min(self.buf.len()))
+            }
+            EditKey::Home | EditKey::Ctrl('a') => self.move_to(0),
+            EditKey::End | EditKey::Ctrl('e') => self.move_to(self.buf.len()),
+            EditKey::Up | EditKey::Ctrl('p') => EditAction::HistoryOlder,
+            EditKey::Down | EditKey::Ctrl('n') => EditAction::HistoryNewer,
+            EditKey::Tab | EditKey::Ctrl('i') => {
+                self.after_tab = true;
+                if after_tab {
+                    EditAction::ListCompletions
+                } else {
+                    EditAction::Complete
+                }
+            }
+            EditKey::Ctrl('u') => self.kill_range(0, self.cursor),
+            EditKey::Ctrl('k') => self.kill_range(self.cursor, self.buf.len()),
+            EditKey::Ctrl('w') => {
+                let mut start = self.cursor;
+                while start > 0 && self.buf[start - 1] == ' ' {
+                    start -= 1;
+                }
+                while start > 0 && self.buf[start - 1] != ' ' {
+                    start -= 1;
+                }
+                self.kill_range(start, self.cursor)
+            }
+            EditKey::Ctrl('y') => {
+                if self.kill.is_empty() {
+                    return EditAction::None;
+                }
+                let kill = core::mem::take(&mut self.kill);
+                self.insert_str(&kill);
+                self.kill = kill;
+                EditAction::Redraw
+            }
+            EditKey::Ctrl('l') => EditAction::ClearScreen,
+            EditKey::Ctrl('c') => EditAction::Cancel,
+            EditKey::Ctrl(_) => EditAction::None,
+        }
+    }
+
+    fn move_to(&mut self, pos: usize) -> EditAction {
+        if pos == self.cursor {
+            return EditAction::None;
+        }
+        self.cursor = pos;
+        EditAction::Redraw
+    }
+
+    fn kill_range(&mut self, start: usize, end: usize) -> EditAction {
+        if start == end {
+            return EditAction::None;
+        }
+        self.kill = self.buf.drain(start..end).collect();
+        self.cursor = start;
+        EditAction::Redraw
+    }
+
+    /// Redraw the line in place and position the console cursor.
+    ///
+    /// Consoles differ in when they wrap and scroll, so instead of predicting
+    /// that, the origin is corrected by comparing where the cursor actually
+    /// ended up with where it would be without scrolling.
+    pub fn render(&mut self, out: &mut dyn Console) {
+        let (cols, rows) = out.size();
+        if cols == 0 || rows == 0 {
+            return;
+        }
+
+        out.set_cursor(self.origin.0, self.origin.1);
+        let line = self.line();
+        let _ = out.write_str(&line);
+        let written = self.buf.len().max(self.drawn);
+        for _ in self.buf.len()..written {
+            let _ = out.write_char(' ');
+        }
+        self.drawn = self.buf.len();
+
+        let start = self.origin.1 * cols + self.origin.0;
+        let (col, row) = out.cursor();
+        // A cursor parked past the last column has not wrapped yet
+        let end = row * cols + col;
+        let expected = start + written;
+        if expected > end {
+            let scrolled = (expected - end).div_ceil(cols);
+            self.origin.1 = self.origin.1.saturating_sub(scrolled);
+        }
+
+        self.place_cursor(out, self.cursor);
+    }
+
+    /// Move the console cursor past the end of the line, ready for output.
+    pub fn finish(&mut self, out: &mut dyn Console) {
+        self.place_cursor(out, self.buf.len());
+        let _ = out.write_char('\n');
+    }
+
+    /// Put the console cursor on character `index` of the line.
+    fn place_cursor(&self, out: &mut dyn Console, index: usize) {
+        let (cols, rows) = out.size();
+        if cols == 0 || rows == 0 {
+            return;
+        }
+        let pos = self.origin.1 * cols + self.origin.0 + index;
+        if pos / cols < rows {
+            out.set_cursor(pos % cols, pos / cols);
+        } else {
+            // Just past the bottom-right corner: park without scrolling
+            out.set_cursor(cols, rows - 1);
+        }
+    }
+}
+
+impl Default for LineEditor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::BufferConsole;
+    use crate::shell::history::History;
+
+    /// An editor on a fresh line holding `text`, cursor at its end.
+    fn editor(text: &str) -> LineEditor {
+        let mut editor = LineEditor::new();
+        editor.begin(&BufferConsole::new());
+        editor.insert_str(text);
+        editor
+    }
+
+    /// Feed `keys` to the editor, rendering after each like the shell does.
+    fn press(editor: &mut LineEditor, keys: &[EditKey]) -> EditAction {
+        let mut out = BufferConsole::new();
+        let mut action = EditAction::None;
+        for &key in keys {
+            action = editor.handle(key);
+            if action == EditAction::Redraw {
+                editor.render(&mut out);
+            }
+        }
+        action
+    }
+
+    fn chars(text: &str) -> Vec<EditKey> {
+        text.chars().map(EditKey::Char).collect()
+    }
+
+    fn assert_line(editor: &LineEditor, line: &str, cursor: usize) {
+        assert_eq!(editor.line(), line);
+        assert_eq!(editor.cursor(), cursor);
+    }
+
+    #[test]
+    fn insert_in_the_middle() {
+        let mut ed = editor("ecso");
+        press(&mut ed, &[EditKey::Left, EditKey::Backspace]);
+        press(&mut ed, &chars("h"));
+        assert_line(&ed, "echo", 3);
+        press(&mut ed, &[EditKey::End]);
+        press(&mut ed, &chars(" hi"));
+        assert_line(&ed, "echo hi", 7);
+    }
+
+    #[test]
+    fn delete() {
+        let mut ed = editor("abc");
+        assert_eq!(press(&mut ed, &[EditKey::Delete]), EditAction::None);
+        assert_line(&ed, "abc", 3);
+        press(&mut ed, &[EditKey::Left, EditKey::Left, EditKey::Delete]);
+        assert_line(&ed, "ac", 1);
+        press(&mut ed, &[EditKey::Ctrl('d')]);
+        assert_line(&ed, "a", 1);
+        assert_eq!(press(&mut ed, &[EditKey::Ctrl('d')]), EditAction::None);
+    }
+
+    #[test]
+    fn home_and_end() {
+        let mut ed = editor("ls -l");
+        press(&mut ed, &[EditKey::Home]);
+        assert_line(&ed, "ls -l", 0);
+        assert_eq!(press(&mut ed, &[EditKey::Left]), EditAction::None);
+        press(&mut ed, &[EditKey::End]);
+        assert_line(&ed, "ls -l", 5);
+        press(&mut ed, &[EditKey::Ctrl('a')]);
+        assert_eq!(ed.cursor(), 0);
+        press(&mut ed, &[EditKey::Ctrl('e')]);
+        assert_eq!(ed.cursor(), 5);
+    }
+
+    #[test]
+    fn kill_to_start() {
+        let mut ed = editor("cat file");
+        press(&mut ed, &[EditKey::Left, EditKey::Left, EditKey::Ctrl('u')]);
+        assert_line(&ed, "le", 0);
+        assert_eq!(press(&mut ed, &[EditKey::Ctrl('u')]), EditAction::None);
+    }
+
+    #[test]
+    fn kill_word() {
+        let mut ed = editor("cp a.txt  b.txt  ");
+        press(&mut ed, &[EditKey::Ctrl('w')]);
+        assert_line(&ed, "cp a.txt  ", 10);
+        press(&mut ed, &[EditKey::Ctrl('w')]);
+        assert_line(&ed, "cp ", 3);
+    }
+
+    #[test]
+    fn kill_to_end() {
+        let mut ed = editor("echo hello");
+        press(
+            &mut ed,
+            &[EditKey::Home, EditKey::Right, EditKey::Ctrl('k')],
+        );
+        assert_line(&ed, "e", 1);
+        assert_eq!(press(&mut ed, &[EditKey::Ctrl('k')]), EditAction::None);
+    }
+
+    #[test]
+    fn yank_after_kill() {
+        let mut ed = editor("");
+        assert_eq!(press(&mut ed, &[EditKey::Ctrl('y')]), EditAction::None);
+
+        let mut ed = editor("rm foo");
+        press(&mut ed, &[EditKey::Ctrl('w')]);
+        assert_line(&ed, "rm ", 3);
+        press(&mut ed, &[EditKey::Home, EditKey::Ctrl('y')]);
+        assert_line(&ed, "foorm ", 3);
+        // The kill stays available for another yank
+        press(&mut ed, &[EditKey::End, EditKey::Ctrl('y')]);
+        assert_line(&ed, "foorm foo", 9);
+    }
+
+    #[test]
+    fn keys_report_actions() {
+        let mut ed = editor("x");
+        assert_eq!(press(&mut ed, &[EditKey::Enter]), EditAction::Submit);
+        assert_eq!(press(&mut ed, &[EditKey::Ctrl('c')]), EditAction::Cancel);
+        assert_eq!(
+            press(&mut ed, &[EditKey::Ctrl('l')]),
+            EditAction::ClearScreen
+        );
+        assert_eq!(press(&mut ed, &[EditKey::Tab]), EditAction::Complete);
+        assert_eq!(press(&mut ed, &[EditKey::Tab]), EditAction::ListCompletions);
+        assert_eq!(press(&mut ed, &[EditKey::Tab]), EditAction::ListCompletions);
+        assert_eq!(
+            press(&mut ed, &[EditKey::Char('y'), EditKey::Tab]),
+            EditAction::Complete
+        );
+        assert_line(&ed, "xy", 2);
+    }
+
+    #[test]
+    fn history_recall() {
+        let mut history = History::new();
+        history.push("ls");
+        history.push("cd \\EFI");
+        let mut ed = editor("ec");
+
+        // Up and Down replace the line as the shell does
+        let mut recall = |ed: &mut LineEditor, key| {
+            let entry = match press(ed, &[key]) {
+                EditAction::HistoryOlder => history.older(&ed.line()),
+                EditAction::HistoryNewer => history.newer(),
+                action => panic!("unexpected {:?}", action),
+            };
+            if let Some(entry) = entry.map(String::from) {
+                ed.set_line(&entry);
+            }
+        };
+        recall(&mut ed, EditKey::Up);
+        assert_line(&ed, "cd \\EFI", 7);
+        recall(&mut ed, EditKey::Up);
+        assert_line(&ed, "ls", 2);
+        recall(&mut ed, EditKey::Ctrl('p'));
+        assert_line(&ed, "ls", 2);
+        recall(&mut ed, EditKey::Down);
+        assert_line(&ed, "cd \\EFI", 7);
+        // Past the newest entry the draft comes back
+        recall(&mut ed, EditKey::Ctrl('n'));
+        assert_line(&ed, "ec", 2);
+    }
+
+    #[test]
+    fn render_draws_line_and_places_cursor() {
+        let mut out = BufferConsole::new();
+        let mut ed = LineEditor::new();
+        ed.begin(&out);
+        ed.insert_str("abc");
+        ed.render(&mut out);
+        ed.handle(EditKey::Backspace);
+        ed.render(&mut out);
+        // The removed character is blanked out
+        assert_eq!(out.text, "abcab ");
+        ed.finish(&mut out);
+        assert_eq!(out.text, "abcab \n");
+    }
+}
@@ -0,0 +1,304 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::editor::EditKey;
+use super::lexer::{self, LexError, Word, WordPart};
+use super::parser::{
+    self, ParseError, Pipeline, RedirectKind, SimpleCommand, Statement, StatementKind,
+};
+use super::{commands, vars, Shell};
+use crate::console::{BufferConsole, Console};
+
+/// True if Ctrl+C was pressed; checked between loop iterations. Other keys
+/// are kept for the line editor, and dropped on Ctrl+C.
+pub(super) fn interrupted() -> bool {
+    while let Some(key) = super::read_console_key() {
+        if key == EditKey::Ctrl('c') {
+            super::clear_type_ahead();
+            return true;
+        }
+        super::type_ahead(key);
+    }
+    false
+}
+
+/// Split a `NAME=value` word into the name and a word for the value.
+fn assignment(word: &Word) -> Option<(&str, Word)> {
+    let (first, rest) = word.parts.split_first()?;
+    let WordPart::Text(text) = first else {
+        return None;
+    };
+    let (name, value) = vars::parse_assignment(text)?;
+    let mut parts = vec![WordPart::Quoted(String::from(value))];
+    parts.extend_from_slice(rest);
+    Some((name, Word { parts }))
+}
+
+impl Shell {
+    /// Parse shell input into a command list.
+    pub fn parse(&self, text: &str) -> Result<Vec<Statement>, ParseError> {
+        let tokens = lexer::tokenize(text).map_err(|e| match e {
+            LexError::UnterminatedQuote(_)
+            | LexError::UnterminatedSubstitution
+            | LexError::UnterminatedHereDoc(_) => ParseError::Incomplete(e.to_string()),
+            _ => ParseError::Syntax(e.to_string()),
+        })?;
+        parser::parse(tokens)
+    }
+
+    /// Parse and run shell input. Blank lines and comments leave `$?` unchanged.
+    pub fn execute(&mut self, text: &str, out: &mut dyn Console) -> Result<(), String> {
+        let list = self.parse(text).map_err(|e| {
+            self.status = 1;
+            e.to_string()
+        })?;
+        self.run_list(&list, out)
+    }
+
+    /// Prefix an error with the script position, if a script is running.
+    pub fn locate(&self, msg: String) -> String {
+        match &self.position {
+            Some(position) if !msg.is_empty() => format!("{}: {}", position, msg),
+            _ => msg,
+        }
+    }
+
+    /// Run statements in order. A failing statement sets `$?` to 1 (or the
+    /// command's `failure_status`) and is reported; with `set -e` its error
+    /// is returned instead and the rest of the list is skipped.
+    pub fn run_list(&mut self, list: &[Statement], out: &mut dyn Console) -> Result<(), String> {
+        for statement in list {
+            if let Err(e) = self.run_statement(statement, out) {
+                self.status = self.failure_status.take().unwrap_or(1);
+                if self.errexit {
+                    return Err(e);
+                }
+                super::report_error(out, &self.locate(e));
+            }
+        }
+        Ok(())
+    }
+
+    fn run_statement(
+        &mut self,
+        statement: &Statement,
+        out: &mut dyn Console,
+    ) -> Result<(), String> {
+        if let Some(position) = &mut self.position {
+            position.enter(statement.line);
+        }
+        match &statement.kind {
+            StatementKind::Pipeline(pipeline) => {
+                self.run_pipeline(pipeline, out)?;
+                self.status = 0;
+            }
+            StatementKind::If {
+                branches,
+                otherwise,
+            } => {
+                for (condition, body) in branches {
+                    if self.run_condition(condition, out) {
+                        return self.run_list(body, out);
+                    }
+                }
+                self.status = 0;
+                self.run_list(otherwise, out)?;
+            }
+            StatementKind::While { condition, body } => {
+                let mut status = 0;
+                while self.run_condition(condition, out) {
+                    self.run_list(body, out)?;
+                    status = self.status;
+                    if interrupted() {
+                        return Err(String::from("Interrupted"));
+                    }
+                }
+                self.status = status;
+            }
+            StatementKind::For { var, words, body } => {
+                let mut status = 0;
+                for item in self.expand_words(words)? {
+                    self.set_var(var, &item)?;
+                    self.run_list(body, out)?;
+                    status = self.status;
+                    if interrupted() {
+                        return Err(String::from("Interrupted"));
+                    }
+                }
+                self.status = status;
+            }
+        }
+        Ok(())
+    }
+
+    /// Run the condition of an `if` or `while` with `set -e` suspended and
+    /// return whether it succeeded.
+    fn run_condition(&mut self, list: &[Statement], out: &mut dyn Console) -> bool {
+        let errexit = core::mem::replace(&mut self.errexit, false);
+        let _ = self.run_list(list, out);
+        self.errexit = errexit;
+        self.status == 0
+    }
+
+    /// Run each command of a pipeline in turn, feeding the captured output
+    /// of one into the next. The last command writes to `out` unless its
+    /// output is redirected.
+    pub fn run_pipeline(
+        &mut self,
+        pipeline: &Pipeline,
+        out: &mut dyn Console,
+    ) -> Result<(), String> {
+        let mut input: Option<Vec<u8>> = None;
+        let last = pipeline.commands.len().saturating_sub(1);
+        for (i, cmd) in pipeline.commands.iter().enumerate() {
+            let piped = i < last;
+            input = self.run_simple(cmd, input.take(), piped, out)?;
+        }
+        Ok(())
+    }
+
+    /// Run one command with redirections applied. Returns its output when
+    /// `capture` is set (it feeds a pipe).
+    fn run_simple(
+        &mut self,
+        cmd: &SimpleCommand,
+        input: Option<Vec<u8>>,
+        capture: bool,
+        out: &mut dyn Console,
+    ) -> Result<Option<Vec<u8>>, String> {
+        // A command made only of `NAME=value` words assigns variables
+        let assignments: Option<Vec<_>> = cmd.argv.iter().map(assignment).collect();
+        if let Some(assignments) = assignments {
+            for (name, value) in assignments {
+                let value = self.expand_string(&value)?;
+                self.set_var(name, &value)?;
+            }
+            return Ok(capture.then(Vec::new));
+        }
+
+        let argv = self.expand_words(&cmd.argv)?;
+        let mut stdin = input;
+        let mut output = None;
+        for redirect in &cmd.redirects {
+            match redirect.kind {
+                RedirectKind::HereDoc => {
+                    stdin = Some(self.expand_string(&redirect.target)?.into_bytes())
+                }
+                RedirectKind::Input => {
+                    let target = self.expand_target(&redirect.target)?;
+                    stdin = Some(crate::fs::read_file(&target)?);
+                }
+                RedirectKind::Output | RedirectKind::Append => {
+                    output = Some((redirect.kind, self.expand_target(&redirect.target)?))
+                }
+            }
+        }
+
+        self.stdin = stdin;
+        let result = if capture || output.is_some() {
+            let mut buffer = BufferConsole::new();
+            commands::dispatch(self, &argv, &mut buffer).map(|_| buffer.text)
+        } else {
+            commands::dispatch(self, &argv, out).map(|_| String::new())
+        };
+        self.stdin = None;
+        let text = result?;
+
+        if let Some((kind, target)) = output {
+            if kind == RedirectKind::Append {
+                crate::fs::append_file(&target, text.as_bytes())?;
+            } else {
+                crate::fs::write_file(&target, text.as_bytes())?;
+            }
+            // Redirected output still feeds the pipe as empty input
+            return Ok(capture.then(Vec::new));
+        }
+        Ok(capture.then(|| text.into_bytes()))
+    }
+
+    // ── Expansion ──
+
+    /// Expand words into arguments; unquoted expansions split at blanks.
+    pub fn expand_words(&mut self, words: &[Word]) -> Result<Vec<String>, String> {
+        let mut args = Vec::new();
+        for word in words {
+            args.extend(self.expand_word(word, true)?);
+        }
+        Ok(args)
+    }
+
+    /// Expand a word into one string without splitting.
+    fn expand_string(&mut self, word: &Word) -> Result<String, String> {
+        Ok(self.expand_word(word, false)?.pop().unwrap_or_default())
+    }
+
+    /// Expand a redirection target, which must be exactly one word, to an
+    /// absolute path.
+    fn expand_target(&mut self, word: &Word) -> Result<String, String> {
+        let fields = self.expand_word(word, true)?;
+        match fields.as_slice() {
+            [target] => Ok(self.resolve_path(target)),
+            _ => Err(String::from("ambiguous redirect")),
+        }
+    }
+
+    fn expand_word(&mut self, word: &Word, split: bool) -> Result<Vec<String>, String> {
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        // Quotes make a field even when empty (`""`)
+        let mut have_field = false;
+        for part in &word.parts {
+            let (value, quoted) = match part {
+                WordPart::Text(text) | WordPart::Quoted(text) => {
+                    field.push_str(text);
+                    have_field = true;
+                    continue;
+                }
+                WordPart::Var { name, quoted } => (self.lookup(name).unwrap_or_default(), *quoted),
+                WordPart::Command { source, quoted } => (self.substitute(source)?, *quoted),
+            };
+            if quoted || !split {
+                field.push_str(&value);
+                have_field = true;
+                continue;
+            }
+            for ch in value.chars() {
+                if matches!(ch, ' ' | '\t' | '\r' | '\n') {
+                    if have_field {
+                        fields.push(core::mem::take(&mut field));
+                        have_field = false;
+                    }
+                } else {
+                    field.push(ch);
+                    have_field = true;
+                }
+            }
+        }
+        if have_field {
+            fields.push(field);
+        }
+        Ok(fields)
+    }
+
+    /// Run the source of a `$(...)` and return its output without trailing
+    /// newlines. The first error inside it fails the command using it.
+    fn substitute(&mut self, source: &str) -> Result<String, String> {
+        let list = self.parse(source).map_err(|e| e.to_string())?;
+        let mut buffer = BufferConsole::new();
+        let errexit = core::mem::replace(&mut self.errexit, true);
+        // Lines inside the substitution count from the statement using it
+        let outer = self.position.clone();
+        if let Some(position) = &mut self.position {
+            position.base = position.line;
+        }
+        let result = self.run_list(&list, &mut buffer);
+        self.position = outer;
+        self.errexit = errexit;
+        result?;
+        Ok(String::from(buffer.text.trim_end_matches(['\r', '\n'])))
+    }
+}
@@ -0,0 +1,219 @@
+extern crate alloc;
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::String;
+
+/// Number of lines kept in memory and on disk.
+pub const HISTORY_SIZE: usize = 100;
+
+/// Directory holding VOS state files on the boot volume.
+pub const VOS_DIR: &str = "\\vos";
+
+/// History file on the boot volume.
+pub const HISTORY_PATH: &str = "\\vos\\history";
+
+/// Number of new lines after which the history file is rewritten; it is
+/// also written before the shell reboots or hands over to another program.
+pub const SAVE_EVERY: usize = 10;
+
+/// Ring of previously entered command lines.
+///
+/// Entries are numbered from 1 like in other shells; numbers keep growing
+/// when old entries fall off the front of the ring.
+pub struct History {
+    entries: VecDeque<String>,
+    /// Number of `entries[0]`.
+    first: usize,
+    /// Index into `entries` while browsing with Up/Down.
+    nav: Option<usize>,
+    /// Line being edited before browsing started, restored past the newest entry.
+    draft: String,
+    /// Lines added since the history was last written.
+    unsaved: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            first: 1,
+            nav: None,
+            draft: String::new(),
+            unsaved: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Iterate over `(number, line)` pairs, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &str)> {
+        self.entries
+            .iter()
+            .enumerate()
+            .map(move |(i, line)| (self.first + i, line.as_str()))
+    }
+
+    /// Append a line, skipping blanks and immediate repeats.
+    pub fn push(&mut self, line: &str) {
+        self.nav = None;
+        if line.trim().is_empty() || self.entries.back().map(String::as_str) == Some(line) {
+            return;
+        }
+        if self.entries.len() == HISTORY_SIZE {
+            self.entries.pop_front();
+            self.first += 1;
+        }
+        self.entries.push_back(String::from(line));
+        self.unsaved += 1;
+    }
+
+    /// Lines added since `mark_saved`.
+    pub fn unsaved(&self) -> usize {
+        self.unsaved
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.unsaved = 0;
+    }
+
+    pub fn clear(&mut self) {
+        self.first += self.entries.len();
+        self.entries.clear();
+        self.nav = None;
+    }
+
+    /// Look up an entry by its number.
+    pub fn get(&self, number: usize) -> Option<&str> {
+        let index = number.checked_sub(self.first)?;
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Step back to an older entry (Up arrow). `current` is the line being
+    /// edited, saved so that stepping forward past the newest entry restores it.
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let index = match self.nav {
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = String::from(current);
+                self.entries.len() - 1
+            }
+            Some(0) => 0,
+            Some(i) => i - 1,
+        };
+        self.nav = Some(index);
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /// Step forward to a newer entry (Down arrow).
+    pub fn newer(&mut self) -> Option<&str> {
+        let index = self.nav?;
+        if index + 1 < self.entries.len() {
+            self.nav = Some(index + 1);
+            self.entries.get(index + 1).map(String::as_str)
+        } else {
+            self.nav = None;
+            Some(self.draft.as_str())
+        }
+    }
+
+    /// Stop browsing, e.g. when a new line is started.
+    pub fn reset_nav(&mut self) {
+        self.nav = None;
+    }
+
+    /// Expand `!!`, `!n` and `!-n` references outside single quotes.
+    /// A backslash before `!` keeps it literal.
+    pub fn expand(&self, line: &str) -> Result<String, String> {
+        let mut out = String::new();
+        let mut in_single = false;
+        let mut chars = line.char_indices();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\'' => {
+                    in_single = !in_single;
+                    out.push(c);
+                }
+                '\\' if !in_single => {
+                    out.push(c);
+                    if let Some((_, next)) = chars.next() {
+                        out.push(next);
+                    }
+                }
+                '!' if !in_single => {
+                    let rest = &line[i + 1..];
+                    if rest.starts_with('!') {
+                        chars.next();
+                        let last = self
+                            .entries
+                            .back()
+                            .ok_or_else(|| String::from("!!: event not found"))?;
+                        out.push_str(last);
+                        continue;
+                    }
+
+                    let negative = rest.starts_with('-');
+                    let digits = rest
+                        .trim_start_matches('-')
+                        .chars()
+                        .take_while(char::is_ascii_digit)
+                        .count();
+                    if digits == 0 {
+                        out.push(c);
+                        continue;
+                    }
+                    let len = digits + usize::from(negative);
+                    let spec = &rest[..len];
+                    let n: usize = spec.trim_start_matches('-').parse().unwrap_or(0);
+                    let number = if negative {
+                        (self.first + self.entries.len()).checked_sub(n)
+                    } else {
+                        Some(n)
+                    };
+                    let entry = number
+                        .and_then(|n| self.get(n))
+                        .ok_or_else(|| format!("!{}: event not found", spec))?;
+                    out.push_str(entry);
+                    for _ in 0..len {
+                        chars.next();
+                    }
+                }
+                _ => out.push(c),
+            }
+        }
+        Ok(out)
+    }
+
+    /// Replace the contents with lines from a saved history file.
+    pub fn load(&mut self, text: &str) {
+        self.entries.clear();
+        self.first = 1;
+        for line in text.lines() {
+            self.push(line);
+        }
+        self.unsaved = 0;
+    }
+
+    /// Serialize for saving, one entry per line.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for line in &self.entries {
+            text.push_str(line);
+            text.push('\n');
+        }
+        text
+    }
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new()
+    }
+}
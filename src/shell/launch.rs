@@ -0,0 +1,133 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use uefi::boot::{self, LoadImageSource};
+use uefi::proto::device_path::build::{self, DevicePathBuilder};
+use uefi::proto::device_path::DevicePath;
+use uefi::proto::loaded_image::LoadedImage;
+use uefi::{CString16, Handle, Status};
+
+use super::Shell;
+use crate::console::Console;
+use crate::vfs;
+
+const EXEC_USAGE: &str = "exec <path.efi> [args...]";
+
+/// Quote a word of a program's command line if it is empty or holds blanks.
+fn push_word(line: &mut String, word: &str) {
+    if word.is_empty() || word.contains([' ', '\t']) {
+        line.push('"');
+        line.push_str(word);
+        line.push('"');
+    } else {
+        line.push_str(word);
+    }
+}
+
+/// The load options a program gets: its command line in UCS-2, starting
+/// with the program's own path as the UEFI Shell passes it.
+fn command_line(path: &str, args: &[String]) -> Result<CString16, String> {
+    let mut line = String::new();
+    push_word(&mut line, path);
+    for arg in args {
+        line.push(' ');
+        push_word(&mut line, arg);
+    }
+    CString16::try_from(line.as_str())
+        .map_err(|_| String::from("Arguments cannot be passed as UCS-2 text"))
+}
+
+/// The full device path of `path` (its volume's path and a file path node)
+/// built in `storage`, so the program can find the files next to it. `None`
+/// for files on file systems VOS implements, such as `\tmp`.
+fn file_device_path<'a>(path: &str, storage: &'a mut Vec<u8>) -> Option<&'a DevicePath> {
+    let (handle, inner) = vfs::device_of(path)?;
+    let volume = boot::open_protocol_exclusive::<DevicePath>(handle).ok()?;
+    let file = CString16::try_from(inner.as_str()).ok()?;
+    let mut builder = DevicePathBuilder::with_vec(storage);
+    for node in volume.node_iter() {
+        builder = builder.push(&node).ok()?;
+    }
+    builder
+        .push(&build::media::FilePath { path_name: &file })
+        .ok()?
+        .finalize()
+        .ok()
+}
+
+/// `$?` for a program's exit status: the status code without the error
+/// bit, so `NOT_FOUND` is 14.
+fn exit_code(status: Status) -> i32 {
+    let code = status.0 & !(1 << (usize::BITS - 1));
+    i32::try_from(code).unwrap_or(i32::MAX).max(1)
+}
+
+/// Refuse to start a program when `out` captures output, which the
+/// program would bypass by writing to the firmware console.
+pub fn check_console(out: &dyn Console) -> Result<(), String> {
+    if out.is_terminal() {
+        Ok(())
+    } else {
+        Err(String::from("Programs cannot be piped or redirected"))
+    }
+}
+
+/// Start the loaded `image` with `options` as its load options, handing
+/// the screen over while it runs. A failing exit status becomes `$?`.
+pub fn start(
+    sh: &mut Shell,
+    image: Handle,
+    name: &str,
+    options: &[u8],
+    out: &mut dyn Console,
+) -> Result<(), String> {
+    match boot::open_protocol_exclusive::<LoadedImage>(image) {
+        // SAFETY: `options` outlives the program, which only runs until
+        // `start_image` returns
+        Ok(mut loaded) => unsafe {
+            loaded.set_load_options(options.as_ptr(), options.len() as u32);
+        },
+        Err(_) => {
+            let _ = boot::unload_image(image);
+            return Err(format!("Cannot pass arguments to '{}'", name));
+        }
+    }
+
+    // The program may reset the machine instead of returning
+    sh.save_history();
+    out.release_screen();
+    let result = boot::start_image(image);
+    out.restore_screen();
+
+    result.map_err(|e| {
+        sh.failure_status = Some(exit_code(e.status()));
+        format!("'{}' exited with {}", name, e.status())
+    })
+}
+
+/// `exec <path.efi> [args...]`: load and start a UEFI application, such as
+/// a firmware updater or the UEFI Shell, and wait for it to exit. The
+/// screen is handed over while it runs; its exit status becomes `$?`.
+pub fn cmd_exec(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let Some((program, rest)) = args.split_first() else {
+        return Err(format!("Usage: {}", EXEC_USAGE));
+    };
+    check_console(out)?;
+    let path = sh.resolve_path(program);
+    let data = crate::fs::read_file(&path)?;
+    let options = command_line(&path, rest)?;
+
+    let mut storage = Vec::new();
+    let file_path = file_device_path(&path, &mut storage);
+    let image = boot::load_image(
+        boot::image_handle(),
+        LoadImageSource::FromBuffer {
+            buffer: &data,
+            file_path,
+        },
+    )
+    .map_err(|e| format!("Cannot load '{}': {}", path, e.status()))?;
+    start(sh, image, &path, options.as_bytes(), out)
+}
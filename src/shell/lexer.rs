@@ -0,0 +1,663 @@
+extern crate alloc;
+
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+use core::iter::Peekable;
+use core::str::Chars;
+
+/// Characters a backslash escapes outside of quotes. Any other backslash is
+/// kept literally, so UEFI paths such as `\EFI\BOOT` need no quoting.
+const ESCAPABLE: &[char] = &[
+    ' ', '\t', '\\', '\'', '"', '!', '|', '<', '>', '#', '$', ';',
+];
+
+/// Characters a backslash escapes inside double quotes.
+const ESCAPABLE_IN_DOUBLE: &[char] = &['\\', '"', '$'];
+
+/// The characters of shell input with one of lookahead, counting lines.
+#[derive(Clone)]
+struct Input<'a> {
+    chars: Peekable<Chars<'a>>,
+    /// Line of the next character, from 1.
+    line: usize,
+}
+
+impl<'a> Input<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            chars: text.chars().peekable(),
+            line: 1,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&char> {
+        self.chars.peek()
+    }
+
+    fn next_if(&mut self, f: impl FnOnce(&char) -> bool) -> Option<char> {
+        let c = self.chars.next_if(f)?;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+}
+
+impl Iterator for Input<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        self.next_if(|_| true)
+    }
+}
+
+/// Characters that end a word.
+const WORD_END: &[char] = &[' ', '\t', '\r', '\n', '|', '<', '>', ';'];
+
+/// Whether the `"` after a backslash that `chars` is at closes a quoted
+/// path ending in a backslash, as in `"\EFI\BOOT\"`: the quote ends the
+/// word and no other quote follows on the line to close it instead.
+fn closes_path(chars: &Input) -> bool {
+    let mut ahead = chars.clone();
+    ahead.next();
+    ahead.peek().is_none_or(|c| WORD_END.contains(c))
+        && !ahead.take_while(|&c| c != '\n').any(|c| c == '"')
+}
+
+/// A piece of a word; expansions are resolved when the command runs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WordPart {
+    /// Unquoted literal text.
+    Text(String),
+    /// Quoted or backslash-escaped literal text.
+    Quoted(String),
+    /// `$NAME`, `${NAME}` or `$?`; `quoted` inside double quotes.
+    Var { name: String, quoted: bool },
+    /// `$(...)` command substitution; `quoted` inside double quotes.
+    Command { source: String, quoted: bool },
+}
+
+/// A shell word as written, before expansion.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+}
+
+impl Word {
+    /// The word's text if it is plain unquoted text with no expansions,
+    /// as required for keywords and `for` variable names.
+    pub fn literal(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [WordPart::Text(text)] => Some(text),
+            _ => None,
+        }
+    }
+
+    fn push_char(&mut self, c: char, quoted: bool) {
+        match self.parts.last_mut() {
+            Some(WordPart::Text(text)) if !quoted => text.push(c),
+            Some(WordPart::Quoted(text)) if quoted => text.push(c),
+            _ => {
+                let mut text = String::new();
+                text.push(c);
+                self.parts.push(if quoted {
+                    WordPart::Quoted(text)
+                } else {
+                    WordPart::Text(text)
+                });
+            }
+        }
+    }
+
+    /// Start a quoted part so that empty quotes (`""`) still make a word.
+    fn push_empty_quote(&mut self) {
+        if !matches!(self.parts.last(), Some(WordPart::Quoted(_))) {
+            self.parts.push(WordPart::Quoted(String::new()));
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Word(Word),
+    /// `|`
+    Pipe,
+    /// `<`
+    RedirectIn,
+    /// `>`
+    RedirectOut,
+    /// `>>`
+    RedirectAppend,
+    /// `<<DELIM`, holding the here-document body from the following lines.
+    HereDoc(Word),
+    /// `;` or a newline
+    Separator,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexError {
+    UnterminatedQuote(char),
+    UnterminatedBrace,
+    UnterminatedSubstitution,
+    MissingDelimiter,
+    UnterminatedHereDoc(String),
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LexError::UnterminatedQuote(q) => write!(f, "Unterminated {} quote", q),
+            LexError::UnterminatedBrace => write!(f, "Unterminated ${{...}}"),
+            LexError::UnterminatedSubstitution => write!(f, "Unterminated $(...)"),
+            LexError::MissingDelimiter => write!(f, "Missing here-document delimiter after <<"),
+            LexError::UnterminatedHereDoc(delimiter) => {
+                write!(f, "Here-document not terminated by '{}'", delimiter)
+            }
+        }
+    }
+}
+
+fn is_name_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+/// Read the text of a `$(...)` up to its matching `)`, skipping quoted
+/// parentheses.
+fn read_substitution(chars: &mut Input) -> Result<String, LexError> {
+    let mut source = String::new();
+    let mut depth = 0;
+    let mut quote = None;
+    loop {
+        let c = chars.next().ok_or(LexError::UnterminatedSubstitution)?;
+        match (quote, c) {
+            (None, ')') if depth == 0 => return Ok(source),
+            (None, '(') => depth += 1,
+            (None, ')') => depth -= 1,
+            (None, '\'' | '"') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            // Single-quoted text is literal here too
+            (None | Some('"'), '\\') => {
+                source.push(c);
+                if let Some(next) = chars.next() {
+                    source.push(next);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        source.push(c);
+    }
+}
+
+/// Read what follows a `$` and return it as a word part, or `None` if the
+/// `$` does not start an expansion and is kept literally.
+fn read_expansion(chars: &mut Input, quoted: bool) -> Result<Option<WordPart>, LexError> {
+    let name = match chars.peek() {
+        Some('?') => {
+            chars.next();
+            String::from("?")
+        }
+        Some('(') => {
+            chars.next();
+            let source = read_substitution(chars)?;
+            return Ok(Some(WordPart::Command { source, quoted }));
+        }
+        Some('{') => {
+            chars.next();
+            let mut name = String::new();
+            loop {
+                match chars.next() {
+                    Some('}') => break,
+                    Some(c) => name.push(c),
+                    None => return Err(LexError::UnterminatedBrace),
+                }
+            }
+            name
+        }
+        Some(&c) if is_name_start(c) => {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if !is_name_char(c) {
+                    break;
+                }
+                name.push(c);
+                chars.next();
+            }
+            name
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(WordPart::Var { name, quoted }))
+}
+
+/// Read the delimiter after `<<`. Quoting any part of it (`'EOF'`) makes
+/// the body literal.
+fn read_delimiter(chars: &mut Input) -> Result<(String, bool), LexError> {
+    while chars.next_if(|&c| c == ' ' || c == '\t').is_some() {}
+    let mut delimiter = String::new();
+    let mut quoted = false;
+    while let Some(c) =
+        chars.next_if(|&c| !matches!(c, ' ' | '\t' | '\r' | '\n' | '|' | '<' | '>' | ';'))
+    {
+        match c {
+            '\'' | '"' => quoted = true,
+            _ => delimiter.push(c),
+        }
+    }
+    if delimiter.is_empty() {
+        return Err(LexError::MissingDelimiter);
+    }
+    Ok((delimiter, quoted))
+}
+
+/// Read here-document lines up to a line holding only `delimiter`. The
+/// body keeps its newlines; unless `quoted`, `$` expansions work as inside
+/// double quotes.
+fn read_heredoc(chars: &mut Input, delimiter: &str, quoted: bool) -> Result<Word, LexError> {
+    let mut body = String::new();
+    loop {
+        if chars.peek().is_none() {
+            return Err(LexError::UnterminatedHereDoc(String::from(delimiter)));
+        }
+        let mut line = String::new();
+        while let Some(c) = chars.next_if(|&c| c != '\n') {
+            line.push(c);
+        }
+        chars.next();
+        if line.trim_end_matches('\r') == delimiter {
+            break;
+        }
+        body.push_str(&line);
+        body.push('\n');
+    }
+    if quoted {
+        return Ok(Word {
+            parts: vec![WordPart::Quoted(body)],
+        });
+    }
+
+    let mut word = Word::default();
+    word.push_empty_quote();
+    let mut chars = Input::new(&body);
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.peek() {
+                Some(&next) if next == '\\' || next == '$' => {
+                    word.push_char(next, true);
+                    chars.next();
+                }
+                _ => word.push_char('\\', true),
+            },
+            '$' => match read_expansion(&mut chars, true)? {
+                Some(part) => word.parts.push(part),
+                None => word.push_char('$', true),
+            },
+            ch => word.push_char(ch, true),
+        }
+    }
+    Ok(word)
+}
+
+/// Split shell input into words and operators.
+///
+/// Words are separated by blanks and by the unquoted operators `|`, `<`,
+/// `>`, `>>` and `;`; a newline is a separator like `;`. `'...'` quotes
+/// text literally, `"..."` quotes text but still honours `\"`, `\\`, `\$`
+/// and expansions, and a backslash outside quotes escapes a blank, quote,
+/// backslash, `!`, `#`, `$` or operator character. An unquoted `#` at the
+/// start of a word begins a comment that runs to the end of the line.
+///
+/// A `\"` that ends a word, with no other `"` after it on the line, is a
+/// backslash and the closing quote, so `"\EFI\BOOT\"` works as a path.
+///
+/// `$NAME`, `${NAME}`, `$?` and `$(...)` are kept as word parts and expanded
+/// when the command runs.
+///
+/// `<<DELIM` takes the lines after the current one, up to a line reading
+/// `DELIM`, as a here-document for the command's input.
+///
+/// Each token comes with the line of the input it starts on, from 1.
+pub fn tokenize(input: &str) -> Result<Vec<(Token, usize)>, LexError> {
+    let mut tokens = Vec::new();
+    let mut word = Word::default();
+    let mut in_word = false;
+    let mut word_line = 1;
+    let mut chars = Input::new(input);
+    // Here-documents whose bodies start after the current line
+    let mut heredocs: Vec<(usize, String, bool)> = Vec::new();
+
+    loop {
+        let line = chars.line;
+        let Some(c) = chars.next() else {
+            break;
+        };
+        if !in_word {
+            word_line = line;
+        }
+        match c {
+            ' ' | '\t' | '\r' | '\n' | '|' | '<' | '>' | ';' => {
+                if in_word {
+                    tokens.push((Token::Word(core::mem::take(&mut word)), word_line));
+                    in_word = false;
+                }
+                match c {
+                    '|' => tokens.push((Token::Pipe, line)),
+                    '<' if chars.peek() == Some(&'<') => {
+                        chars.next();
+                        let (delimiter, quoted) = read_delimiter(&mut chars)?;
+                        heredocs.push((tokens.len(), delimiter, quoted));
+                        tokens.push((Token::HereDoc(Word::default()), line));
+                    }
+                    '<' => tokens.push((Token::RedirectIn, line)),
+                    '>' if chars.peek() == Some(&'>') => {
+                        chars.next();
+                        tokens.push((Token::RedirectAppend, line));
+                    }
+                    '>' => tokens.push((Token::RedirectOut, line)),
+                    ';' => tokens.push((Token::Separator, line)),
+                    '\n' => {
+                        tokens.push((Token::Separator, line));
+                        for (index, delimiter, quoted) in heredocs.drain(..) {
+                            tokens[index].0 =
+                                Token::HereDoc(read_heredoc(&mut chars, &delimiter, quoted)?);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            '\'' => {
+                in_word = true;
+                word.push_empty_quote();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => word.push_char(ch, true),
+                        None => return Err(LexError::UnterminatedQuote('\'')),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                word.push_empty_quote();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.peek().copied() {
+                            Some('"') if closes_path(&chars) => word.push_char('\\', true),
+                            Some(next) if ESCAPABLE_IN_DOUBLE.contains(&next) => {
+                                word.push_char(next, true);
+                                chars.next();
+                            }
+                            _ => word.push_char('\\', true),
+                        },
+                        Some('$') => match read_expansion(&mut chars, true)? {
+                            Some(part) => word.parts.push(part),
+                            None => word.push_char('$', true),
+                        },
+                        Some(ch) => word.push_char(ch, true),
+                        None => return Err(LexError::UnterminatedQuote('"')),
+                    }
+                }
+            }
+            '\\' => {
+                in_word = true;
+                match chars.peek() {
+                    Some(&next) if ESCAPABLE.contains(&next) => {
+                        word.push_char(next, true);
+                        chars.next();
+                    }
+                    _ => word.push_char('\\', false),
+                }
+            }
+            '$' => {
+                in_word = true;
+                match read_expansion(&mut chars, false)? {
+                    Some(part) => word.parts.push(part),
+                    None => word.push_char('$', false),
+                }
+            }
+            '#' if !in_word => while chars.next_if(|&ch| ch != '\n').is_some() {},
+            ch => {
+                in_word = true;
+                word.push_char(ch, false);
+            }
+        }
+    }
+
+    if in_word {
+        tokens.push((Token::Word(word), word_line));
+    }
+    if let Some((_, delimiter, _)) = heredocs.pop() {
+        return Err(LexError::UnterminatedHereDoc(delimiter));
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::string::ToString;
+
+    fn text(s: &str) -> WordPart {
+        WordPart::Text(s.to_string())
+    }
+
+    fn quoted(s: &str) -> WordPart {
+        WordPart::Quoted(s.to_string())
+    }
+
+    /// The parts of each word, with operators as `None`.
+    fn words(input: &str) -> Vec<Option<Vec<WordPart>>> {
+        tokenize(input)
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| match token {
+                Token::Word(word) => Some(word.parts),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn blanks_and_operators_split_words() {
+        let tokens: Vec<Token> = tokenize("ls -l|wc>out;cat<in >>log")
+            .unwrap()
+            .into_iter()
+            .map(|(token, _)| token)
+            .collect();
+        let word = |s: &str| {
+            Token::Word(Word {
+                parts: vec![text(s)],
+            })
+        };
+        assert_eq!(
+            tokens,
+            vec![
+                word("ls"),
+                word("-l"),
+                Token::Pipe,
+                word("wc"),
+                Token::RedirectOut,
+                word("out"),
+                Token::Separator,
+                word("cat"),
+                Token::RedirectIn,
+                word("in"),
+                Token::RedirectAppend,
+                word("log"),
+            ]
+        );
+    }
+
+    #[test]
+    fn single_quotes_are_literal() {
+        assert_eq!(
+            words(r#"echo 'a "b" $X \n|;'"#),
+            vec![
+                Some(vec![text("echo")]),
+                Some(vec![quoted(r#"a "b" $X \n|;"#)])
+            ]
+        );
+    }
+
+    #[test]
+    fn empty_quotes_make_a_word() {
+        assert_eq!(words("''"), vec![Some(vec![quoted("")])]);
+        assert_eq!(words(r#""""#), vec![Some(vec![quoted("")])]);
+    }
+
+    #[test]
+    fn double_quotes_keep_escapes_and_expansions() {
+        assert_eq!(
+            words(r#""say \"hi\" \\ \$X $HOME""#),
+            vec![Some(vec![
+                quoted(r#"say "hi" \ $X "#),
+                WordPart::Var {
+                    name: "HOME".to_string(),
+                    quoted: true
+                },
+            ])]
+        );
+        // Other backslashes stay
+        assert_eq!(words(r#""a\nb""#), vec![Some(vec![quoted(r"a\nb")])]);
+    }
+
+    #[test]
+    fn quoted_path_may_end_in_backslash() {
+        assert_eq!(
+            words(r#"ls "\EFI\BOOT\""#),
+            vec![Some(vec![text("ls")]), Some(vec![quoted(r"\EFI\BOOT\")])]
+        );
+        assert_eq!(
+            words(r#"cd "\My Files\" > out"#)[1],
+            Some(vec![quoted(r"\My Files\")])
+        );
+        // A later quote on the line closes the string instead
+        assert_eq!(words(r#"echo "a \" b""#)[1], Some(vec![quoted(r#"a " b"#)]));
+    }
+
+    #[test]
+    fn unquoted_backslashes() {
+        // Paths need no quoting
+        assert_eq!(words(r"\EFI\BOOT"), vec![Some(vec![text(r"\EFI\BOOT")])]);
+        assert_eq!(
+            words(r"a\ b\|c"),
+            vec![Some(vec![
+                text("a"),
+                quoted(" "),
+                text("b"),
+                quoted("|"),
+                text("c")
+            ])]
+        );
+    }
+
+    #[test]
+    fn expansions() {
+        assert_eq!(
+            words("$? ${A B}x $(echo ')') $ 5"),
+            vec![
+                Some(vec![WordPart::Var {
+                    name: "?".to_string(),
+                    quoted: false
+                }]),
+                Some(vec![
+                    WordPart::Var {
+                        name: "A B".to_string(),
+                        quoted: false
+                    },
+                    text("x"),
+                ]),
+                Some(vec![WordPart::Command {
+                    source: "echo ')'".to_string(),
+                    quoted: false
+                }]),
+                Some(vec![text("$")]),
+                Some(vec![text("5")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn substitutions_skip_quoted_and_escaped_parens() {
+        let command = |input: &str| match words(input).remove(0) {
+            Some(mut parts) => match parts.remove(0) {
+                WordPart::Command { source, .. } => source,
+                part => panic!("unexpected {:?}", part),
+            },
+            None => panic!("no word"),
+        };
+        assert_eq!(command("$(ls '\\EFI\\BOOT\\')"), "ls '\\EFI\\BOOT\\'");
+        assert_eq!(command("$(echo \\))"), "echo \\)");
+        assert_eq!(command("$(echo \"a\\\")\")"), "echo \"a\\\")\"");
+    }
+
+    #[test]
+    fn comments_start_words_only() {
+        assert_eq!(
+            words("echo a#b # rest ; ls"),
+            vec![Some(vec![text("echo")]), Some(vec![text("a#b")])]
+        );
+    }
+
+    #[test]
+    fn heredoc_reads_following_lines() {
+        let tokens = tokenize("cat <<EOF\nhi $X\n\\$Y\nEOF\necho").unwrap();
+        assert_eq!(
+            tokens[1].0,
+            Token::HereDoc(Word {
+                parts: vec![
+                    quoted("hi "),
+                    WordPart::Var {
+                        name: "X".to_string(),
+                        quoted: true
+                    },
+                    quoted("\n$Y\n"),
+                ]
+            })
+        );
+        let tokens = tokenize("cat <<'EOF'\n$X\nEOF\n").unwrap();
+        assert_eq!(
+            tokens[1].0,
+            Token::HereDoc(Word {
+                parts: vec![quoted("$X\n")]
+            })
+        );
+    }
+
+    #[test]
+    fn tokens_carry_their_line() {
+        let lines: Vec<usize> = tokenize("a 'b\nc' d\n\n  e <<X\nbody\nX\nf $(g\n) h")
+            .unwrap()
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect();
+        // a 'b c' d ; ; e << ; f $(g) h
+        assert_eq!(lines, vec![1, 1, 2, 2, 3, 4, 4, 4, 7, 7, 8]);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(tokenize("echo 'a"), Err(LexError::UnterminatedQuote('\'')));
+        assert_eq!(
+            tokenize(r#"echo "a"#),
+            Err(LexError::UnterminatedQuote('"'))
+        );
+        assert_eq!(tokenize("echo ${A"), Err(LexError::UnterminatedBrace));
+        assert_eq!(
+            tokenize("echo $(ls"),
+            Err(LexError::UnterminatedSubstitution)
+        );
+        assert_eq!(tokenize("cat <<"), Err(LexError::MissingDelimiter));
+        assert_eq!(
+            tokenize("cat <<EOF\nbody\n"),
+            Err(LexError::UnterminatedHereDoc("EOF".to_string()))
+        );
+    }
+}
@@ -0,0 +1,828 @@
+extern crate alloc;
+
+pub mod bootopts;
+pub mod commands;
+pub mod complete;
+pub mod condition;
+pub mod dirs;
+pub mod disks;
+pub mod editor;
+pub mod exec;
+pub mod history;
+pub mod launch;
+pub mod lexer;
+pub mod parser;
+pub mod script;
+pub mod search;
+pub mod vars;
+pub mod view;
+
+use alloc::collections::VecDeque;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt::Write;
+use uefi::proto::console::pointer::Pointer;
+use uefi::proto::console::text::{Key, ScanCode};
+use uefi::boot::{EventType, OpenProtocolAttributes, OpenProtocolParams};
+use spin::Mutex;
+use uefi::{boot, system, Event, Identify};
+
+use editor::{EditAction, EditKey, LineEditor};
+use history::History;
+use parser::ParseError;
+use script::Position;
+use vars::Vars;
+
+use crate::console::{Console, Style, TextConsole};
+use crate::gui::desktop::{ClickAction, Desktop};
+use crate::gui::gop::ScreenInfo;
+use crate::gui::mouse::MouseState;
+
+// ── Line input (shared by both shells) ──
+
+/// Translate a UEFI key into a line-editor key.
+fn edit_key(key: Key) -> Option<EditKey> {
+    match key {
+        Key::Printable(c) => match char::from(c) {
+            '\r' | '\n' => Some(EditKey::Enter),
+            '\u{8}' | '\u{7f}' => Some(EditKey::Backspace),
+            '\t' => Some(EditKey::Tab),
+            ch @ '\u{1}'..='\u{1a}' => Some(EditKey::Ctrl((b'a' + ch as u8 - 1) as char)),
+            ch if ch >= ' ' => Some(EditKey::Char(ch)),
+            _ => None,
+        },
+        Key::Special(ScanCode::LEFT) => Some(EditKey::Left),
+        Key::Special(ScanCode::RIGHT) => Some(EditKey::Right),
+        Key::Special(ScanCode::HOME) => Some(EditKey::Home),
+        Key::Special(ScanCode::END) => Some(EditKey::End),
+        Key::Special(ScanCode::UP) => Some(EditKey::Up),
+        Key::Special(ScanCode::DOWN) => Some(EditKey::Down),
+        Key::Special(ScanCode::DELETE) => Some(EditKey::Delete),
+        _ => None,
+    }
+}
+
+/// Most keys kept while a command runs; later ones are dropped.
+const TYPE_AHEAD_SIZE: usize = 64;
+
+/// Keys typed while a command was running, read before new ones so the
+/// next line starts with them.
+static TYPE_AHEAD: Mutex<VecDeque<EditKey>> = Mutex::new(VecDeque::new());
+
+/// Read the next key press from `ConIn`, if one is pending, skipping any
+/// the line editor does not understand.
+fn read_console_key() -> Option<EditKey> {
+    loop {
+        match system::with_stdin(|stdin| stdin.read_key()) {
+            Ok(Some(key)) => {
+                if let Some(key) = edit_key(key) {
+                    return Some(key);
+                }
+            }
+            _ => return None,
+        }
+    }
+}
+
+/// Keep a key typed ahead while a command runs.
+fn type_ahead(key: EditKey) {
+    let mut keys = TYPE_AHEAD.lock();
+    if keys.len() < TYPE_AHEAD_SIZE {
+        keys.push_back(key);
+    }
+}
+
+fn has_type_ahead() -> bool {
+    !TYPE_AHEAD.lock().is_empty()
+}
+
+/// Drop keys typed ahead, e.g. when a command is interrupted.
+fn clear_type_ahead() {
+    TYPE_AHEAD.lock().clear();
+}
+
+/// The next key typed ahead or pending in `ConIn`, if any.
+fn read_edit_key() -> Option<EditKey> {
+    let key = TYPE_AHEAD.lock().pop_front();
+    key.or_else(read_console_key)
+}
+
+// ── Shared helpers ──
+
+/// Block until a key is typed ahead or ConIn has one ready.
+fn wait_for_key() {
+    if has_type_ahead() {
+        return;
+    }
+    let event = system::with_stdin(|stdin| stdin.wait_for_key_event());
+    if let Some(event) = event {
+        let mut events = [event];
+        let _ = boot::wait_for_event(&mut events);
+    }
+}
+
+/// Block until a key the line editor understands is pressed.
+fn read_key() -> EditKey {
+    loop {
+        wait_for_key();
+        if let Some(key) = read_edit_key() {
+            return key;
+        }
+    }
+}
+
+/// Ask a yes/no question at the console and wait for a key; only `y`
+/// answers yes. Keys typed before the question appears are discarded.
+pub fn confirm(out: &mut dyn Console, question: &str) -> bool {
+    clear_type_ahead();
+    while read_console_key().is_some() {}
+    let _ = write!(out, "{} [y/N] ", question);
+    out.flush();
+    let key = read_key();
+    let yes = matches!(key, EditKey::Char('y' | 'Y'));
+    let _ = writeln!(out, "{}", if yes { "y" } else { "n" });
+    yes
+}
+
+const VOS_VERSION: &str = "0.1.0";
+
+#[cfg(target_arch = "x86_64")]
+const ARCH: &str = "x86_64";
+#[cfg(target_arch = "aarch64")]
+const ARCH: &str = "aarch64";
+
+/// Firmware details shown by `info` and exposed as built-in variables.
+struct SystemInfo {
+    fw_vendor: String,
+    fw_revision: u32,
+    uefi_version: String,
+}
+
+fn system_info() -> SystemInfo {
+    let uefi_rev = system::uefi_revision();
+    SystemInfo {
+        fw_vendor: system::firmware_vendor().to_string(),
+        fw_revision: system::firmware_revision(),
+        uefi_version: format!("{}.{}", uefi_rev.major(), uefi_rev.minor()),
+    }
+}
+
+fn info_text() -> String {
+    let info = system_info();
+    let mut s = String::new();
+    let _ = writeln!(s, "VOS v{}", VOS_VERSION);
+    let _ = writeln!(s, "Firmware: {} (rev {})", info.fw_vendor, info.fw_revision);
+    let _ = writeln!(s, "UEFI: {}", info.uefi_version);
+    s
+}
+
+/// Value of a read-only built-in variable (see `vars::BUILTIN_VARS`).
+fn builtin_var(sh: &Shell, name: &str) -> Option<String> {
+    let value = match name {
+        "ARCH" => String::from(ARCH),
+        "FW_REVISION" => system_info().fw_revision.to_string(),
+        "FW_VENDOR" => system_info().fw_vendor,
+        "PWD" => sh.cwd.clone(),
+        "UEFI_VERSION" => system_info().uefi_version,
+        "VOS_VERSION" => String::from(VOS_VERSION),
+        _ => return None,
+    };
+    Some(value)
+}
+
+fn print_banner(out: &mut dyn Console, title: &str) {
+    out.set_style(Style::Banner);
+    let _ = writeln!(out, "  _    _____  ___");
+    let _ = writeln!(out, " | |  / / __ \\/ __|");
+    let _ = writeln!(out, " | | / / /_/ /\\__ \\");
+    let _ = writeln!(out, " | |/ / ____/ ___) |");
+    let _ = writeln!(out, " |___/_/    /____/");
+    let _ = writeln!(out);
+    out.set_style(Style::Normal);
+    let _ = writeln!(out, " {}", title);
+    let _ = writeln!(out, " Type 'help' for available commands.");
+    let _ = writeln!(out);
+}
+
+/// List completion candidates in columns across the console width.
+fn print_candidates(out: &mut dyn Console, candidates: &[complete::Candidate]) {
+    let width = candidates
+        .iter()
+        .map(|c| c.name.chars().count() + usize::from(c.is_dir))
+        .max()
+        .unwrap_or(0)
+        + 2;
+    let per_row = (out.size().0 / width).max(1);
+    for (i, c) in candidates.iter().enumerate() {
+        let mut name = c.name.clone();
+        if c.is_dir {
+            name.push('\\');
+        }
+        let last = i + 1 == candidates.len() || (i + 1) % per_row == 0;
+        if last {
+            let _ = writeln!(out, "{}", name);
+        } else {
+            let _ = write!(out, "{:<width$}", name, width = width);
+        }
+    }
+}
+
+/// Print the prompt, which shows the current directory.
+fn print_prompt(sh: &Shell, out: &mut dyn Console) {
+    if sh.continuation.is_empty() {
+        out.write_styled(Style::Prompt, &format!("vos {}> ", sh.cwd));
+    } else {
+        out.write_styled(Style::Prompt, "> ");
+    }
+}
+
+/// Print an error message. An empty message (a command that failed
+/// silently, like a false `test`) prints nothing.
+fn report_error(out: &mut dyn Console, msg: &str) {
+    if msg.is_empty() {
+        return;
+    }
+    out.set_style(Style::Error);
+    let _ = writeln!(out, "{}", msg);
+    out.set_style(Style::Normal);
+}
+
+/// State kept between commands.
+pub struct Shell {
+    pub history: History,
+    /// Input for the running command, from a pipe or `<` redirection.
+    pub stdin: Option<Vec<u8>>,
+    /// `set -e`: stop a script at the first failing command.
+    pub errexit: bool,
+    pub vars: Vars,
+    /// Exit status of the last command, read as `$?` (0 on success, 1 on error).
+    pub status: i32,
+    /// Status a failing command reports instead of 1, such as the exit
+    /// status of a program run by `exec`.
+    pub failure_status: Option<i32>,
+    /// The script statement being run, prefixed to errors.
+    pub position: Option<Position>,
+    /// Current directory, always absolute and normalized.
+    pub cwd: String,
+    /// Directories saved by `pushd`.
+    pub dir_stack: Vec<String>,
+    /// Interactive lines read so far of input that continues on the next
+    /// line, such as an `if` block or a here-document.
+    pub continuation: String,
+}
+
+impl Shell {
+    pub fn new() -> Self {
+        let mut history = History::new();
+        if let Ok(data) = crate::fs::read_file(history::HISTORY_PATH) {
+            history.load(&String::from_utf8_lossy(&data));
+        }
+        Self {
+            history,
+            stdin: None,
+            errexit: false,
+            vars: Vars::new(),
+            status: 0,
+            failure_status: None,
+            position: None,
+            cwd: String::from(crate::path::ROOT),
+            dir_stack: Vec::new(),
+            continuation: String::new(),
+        }
+    }
+
+    /// Take the running command's piped or redirected input, if any.
+    pub fn take_stdin(&mut self) -> Option<Vec<u8>> {
+        self.stdin.take()
+    }
+
+    /// Write the history to the boot volume; failures (e.g. read-only media) are only logged.
+    pub fn save_history(&mut self) {
+        self.history.mark_saved();
+        let result = crate::fs::create_dir_all(history::VOS_DIR).and_then(|_| {
+            crate::fs::write_file(history::HISTORY_PATH, self.history.to_text().as_bytes())
+        });
+        if let Err(e) = result {
+            log::warn!("Could not save history: {}", e);
+        }
+    }
+
+    /// Apply one key to the line being edited; returns the line once it is complete.
+    fn edit(
+        &mut self,
+        editor: &mut LineEditor,
+        key: EditKey,
+        out: &mut dyn Console,
+    ) -> Option<String> {
+        match editor.handle(key) {
+            EditAction::None => {}
+            EditAction::Redraw => editor.render(out),
+            EditAction::Submit => {
+                editor.finish(out);
+                return Some(editor.line());
+            }
+            EditAction::Cancel => {
+                editor.handle(EditKey::End);
+                editor.render(out);
+                let _ = writeln!(out, "^C");
+                self.continuation.clear();
+                return Some(String::new());
+            }
+            EditAction::ClearScreen => {
+                out.clear();
+                print_prompt(self, out);
+                editor.rebase(out);
+                editor.render(out);
+            }
+            EditAction::HistoryOlder => {
+                if let Some(entry) = self.history.older(&editor.line()) {
+                    editor.set_line(&String::from(entry));
+                    editor.render(out);
+                }
+            }
+            EditAction::HistoryNewer => {
+                if let Some(entry) = self.history.newer() {
+                    editor.set_line(&String::from(entry));
+                    editor.render(out);
+                }
+            }
+            EditAction::Complete => {
+                if let Some(c) = self.complete(editor) {
+                    editor.replace_range(c.start, editor.cursor(), &c.replacement);
+                    editor.render(out);
+                }
+            }
+            EditAction::ListCompletions => {
+                if let Some(c) = self.complete(editor) {
+                    if c.candidates.len() > 1 {
+                        editor.finish(out);
+                        print_candidates(out, &c.candidates);
+                        print_prompt(self, out);
+                        editor.rebase(out);
+                        editor.render(out);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn complete(&self, editor: &LineEditor) -> Option<complete::Completion> {
+        let names: Vec<&str> = commands::COMMANDS.iter().map(|c| c.name).collect();
+        complete::complete(&editor.line(), editor.cursor(), &names, &|dir| {
+            crate::vfs::read_dir(&self.resolve_path(dir))
+                .map(|entries| {
+                    entries
+                        .into_iter()
+                        .map(|e| complete::Candidate {
+                            name: e.name,
+                            is_dir: e.is_dir,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default()
+        })
+    }
+
+    /// Collect one interactive line. Returns the input to run once it is
+    /// complete, which for `if` blocks, loops and here-documents takes
+    /// several lines.
+    pub fn accept_line(&mut self, line: &str) -> Option<String> {
+        let line = if self.continuation.is_empty() {
+            line.trim()
+        } else {
+            line
+        };
+        if self.continuation.is_empty() && line.is_empty() {
+            return None;
+        }
+        self.continuation.push_str(line);
+        self.continuation.push('\n');
+        if let Err(ParseError::Incomplete(_)) = self.parse(&self.continuation) {
+            return None;
+        }
+        let text = core::mem::take(&mut self.continuation);
+        Some(String::from(text.trim_end()))
+    }
+
+    /// Run interactive input, reporting errors in the error style.
+    pub fn run_line(&mut self, line: &str, out: &mut dyn Console) {
+        // Multi-line input is neither expanded nor recalled by history
+        if line.contains('\n') {
+            if let Err(e) = self.execute(line, out) {
+                report_error(out, &e);
+            }
+            return;
+        }
+        let line = match self.history.expand(line) {
+            Ok(expanded) => {
+                if expanded != line {
+                    let _ = writeln!(out, "{}", expanded);
+                }
+                expanded
+            }
+            Err(e) => {
+                report_error(out, &e);
+                return;
+            }
+        };
+        self.history.push(&line);
+        if self.history.unsaved() >= history::SAVE_EVERY {
+            self.save_history();
+        }
+
+        if let Err(e) = self.execute(&line, out) {
+            report_error(out, &e);
+        }
+    }
+}
+
+impl Default for Shell {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ── Text mode shell (fallback) ──
+
+fn read_line(shell: &mut Shell, out: &mut dyn Console) -> String {
+    let mut editor = LineEditor::new();
+    editor.begin(out);
+    shell.history.reset_nav();
+    loop {
+        wait_for_key();
+
+        if let Some(key) = read_edit_key() {
+            if let Some(line) = shell.edit(&mut editor, key, out) {
+                return line;
+            }
+        }
+    }
+}
+
+pub fn run_shell() -> ! {
+    let mut console = TextConsole;
+    let mut shell = Shell::new();
+    console.clear();
+    system::with_stdout(|stdout| {
+        let _ = stdout.enable_cursor(true);
+    });
+    print_banner(&mut console, "VOS v0.1.0 - UEFI Shell");
+    shell.run_startup(&mut console);
+
+    loop {
+        print_prompt(&shell, &mut console);
+
+        let line = read_line(&mut shell, &mut console);
+        let Some(text) = shell.accept_line(&line) else {
+            continue;
+        };
+
+        shell.run_line(&text, &mut console);
+    }
+}
+
+// ── GUI mode shell ──
+
+/// The GUI terminal window; flushing redraws the desktop and mouse cursor.
+struct GuiConsole<'a> {
+    desktop: &'a mut Desktop,
+    mouse: &'a mut MouseState,
+}
+
+impl Write for GuiConsole<'_> {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.desktop.terminal.write_str(s);
+        Ok(())
+    }
+}
+
+impl Console for GuiConsole<'_> {
+    fn set_style(&mut self, style: Style) {
+        self.desktop.terminal.set_style(style);
+    }
+
+    fn clear(&mut self) {
+        self.desktop.terminal.clear();
+        self.desktop.needs_full_redraw = true;
+    }
+
+    fn flush(&mut self) {
+        render_with_cursor(self.desktop, self.mouse);
+    }
+
+    fn size(&self) -> (usize, usize) {
+        Console::size(&self.desktop.terminal)
+    }
+
+    fn cursor(&self) -> (usize, usize) {
+        Console::cursor(&self.desktop.terminal)
+    }
+
+    fn set_cursor(&mut self, col: usize, row: usize) {
+        Console::set_cursor(&mut self.desktop.terminal, col, row);
+    }
+
+    /// Start the other program on a blank text console instead of over the
+    /// desktop.
+    fn release_screen(&mut self) {
+        system::with_stdout(|stdout| {
+            let _ = stdout.clear();
+            let _ = stdout.enable_cursor(true);
+        });
+    }
+
+    fn restore_screen(&mut self) {
+        let screen = ScreenInfo {
+            width: self.desktop.fb.width,
+            height: self.desktop.fb.height,
+        };
+        if let Err(e) = crate::gui::gop::restore_mode(screen) {
+            log::warn!("Cannot restore the video mode: {}", e);
+        }
+        self.desktop.fb.mark_all_dirty();
+        render_full_with_cursor(self.desktop, self.mouse);
+    }
+}
+
+/// Try to open the UEFI SimplePointer protocol.
+/// Uses GetProtocol (not Exclusive) to avoid disconnecting the USB mouse driver.
+fn try_open_pointer() -> Option<boot::ScopedProtocol<Pointer>> {
+    let handle = boot::get_handle_for_protocol::<Pointer>().ok()?;
+    unsafe {
+        boot::open_protocol::<Pointer>(
+            OpenProtocolParams {
+                handle,
+                agent: boot::image_handle(),
+                controller: None,
+            },
+            OpenProtocolAttributes::GetProtocol,
+        )
+        .ok()
+    }
+}
+
+/// Create a periodic timer event for mouse polling (~60fps)
+fn create_timer_event() -> Option<Event> {
+    let event = unsafe {
+        boot::create_event(
+            EventType::TIMER,
+            boot::Tpl::APPLICATION,
+            None,
+            None,
+        )
+    };
+    if let Ok(ref evt) = event {
+        // 160,000 * 100ns = 16ms = ~62fps
+        let _ = boot::set_timer(evt, boot::TimerTrigger::Periodic(160_000));
+    }
+    event.ok()
+}
+
+/// Render with cursor: erase old cursor, render scene, draw new cursor, flush
+fn render_with_cursor(desktop: &mut Desktop, mouse: &mut MouseState) {
+    mouse.erase_cursor(&mut desktop.fb);
+    desktop.render();
+    mouse.draw_cursor(&mut desktop.fb);
+    desktop.fb.flush();
+}
+
+/// Full render with cursor (for full redraws)
+fn render_full_with_cursor(desktop: &mut Desktop, mouse: &mut MouseState) {
+    desktop.needs_full_redraw = true;
+    render_with_cursor(desktop, mouse);
+}
+
+/// Active boot options for the boot menu, except the one running VOS.
+fn boot_targets() -> Vec<(u16, String)> {
+    let current = crate::bootmgr::boot_current();
+    let order = crate::bootmgr::boot_order().unwrap_or_default();
+    crate::bootmgr::load_options()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|o| o.active() && !o.hidden() && order.contains(&o.number))
+        .filter(|o| Some(o.number) != current)
+        .map(|o| (o.number, o.description))
+        .collect()
+}
+
+/// Poll the mouse and act on clicks. Returns a command picked from the
+/// desktop, such as `boot 0003` from the boot menu, to run as if typed.
+fn handle_mouse_poll(
+    desktop: &mut Desktop,
+    mouse: &mut MouseState,
+    pointer: &mut Option<boot::ScopedProtocol<Pointer>>,
+) -> Option<String> {
+    if let Some(ref mut ptr) = pointer {
+        if let Ok(Some(state)) = ptr.read_state() {
+            let old_x = mouse.x;
+            let old_y = mouse.y;
+            mouse.update(&state);
+
+            // Update desktop's mouse position for hover effects
+            desktop.mouse_x = mouse.x;
+            desktop.mouse_y = mouse.y;
+
+            if mouse.left_clicked {
+                let action = desktop.handle_click(mouse.x, mouse.y);
+                match action {
+                    ClickAction::ToggleStartMenu => {
+                        render_full_with_cursor(desktop, mouse);
+                    }
+                    ClickAction::CloseTerminal => {
+                        desktop.terminal.clear();
+                        desktop.terminal.write_str("Terminal cleared.\n");
+                        render_full_with_cursor(desktop, mouse);
+                    }
+                    ClickAction::MenuTerminal => {
+                        desktop.terminal.set_style(Style::Normal);
+                        desktop.terminal.write_str("Terminal is active.\n");
+                        render_full_with_cursor(desktop, mouse);
+                    }
+                    ClickAction::MenuInfo => {
+                        desktop.terminal.write_str(&info_text());
+                        render_full_with_cursor(desktop, mouse);
+                    }
+                    ClickAction::MenuBoot => {
+                        desktop.open_boot_menu(boot_targets());
+                        render_full_with_cursor(desktop, mouse);
+                    }
+                    ClickAction::Boot(number) => {
+                        render_full_with_cursor(desktop, mouse);
+                        return Some(format!("boot {:04X}", number));
+                    }
+                    ClickAction::MenuReboot => {
+                        render_full_with_cursor(desktop, mouse);
+                        return Some(String::from("reboot"));
+                    }
+                    ClickAction::None => {
+                        if desktop.needs_full_redraw {
+                            render_full_with_cursor(desktop, mouse);
+                        }
+                    }
+                }
+            } else if mouse.moved(old_x, old_y) {
+                // Just redraw cursor at new position
+                mouse.erase_cursor(&mut desktop.fb);
+                // Check if hover state changed on interactive elements
+                let need_redraw = desktop.start_menu_open
+                    || desktop.close_button_rect.contains(mouse.x, mouse.y)
+                    || desktop.close_button_rect.contains(old_x, old_y)
+                    || desktop.start_button_rect.contains(mouse.x, mouse.y)
+                    || desktop.start_button_rect.contains(old_x, old_y);
+
+                if need_redraw {
+                    desktop.needs_full_redraw = true;
+                    desktop.render();
+                }
+                mouse.draw_cursor(&mut desktop.fb);
+                desktop.fb.flush();
+            }
+        }
+    }
+    None
+}
+
+fn read_line_gui(
+    shell: &mut Shell,
+    desktop: &mut Desktop,
+    mouse: &mut MouseState,
+    timer: &Option<Event>,
+    pointer: &mut Option<boot::ScopedProtocol<Pointer>>,
+) -> String {
+    let mut editor = LineEditor::new();
+    editor.begin(&desktop.terminal);
+    shell.history.reset_nav();
+
+    loop {
+        // Keys typed while the last command ran come first
+        if has_type_ahead() {
+            if let Some(result) = handle_key_input(shell, &mut editor, desktop, mouse) {
+                return result;
+            }
+            continue;
+        }
+
+        // Get keyboard wait event
+        let kb_event = system::with_stdin(|stdin| stdin.wait_for_key_event());
+
+        // Build event array
+        match (&kb_event, timer) {
+            (Some(kb), Some(tmr)) => {
+                let mut events = unsafe { [kb.unsafe_clone(), tmr.unsafe_clone()] };
+                match boot::wait_for_event(&mut events) {
+                    Ok(0) => {
+                        // Keyboard event
+                        if let Some(result) = handle_key_input(shell, &mut editor, desktop, mouse)
+                        {
+                            return result;
+                        }
+                    }
+                    Ok(1) => {
+                        // Timer event — poll mouse
+                        if let Some(command) = handle_mouse_poll(desktop, mouse, pointer) {
+                            // Show the picked command as the submitted line
+                            editor.set_line(&command);
+                            let mut console = GuiConsole { desktop, mouse };
+                            editor.render(&mut console);
+                            editor.finish(&mut console);
+                            return command;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            (Some(kb), None) => {
+                // No timer/mouse — keyboard only
+                let mut events = unsafe { [kb.unsafe_clone()] };
+                let _ = boot::wait_for_event(&mut events);
+                if let Some(result) = handle_key_input(shell, &mut editor, desktop, mouse) {
+                    return result;
+                }
+            }
+            _ => {
+                // No keyboard event available (shouldn't happen)
+                continue;
+            }
+        }
+    }
+}
+
+fn handle_key_input(
+    shell: &mut Shell,
+    editor: &mut LineEditor,
+    desktop: &mut Desktop,
+    mouse: &mut MouseState,
+) -> Option<String> {
+    let key = read_edit_key()?;
+    let mut console = GuiConsole { desktop, mouse };
+    let result = shell.edit(editor, key, &mut console);
+    console.flush();
+    result
+}
+
+pub fn run_gui_shell(screen: ScreenInfo) -> ! {
+    let mut desktop = Desktop::new(screen);
+    let mut mouse = MouseState::new(screen.width, screen.height);
+
+    // Find all SimplePointer handles and log them
+    if let Ok(handles) = boot::locate_handle_buffer(boot::SearchType::ByProtocol(
+        &Pointer::GUID,
+    )) {
+        log::info!("Found {} SimplePointer handles", handles.len());
+    }
+
+    // Open pointer protocol once and keep it open
+    let mut pointer = try_open_pointer();
+    if let Some(ref ptr) = pointer {
+        let mode = ptr.mode();
+        let res_x = mode.resolution[0];
+        mouse.set_sensitivity(1);
+        log::info!("Mouse detected (resolution: {}x{}, using raw values)", res_x, mode.resolution[1]);
+    } else {
+        log::info!("No mouse detected, keyboard only");
+    }
+
+    // Create timer for mouse polling
+    let timer = create_timer_event();
+
+    let mut shell = Shell::new();
+
+    // Banner
+    print_banner(&mut desktop.terminal, "VOS v0.1.0 - UEFI GUI Shell");
+
+    // Initial full render with cursor
+    render_full_with_cursor(&mut desktop, &mut mouse);
+
+    shell.run_startup(&mut GuiConsole {
+        desktop: &mut desktop,
+        mouse: &mut mouse,
+    });
+
+    loop {
+        print_prompt(&shell, &mut desktop.terminal);
+        render_with_cursor(&mut desktop, &mut mouse);
+
+        let line = read_line_gui(
+            &mut shell,
+            &mut desktop,
+            &mut mouse,
+            &timer,
+            &mut pointer,
+        );
+        let Some(text) = shell.accept_line(&line) else {
+            continue;
+        };
+
+        shell.run_line(
+            &text,
+            &mut GuiConsole {
+                desktop: &mut desktop,
+                mouse: &mut mouse,
+            },
+        );
+
+        render_with_cursor(&mut desktop, &mut mouse);
+    }
+}
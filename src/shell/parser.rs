@@ -0,0 +1,394 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::lexer::{Token, Word};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedirectKind {
+    /// `< file`: read standard input from a file.
+    Input,
+    /// `> file`: replace a file with the output.
+    Output,
+    /// `>> file`: append the output to a file.
+    Append,
+    /// `<<DELIM`: read standard input from the here-document in `target`.
+    HereDoc,
+}
+
+#[derive(Debug, Clone)]
+pub struct Redirect {
+    pub kind: RedirectKind,
+    pub target: Word,
+}
+
+/// One command of a pipeline: its words and redirections.
+#[derive(Debug, Clone, Default)]
+pub struct SimpleCommand {
+    pub argv: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+}
+
+/// Commands joined by `|`; each one's output is the next one's input.
+#[derive(Debug, Clone, Default)]
+pub struct Pipeline {
+    pub commands: Vec<SimpleCommand>,
+}
+
+/// One entry of a command list.
+#[derive(Debug, Clone)]
+pub struct Statement {
+    /// Line of the parsed input the statement starts on, from 1.
+    pub line: usize,
+    pub kind: StatementKind,
+}
+
+#[derive(Debug, Clone)]
+pub enum StatementKind {
+    Pipeline(Pipeline),
+    /// `if c; then ...; elif c; then ...; else ...; fi`
+    If {
+        branches: Vec<(Vec<Statement>, Vec<Statement>)>,
+        otherwise: Vec<Statement>,
+    },
+    /// `while c; do ...; done`
+    While {
+        condition: Vec<Statement>,
+        body: Vec<Statement>,
+    },
+    /// `for name in words...; do ...; done`
+    For {
+        var: String,
+        words: Vec<Word>,
+        body: Vec<Statement>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The input ended inside a construct or quote; more lines may complete it.
+    Incomplete(String),
+    Syntax(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Incomplete(msg) | ParseError::Syntax(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// Words that start or end a compound command when in command position.
+const KEYWORDS: &[&str] = &[
+    "if", "then", "elif", "else", "fi", "while", "do", "done", "for",
+];
+
+fn operator(token: &Token) -> &str {
+    match token {
+        Token::Word(word) => word.literal().unwrap_or("word"),
+        Token::Pipe => "|",
+        Token::RedirectIn => "<",
+        Token::RedirectOut => ">",
+        Token::RedirectAppend => ">>",
+        Token::HereDoc(_) => "<<",
+        Token::Separator => ";",
+    }
+}
+
+fn unexpected(token: &Token) -> ParseError {
+    ParseError::Syntax(format!("syntax error near '{}'", operator(token)))
+}
+
+fn end_of_input(expected: &str) -> ParseError {
+    ParseError::Incomplete(format!(
+        "syntax error: unexpected end of input (expected '{}')",
+        expected
+    ))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    /// Line of each token.
+    lines: Vec<usize>,
+    pos: usize,
+}
+
+impl Parser {
+    /// Line of the token at the current position.
+    fn line(&self) -> usize {
+        self.lines
+            .get(self.pos)
+            .or(self.lines.last())
+            .copied()
+            .unwrap_or(1)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// The keyword at the current position, if any.
+    fn keyword(&self) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Word(word)) => {
+                let text = word.literal()?;
+                KEYWORDS.iter().copied().find(|k| *k == text)
+            }
+            _ => None,
+        }
+    }
+
+    fn skip_separators(&mut self) {
+        while self.peek() == Some(&Token::Separator) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, keyword: &'static str) -> Result<(), ParseError> {
+        match self.keyword() {
+            Some(k) if k == keyword => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => match self.peek() {
+                Some(token) => Err(unexpected(token)),
+                None => Err(end_of_input(keyword)),
+            },
+        }
+    }
+
+    /// Parse statements until end of input or one of the `end` keywords,
+    /// which is left for the caller.
+    fn list(&mut self, end: &[&str]) -> Result<Vec<Statement>, ParseError> {
+        let mut list = Vec::new();
+        loop {
+            self.skip_separators();
+            match self.keyword() {
+                Some(k) if end.contains(&k) => return Ok(list),
+                _ => {}
+            }
+            if self.peek().is_none() {
+                return Ok(list);
+            }
+            list.push(self.statement()?);
+            match self.peek() {
+                None | Some(Token::Separator) => {}
+                Some(token) => return Err(unexpected(token)),
+            }
+        }
+    }
+
+    /// A non-empty list that must be followed by one of `end`.
+    fn body(&mut self, end: &[&str]) -> Result<Vec<Statement>, ParseError> {
+        let list = self.list(end)?;
+        match self.peek() {
+            None => Err(end_of_input(end[end.len() - 1])),
+            Some(token) if list.is_empty() => Err(unexpected(token)),
+            Some(_) => Ok(list),
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        let line = self.line();
+        let kind = match self.keyword() {
+            Some("if") => self.if_statement()?,
+            Some("while") => {
+                self.pos += 1;
+                let condition = self.body(&["do"])?;
+                self.expect("do")?;
+                let body = self.body(&["done"])?;
+                self.expect("done")?;
+                StatementKind::While { condition, body }
+            }
+            Some("for") => self.for_statement()?,
+            Some(_) => return Err(unexpected(self.peek().unwrap_or(&Token::Separator))),
+            None => StatementKind::Pipeline(self.pipeline()?),
+        };
+        Ok(Statement { line, kind })
+    }
+
+    fn if_statement(&mut self) -> Result<StatementKind, ParseError> {
+        let mut branches = Vec::new();
+        let mut otherwise = Vec::new();
+        self.pos += 1;
+        loop {
+            let condition = self.body(&["then"])?;
+            self.expect("then")?;
+            let body = self.body(&["elif", "else", "fi"])?;
+            branches.push((condition, body));
+            match self.keyword() {
+                Some("elif") => self.pos += 1,
+                Some("else") => {
+                    self.pos += 1;
+                    otherwise = self.body(&["fi"])?;
+                    self.expect("fi")?;
+                    break;
+                }
+                _ => {
+                    self.expect("fi")?;
+                    break;
+                }
+            }
+        }
+        Ok(StatementKind::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    fn for_statement(&mut self) -> Result<StatementKind, ParseError> {
+        self.pos += 1;
+        let var = match self.next() {
+            Some(Token::Word(word)) => match word.literal() {
+                Some(name) if super::vars::is_valid_name(name) => String::from(name),
+                _ => {
+                    return Err(ParseError::Syntax(String::from(
+                        "for: invalid variable name",
+                    )))
+                }
+            },
+            Some(token) => return Err(unexpected(&token)),
+            None => return Err(end_of_input("do")),
+        };
+        let mut words = Vec::new();
+        if matches!(self.peek(), Some(Token::Word(w)) if w.literal() == Some("in")) {
+            self.pos += 1;
+            while let Some(Token::Word(word)) = self.peek() {
+                words.push(word.clone());
+                self.pos += 1;
+            }
+        }
+        self.skip_separators();
+        self.expect("do")?;
+        let body = self.body(&["done"])?;
+        self.expect("done")?;
+        Ok(StatementKind::For { var, words, body })
+    }
+
+    fn pipeline(&mut self) -> Result<Pipeline, ParseError> {
+        let mut pipeline = Pipeline::default();
+        let mut current = SimpleCommand::default();
+
+        while let Some(token) = self.peek() {
+            let kind = match token {
+                Token::Separator => break,
+                Token::Word(word) => {
+                    current.argv.push(word.clone());
+                    self.pos += 1;
+                    continue;
+                }
+                Token::Pipe => {
+                    if current.argv.is_empty() {
+                        return Err(ParseError::Syntax(String::from("syntax error near '|'")));
+                    }
+                    pipeline.commands.push(core::mem::take(&mut current));
+                    self.pos += 1;
+                    continue;
+                }
+                Token::HereDoc(body) => {
+                    current.redirects.push(Redirect {
+                        kind: RedirectKind::HereDoc,
+                        target: body.clone(),
+                    });
+                    self.pos += 1;
+                    continue;
+                }
+                Token::RedirectIn => RedirectKind::Input,
+                Token::RedirectOut => RedirectKind::Output,
+                Token::RedirectAppend => RedirectKind::Append,
+            };
+            self.pos += 1;
+            match self.next() {
+                Some(Token::Word(target)) => current.redirects.push(Redirect { kind, target }),
+                Some(other) => return Err(unexpected(&other)),
+                None => {
+                    return Err(ParseError::Syntax(String::from(
+                        "syntax error: missing redirection target",
+                    )))
+                }
+            }
+        }
+
+        if current.argv.is_empty() {
+            return Err(ParseError::Syntax(String::from(
+                "syntax error: missing command",
+            )));
+        }
+        pipeline.commands.push(current);
+        Ok(pipeline)
+    }
+}
+
+/// Build a command list from tokens and their lines. An empty token list
+/// gives an empty list.
+pub fn parse(tokens: Vec<(Token, usize)>) -> Result<Vec<Statement>, ParseError> {
+    let (tokens, lines) = tokens.into_iter().unzip();
+    let mut parser = Parser {
+        tokens,
+        lines,
+        pos: 0,
+    };
+    let list = parser.list(&[])?;
+    match parser.peek() {
+        Some(token) => Err(unexpected(token)),
+        None => Ok(list),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shell::lexer::tokenize;
+
+    fn lines(list: &[Statement]) -> Vec<usize> {
+        list.iter().map(|s| s.line).collect()
+    }
+
+    #[test]
+    fn statements_carry_their_line() {
+        let text = "echo a; echo b\n\nif test 1\nthen\n  echo c\n  echo d\nelse echo e\nfi\n\
+                    while false; do\n  echo f\ndone\nfor x in 1 2\ndo\n  echo $x\ndone";
+        let list = parse(tokenize(text).unwrap()).unwrap();
+        assert_eq!(lines(&list), vec![1, 1, 3, 9, 12]);
+        let StatementKind::If {
+            branches,
+            otherwise,
+        } = &list[2].kind
+        else {
+            panic!("expected if");
+        };
+        assert_eq!(lines(&branches[0].0), vec![3]);
+        assert_eq!(lines(&branches[0].1), vec![5, 6]);
+        assert_eq!(lines(otherwise), vec![7]);
+        let StatementKind::While { body, .. } = &list[3].kind else {
+            panic!("expected while");
+        };
+        assert_eq!(lines(body), vec![10]);
+        let StatementKind::For { body, .. } = &list[4].kind else {
+            panic!("expected for");
+        };
+        assert_eq!(lines(body), vec![14]);
+    }
+
+    #[test]
+    fn incomplete_and_syntax_errors() {
+        let parse_text = |text: &str| parse(tokenize(text).unwrap());
+        assert!(matches!(
+            parse_text("if true; then echo"),
+            Err(ParseError::Incomplete(_))
+        ));
+        assert!(matches!(parse_text("ls |"), Err(ParseError::Syntax(_))));
+        assert!(matches!(parse_text("fi"), Err(ParseError::Syntax(_))));
+        assert!(parse_text("").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+extern crate alloc;
+
+use alloc::string::{String, ToString};
+use core::fmt;
+
+use super::parser::ParseError;
+use super::Shell;
+use crate::console::Console;
+
+/// Script run automatically before the first prompt, if present.
+pub const STARTUP_SCRIPT: &str = "\\vos\\startup.vsh";
+
+/// The script statement being run, shown as `file:line` before errors.
+#[derive(Debug, Clone)]
+pub struct Position {
+    pub file: String,
+    /// Script line the text being run starts on; statement lines count
+    /// from it.
+    pub base: usize,
+    pub line: usize,
+}
+
+impl Position {
+    /// Point at the statement on `line` of the text being run.
+    pub fn enter(&mut self, line: usize) {
+        self.line = self.base + line.saturating_sub(1);
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+impl Shell {
+    /// Run a script file in this shell.
+    ///
+    /// Lines are collected until they form complete statements, so `if`,
+    /// `while` and `for` may span several lines. Errors are reported as
+    /// `file:line: message` and execution continues, unless `set -e` is
+    /// active, in which case the first error stops the script and is
+    /// returned instead.
+    pub fn run_script(&mut self, path: &str, out: &mut dyn Console) -> Result<(), String> {
+        let data = crate::fs::read_file(path)?;
+        self.run_script_data(path, &data, out)
+    }
+
+    fn run_script_data(
+        &mut self,
+        path: &str,
+        data: &[u8],
+        out: &mut dyn Console,
+    ) -> Result<(), String> {
+        let text = String::from_utf8_lossy(data);
+        let name = crate::path::file_name(path);
+        let outer = self.position.take();
+        let result = self.run_script_text(name, &text, out);
+        self.position = outer;
+        result
+    }
+
+    fn run_script_text(
+        &mut self,
+        name: &str,
+        text: &str,
+        out: &mut dyn Console,
+    ) -> Result<(), String> {
+        let mut pending = String::new();
+        let mut start = 0;
+        for (index, line) in text.lines().enumerate() {
+            if pending.is_empty() {
+                start = index + 1;
+            }
+            pending.push_str(line);
+            pending.push('\n');
+            self.position = Some(Position {
+                file: String::from(name),
+                base: start,
+                line: start,
+            });
+
+            let result = match self.parse(&pending) {
+                Err(ParseError::Incomplete(_)) => continue,
+                Err(e) => {
+                    self.status = 1;
+                    Err(e.to_string())
+                }
+                Ok(list) => self.run_list(&list, out),
+            };
+            pending.clear();
+            if let Err(e) = result {
+                let msg = self.locate(e);
+                if self.errexit {
+                    return Err(msg);
+                }
+                super::report_error(out, &msg);
+            }
+        }
+
+        // Input ended inside an `if`, loop or quote
+        if let Err(e) = self.parse(&pending) {
+            self.status = 1;
+            let msg = self.locate(e.to_string());
+            if self.errexit {
+                return Err(msg);
+            }
+            super::report_error(out, &msg);
+        }
+        Ok(())
+    }
+
+    /// Run the startup script if the boot volume has one.
+    pub fn run_startup(&mut self, out: &mut dyn Console) {
+        let Ok(data) = crate::fs::read_file(STARTUP_SCRIPT) else {
+            return;
+        };
+        if let Err(e) = self.run_script_data(STARTUP_SCRIPT, &data, out) {
+            super::report_error(out, &e);
+        }
+        self.errexit = false;
+    }
+}
+
+/// `source <file>`: run a script in the current shell; options and variables
+/// it sets stay set.
+pub fn cmd_source(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let [path] = args else {
+        return Err(String::from("Usage: source <file>"));
+    };
+    sh.run_script(&sh.resolve_path(path), out)
+}
+
+/// `run <file>`: run a script that sees only exported variables, restoring
+/// shell options, variables and the current directory afterwards.
+pub fn cmd_run(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let [path] = args else {
+        return Err(String::from("Usage: run <file>"));
+    };
+    let errexit = sh.errexit;
+    let cwd = sh.cwd.clone();
+    let exported = sh.vars.exported();
+    let vars = core::mem::replace(&mut sh.vars, exported);
+    let result = sh.run_script(&sh.resolve_path(path), out);
+    sh.errexit = errexit;
+    sh.vars = vars;
+    sh.cwd = cwd;
+    result
+}
@@ -0,0 +1,245 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use super::view::Source;
+use super::Shell;
+use crate::console::Console;
+use crate::vfs::DirEntry;
+use crate::path;
+use crate::pattern::Regex;
+
+/// Visit every entry below `dir` depth-first with its full path. Ctrl+C
+/// interrupts.
+fn walk(
+    dir: &str,
+    visit: &mut dyn FnMut(&str, &DirEntry) -> Result<(), String>,
+) -> Result<(), String> {
+    if super::exec::interrupted() {
+        return Err(String::from("Interrupted"));
+    }
+    for entry in crate::vfs::read_dir(dir)? {
+        if entry.is_dot() {
+            continue;
+        }
+        let full = path::join(dir, &entry.name);
+        visit(&full, &entry)?;
+        if entry.is_dir {
+            walk(&full, visit)?;
+        }
+    }
+    Ok(())
+}
+
+fn is_dir(path: &str) -> bool {
+    path::is_root(path) || crate::vfs::stat(path).is_ok_and(|meta| meta.is_dir)
+}
+
+const FIND_USAGE: &str = "Usage: find [dir] [-name glob | -iname glob] [-type f|d]";
+
+/// `find [dir] [-name glob] [-type f|d]`: list matching paths below `dir`.
+pub fn cmd_find(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let mut dir = None;
+    let mut name = None;
+    let mut want_dir = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-name" | "-iname" => {
+                let glob = args.next().ok_or_else(|| String::from(FIND_USAGE))?;
+                let regex =
+                    Regex::glob(glob, arg == "-iname").map_err(|e| format!("find: {}", e))?;
+                name = Some(regex);
+            }
+            "-type" => {
+                want_dir = match args.next().map(String::as_str) {
+                    Some("f") => Some(false),
+                    Some("d") => Some(true),
+                    _ => return Err(String::from(FIND_USAGE)),
+                }
+            }
+            _ if arg.len() > 1 && arg.starts_with('-') => {
+                return Err(format!("find: unknown option '{}'", arg))
+            }
+            _ if dir.is_none() => dir = Some(arg.as_str()),
+            _ => return Err(String::from(FIND_USAGE)),
+        }
+    }
+
+    let root = sh.resolve_path(dir.unwrap_or("."));
+    if !is_dir(&root) {
+        return Err(format!("find: '{}' is not a directory", dir.unwrap_or(".")));
+    }
+    walk(&root, &mut |full, entry| {
+        let matches = name.as_ref().is_none_or(|name| name.is_match(&entry.name))
+            && want_dir.is_none_or(|want_dir| want_dir == entry.is_dir);
+        if matches {
+            let _ = writeln!(out, "{}", full);
+        }
+        Ok(())
+    })
+}
+
+/// Longest line `grep` matches in one piece. Longer lines, such as those of
+/// binary files with few newlines, are split, so a match across a split is
+/// missed.
+const MAX_LINE: usize = 8 * 1024;
+
+/// Prints the matching lines of one input for `grep`.
+struct LineMatcher<'a> {
+    regex: &'a Regex,
+    /// Prefix for each match when searching several files.
+    name: Option<&'a str>,
+    numbers: bool,
+    /// Number of the line being read.
+    number: usize,
+    /// The input has a NUL in its first chunk; a match is only reported.
+    binary: bool,
+    found: bool,
+}
+
+impl LineMatcher<'_> {
+    /// Check one line, or a piece of a long one; returns false to stop
+    /// reading (a binary file matched).
+    fn line(&mut self, bytes: &[u8], out: &mut dyn Console) -> bool {
+        let text = String::from_utf8_lossy(bytes);
+        let text = text.trim_end_matches('\r');
+        if !self.regex.is_match(text) {
+            return true;
+        }
+        self.found = true;
+        if self.binary {
+            let _ = writeln!(
+                out,
+                "Binary file {} matches",
+                self.name.unwrap_or("(input)")
+            );
+            return false;
+        }
+        if let Some(name) = self.name {
+            let _ = write!(out, "{}:", name);
+        }
+        if self.numbers {
+            let _ = write!(out, "{}:", self.number);
+        }
+        let _ = writeln!(out, "{}", text);
+        true
+    }
+}
+
+/// Print the lines of `source` matching `regex`; returns whether any did.
+fn grep_source(
+    source: &mut Source,
+    regex: &Regex,
+    name: Option<&str>,
+    numbers: bool,
+    out: &mut dyn Console,
+) -> Result<bool, String> {
+    let mut matcher = LineMatcher {
+        regex,
+        name,
+        numbers,
+        number: 1,
+        binary: false,
+        found: false,
+    };
+    let mut line = Vec::new();
+    let mut reading = true;
+    let mut first = true;
+    source.for_each_chunk(0, |chunk| {
+        if core::mem::take(&mut first) {
+            matcher.binary = chunk.contains(&0);
+        }
+        for &b in chunk {
+            if b != b'\n' && line.len() < MAX_LINE {
+                line.push(b);
+                continue;
+            }
+            reading = matcher.line(&line, out);
+            line.clear();
+            if b == b'\n' {
+                matcher.number += 1;
+            } else {
+                line.push(b);
+            }
+            if !reading {
+                break;
+            }
+        }
+        Ok(reading)
+    })?;
+    if reading && !line.is_empty() {
+        matcher.line(&line, out);
+    }
+    Ok(matcher.found)
+}
+
+const GREP_USAGE: &str = "grep [-inr] <pattern> [path...]";
+
+/// `grep [-inr] <pattern> [path...]`: print lines matching a regular
+/// expression in files, directory trees (`-r`) or piped input. Fails
+/// silently when nothing matches, so it can be used as a condition.
+pub fn cmd_grep(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let (mut ignore_case, mut numbers, mut recursive) = (false, false, false);
+    let mut operands = Vec::new();
+    for arg in args {
+        match arg
+            .strip_prefix('-')
+            .filter(|f| !f.is_empty() && operands.is_empty())
+        {
+            Some(flags) => {
+                for flag in flags.chars() {
+                    match flag {
+                        'i' => ignore_case = true,
+                        'n' => numbers = true,
+                        'r' => recursive = true,
+                        _ => return Err(format!("grep: unknown option '-{}'", flag)),
+                    }
+                }
+            }
+            None => operands.push(arg.as_str()),
+        }
+    }
+    let (pattern, paths) = operands
+        .split_first()
+        .ok_or_else(|| format!("Usage: {}", GREP_USAGE))?;
+    let regex = Regex::new(pattern, ignore_case).map_err(|e| format!("grep: {}", e))?;
+
+    if paths.is_empty() && !recursive {
+        let mut source = Source::open(sh, None, GREP_USAGE)?;
+        let found = grep_source(&mut source, &regex, None, numbers, out)?;
+        return if found { Ok(()) } else { Err(String::new()) };
+    }
+
+    // Files to search, as (shown name, path)
+    let mut files = Vec::new();
+    let paths = if paths.is_empty() { &["."][..] } else { paths };
+    for &name in paths {
+        let full = sh.resolve_path(name);
+        if recursive && is_dir(&full) {
+            walk(&full, &mut |file, entry| {
+                if !entry.is_dir {
+                    files.push((String::from(file), String::from(file)));
+                }
+                Ok(())
+            })?;
+        } else {
+            files.push((String::from(name), full));
+        }
+    }
+
+    let show_names = recursive || files.len() > 1;
+    let mut found = false;
+    for (name, full) in &files {
+        let mut source = Source::open(sh, Some(full), GREP_USAGE)?;
+        let name = show_names.then_some(name.as_str());
+        found |= grep_source(&mut source, &regex, name, numbers, out)?;
+    }
+    if found {
+        Ok(())
+    } else {
+        Err(String::new())
+    }
+}
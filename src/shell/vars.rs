@@ -0,0 +1,201 @@
+extern crate alloc;
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+
+use super::Shell;
+use crate::console::Console;
+
+/// Read-only variables computed on every lookup.
+pub const BUILTIN_VARS: &[&str] = &[
+    "ARCH",
+    "FW_REVISION",
+    "FW_VENDOR",
+    "PWD",
+    "UEFI_VERSION",
+    "VOS_VERSION",
+];
+
+#[derive(Clone)]
+pub struct Var {
+    pub value: String,
+    /// Exported variables are passed on to scripts started with `run`.
+    pub exported: bool,
+}
+
+/// User-defined shell variables.
+#[derive(Clone, Default)]
+pub struct Vars {
+    map: BTreeMap<String, Var>,
+}
+
+impl Vars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.map.get(name).map(|v| v.value.as_str())
+    }
+
+    /// Set a variable, keeping its export flag if it already exists.
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self.map.get_mut(name) {
+            Some(var) => var.value = String::from(value),
+            None => {
+                self.map.insert(
+                    String::from(name),
+                    Var {
+                        value: String::from(value),
+                        exported: false,
+                    },
+                );
+            }
+        }
+    }
+
+    /// Mark a variable as exported, creating it empty if needed.
+    pub fn export(&mut self, name: &str) {
+        self.map
+            .entry(String::from(name))
+            .or_insert_with(|| Var {
+                value: String::new(),
+                exported: false,
+            })
+            .exported = true;
+    }
+
+    pub fn unset(&mut self, name: &str) {
+        self.map.remove(name);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &Var)> {
+        self.map.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Copy of the exported variables only.
+    pub fn exported(&self) -> Vars {
+        Vars {
+            map: self
+                .map
+                .iter()
+                .filter(|(_, v)| v.exported)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        }
+    }
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Split a `NAME=value` word.
+pub fn parse_assignment(word: &str) -> Option<(&str, &str)> {
+    let (name, value) = word.split_once('=')?;
+    is_valid_name(name).then_some((name, value))
+}
+
+impl Shell {
+    /// Value of `$name`: `?`, then user variables, then built-ins.
+    pub fn lookup(&self, name: &str) -> Option<String> {
+        if name == "?" {
+            return Some(format!("{}", self.status));
+        }
+        if let Some(value) = self.vars.get(name) {
+            return Some(String::from(value));
+        }
+        super::builtin_var(self, name)
+    }
+
+    /// Assign a variable, refusing built-in names.
+    pub fn set_var(&mut self, name: &str, value: &str) -> Result<(), String> {
+        if !is_valid_name(name) {
+            return Err(format!("'{}': not a valid variable name", name));
+        }
+        if BUILTIN_VARS.contains(&name) {
+            return Err(format!("{}: read-only variable", name));
+        }
+        self.vars.set(name, value);
+        Ok(())
+    }
+}
+
+fn print_var(out: &mut dyn Console, name: &str, value: &str) {
+    let _ = writeln!(out, "{}={}", name, value);
+}
+
+/// `set`: list variables, set `NAME=value` pairs and toggle `-e`.
+pub fn cmd_set(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    if args.is_empty() {
+        let _ = writeln!(out, "errexit  {}", if sh.errexit { "on" } else { "off" });
+        for name in BUILTIN_VARS {
+            print_var(out, name, &sh.lookup(name).unwrap_or_default());
+        }
+        for (name, var) in sh.vars.iter() {
+            print_var(out, name, &var.value);
+        }
+        return Ok(());
+    }
+    for arg in args {
+        match arg.as_str() {
+            "-e" => sh.errexit = true,
+            "+e" => sh.errexit = false,
+            _ => {
+                let (name, value) = arg
+                    .split_once('=')
+                    .ok_or_else(|| format!("set: unknown option '{}'", arg))?;
+                sh.set_var(name, value)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn cmd_unset(sh: &mut Shell, args: &[String], _out: &mut dyn Console) -> Result<(), String> {
+    if args.is_empty() {
+        return Err(String::from("Usage: unset <name...>"));
+    }
+    for name in args {
+        sh.vars.unset(name);
+    }
+    Ok(())
+}
+
+/// `export`: mark variables as exported, optionally assigning them.
+pub fn cmd_export(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    if args.is_empty() {
+        for (name, var) in sh.vars.iter().filter(|(_, v)| v.exported) {
+            print_var(out, name, &var.value);
+        }
+        return Ok(());
+    }
+    for arg in args {
+        let name = match arg.split_once('=') {
+            Some((name, value)) => {
+                sh.set_var(name, value)?;
+                name
+            }
+            None if is_valid_name(arg) => arg.as_str(),
+            None => return Err(format!("'{}': not a valid variable name", arg)),
+        };
+        sh.vars.export(name);
+    }
+    Ok(())
+}
+
+/// `env`: show the environment passed to scripts (built-ins and exported variables).
+pub fn cmd_env(sh: &mut Shell, _args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    for name in BUILTIN_VARS {
+        print_var(out, name, &sh.lookup(name).unwrap_or_default());
+    }
+    for (name, var) in sh.vars.iter().filter(|(_, v)| v.exported) {
+        print_var(out, name, &var.value);
+    }
+    Ok(())
+}
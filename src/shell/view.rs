@@ -0,0 +1,578 @@
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt::Write;
+
+use super::editor::EditKey;
+use super::Shell;
+use crate::console::{Console, Style};
+use crate::vfs::{self, OpenFile, OpenMode};
+
+const CHUNK_SIZE: usize = 4096;
+
+/// Bytes per `hexdump`/`xxd` row.
+pub(super) const ROW_SIZE: usize = 16;
+
+/// Lines shown by `head` and `tail` without `-n`.
+const DEFAULT_LINES: u64 = 10;
+
+// ── Input ──
+
+/// What a viewer reads: a file, read a chunk at a time,
+/// or the piped output of the previous command.
+pub(super) enum Source {
+    File(Box<dyn OpenFile>),
+    Piped(Vec<u8>),
+}
+
+impl Source {
+    /// Open `path`, or take piped input when no path is given.
+    pub(super) fn open(sh: &mut Shell, path: Option<&str>, usage: &str) -> Result<Self, String> {
+        match path {
+            Some(path) => vfs::open(&sh.resolve_path(path), OpenMode::Read).map(Source::File),
+            None => sh
+                .take_stdin()
+                .map(Source::Piped)
+                .ok_or_else(|| format!("Usage: {}", usage)),
+        }
+    }
+
+    fn len(&mut self) -> Result<u64, String> {
+        match self {
+            Source::File(file) => file.size(),
+            Source::Piped(data) => Ok(data.len() as u64),
+        }
+    }
+
+    /// Read up to `buf.len()` bytes starting at `offset`; 0 means the end.
+    fn read_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<usize, String> {
+        match self {
+            Source::File(file) => {
+                file.seek(offset)?;
+                file.read(buf)
+            }
+            Source::Piped(data) => {
+                let start = usize::try_from(offset).map_or(data.len(), |o| o.min(data.len()));
+                let n = buf.len().min(data.len() - start);
+                buf[..n].copy_from_slice(&data[start..start + n]);
+                Ok(n)
+            }
+        }
+    }
+
+    /// Pass the bytes from `start` to the end to `f` a chunk at a time,
+    /// stopping early when it returns false. Ctrl+C interrupts.
+    pub(super) fn for_each_chunk(
+        &mut self,
+        start: u64,
+        mut f: impl FnMut(&[u8]) -> Result<bool, String>,
+    ) -> Result<(), String> {
+        // Reading a file past its end is an error in UEFI
+        let len = self.len()?;
+        let mut buf = vec![0u8; CHUNK_SIZE];
+        let mut offset = start;
+        while offset < len {
+            let n = self.read_at(offset, &mut buf)?;
+            if n == 0 || !f(&buf[..n])? {
+                return Ok(());
+            }
+            offset += n as u64;
+            if super::exec::interrupted() {
+                return Err(String::from("Interrupted"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decodes UTF-8 that arrives in chunks: a character split between two
+/// chunks is kept whole and invalid bytes become U+FFFD.
+#[derive(Default)]
+struct Utf8Decoder {
+    partial: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    fn decode(&mut self, chunk: &[u8]) -> String {
+        let mut data = core::mem::take(&mut self.partial);
+        data.extend_from_slice(chunk);
+        let mut text = String::new();
+        let mut rest = data.as_slice();
+        loop {
+            match core::str::from_utf8(rest) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    break;
+                }
+                Err(e) => {
+                    let (valid, after) = rest.split_at(e.valid_up_to());
+                    text.push_str(core::str::from_utf8(valid).unwrap_or_default());
+                    match e.error_len() {
+                        Some(len) => {
+                            text.push(char::REPLACEMENT_CHARACTER);
+                            rest = &after[len..];
+                        }
+                        // Incomplete character at the end: wait for more
+                        None => {
+                            self.partial = after.to_vec();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        text
+    }
+
+    /// Flush a character left incomplete at the end of the input.
+    fn finish(&mut self) -> &'static str {
+        if core::mem::take(&mut self.partial).is_empty() {
+            ""
+        } else {
+            "\u{FFFD}"
+        }
+    }
+}
+
+/// Parse a count or offset in decimal or `0x` hex.
+pub(super) fn parse_number(text: Option<&String>, usage: &str) -> Result<u64, String> {
+    let text = text.ok_or_else(|| format!("Usage: {}", usage))?;
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("invalid number '{}'", text))
+}
+
+fn unknown_option(cmd: &str, arg: &str) -> Option<String> {
+    (arg.len() > 1 && arg.starts_with('-')).then(|| format!("{}: unknown option '{}'", cmd, arg))
+}
+
+// ── Pager ──
+
+const MORE_PROMPT: &str = "-- More -- (Space: page, Enter: line, q: quit)";
+
+/// Writes text a screen at a time, waiting for a key after each page.
+/// When disabled, or when output is captured, text passes straight through.
+struct Pager<'a> {
+    out: &'a mut dyn Console,
+    /// Lines per page while paging.
+    page: Option<usize>,
+    cols: usize,
+    col: usize,
+    lines: usize,
+}
+
+impl<'a> Pager<'a> {
+    fn new(out: &'a mut dyn Console, enabled: bool) -> Self {
+        let (cols, rows) = out.size();
+        let page = (enabled && out.is_terminal()).then(|| rows.saturating_sub(1).max(1));
+        Self {
+            out,
+            page,
+            cols: cols.max(1),
+            col: 0,
+            lines: 0,
+        }
+    }
+
+    /// Write `text`, pausing after each full page. Returns false once the
+    /// user has quit.
+    fn write(&mut self, text: &str) -> bool {
+        let Some(page) = self.page else {
+            let _ = self.out.write_str(text);
+            return true;
+        };
+        let mut start = 0;
+        for (i, c) in text.char_indices() {
+            // Long lines wrap, so they count once per screen row
+            self.col += 1;
+            if c == '\n' || self.col == self.cols {
+                self.col = 0;
+                self.lines += 1;
+            }
+            if self.lines >= page {
+                let end = i + c.len_utf8();
+                let _ = self.out.write_str(&text[start..end]);
+                start = end;
+                match self.more(page) {
+                    Some(lines) => self.lines = lines,
+                    None => return false,
+                }
+            }
+        }
+        let _ = self.out.write_str(&text[start..]);
+        true
+    }
+
+    /// Show the prompt until a key is pressed, then erase it. Returns the
+    /// number of lines of the next page already used, or `None` to quit.
+    fn more(&mut self, page: usize) -> Option<usize> {
+        let (col, row) = self.out.cursor();
+        self.out.write_styled(Style::Prompt, MORE_PROMPT);
+        self.out.flush();
+        let key = super::read_key();
+        self.out.set_cursor(col, row);
+        let _ = write!(self.out, "{:1$}", "", MORE_PROMPT.len());
+        self.out.set_cursor(col, row);
+        match key {
+            EditKey::Char('q' | 'Q') | EditKey::Ctrl('c') => None,
+            EditKey::Enter | EditKey::Down => Some(page - 1),
+            _ => Some(0),
+        }
+    }
+}
+
+// ── Commands ──
+
+const CAT_USAGE: &str = "cat [-p] [file...]";
+
+/// Write a source as text. Files with a NUL byte in their first chunk are
+/// refused as binary. Returns false if the user quit the pager.
+fn show_text(source: &mut Source, pager: &mut Pager, name: Option<&str>) -> Result<bool, String> {
+    let mut decoder = Utf8Decoder::default();
+    let mut first = true;
+    let mut open = true;
+    source.for_each_chunk(0, |chunk| {
+        if let Some(name) = name.filter(|_| first && chunk.contains(&0)) {
+            return Err(format!("'{}' is a binary file (use hexdump)", name));
+        }
+        first = false;
+        open = pager.write(&decoder.decode(chunk));
+        Ok(open)
+    })?;
+    Ok(open && pager.write(decoder.finish()))
+}
+
+/// `cat [-p] [file...]`: print files or piped input; `-p` pages the output.
+pub fn cmd_cat(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let mut paged = false;
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "-p" => paged = true,
+            _ => match unknown_option("cat", arg) {
+                Some(e) => return Err(e),
+                None => paths.push(arg.as_str()),
+            },
+        }
+    }
+
+    let mut pager = Pager::new(out, paged);
+    if paths.is_empty() {
+        let mut source = Source::open(sh, None, CAT_USAGE)?;
+        show_text(&mut source, &mut pager, None)?;
+        return Ok(());
+    }
+    for path in paths {
+        let mut source = Source::open(sh, Some(path), CAT_USAGE)?;
+        if !show_text(&mut source, &mut pager, Some(path))? {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Parse `[-n lines] [file]` for `head` and `tail`.
+fn line_args<'a>(cmd: &str, args: &'a [String]) -> Result<(u64, Option<&'a str>), String> {
+    let usage = format!("{} [-n lines] [file]", cmd);
+    let mut count = DEFAULT_LINES;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-n" => count = parse_number(args.next(), &usage)?,
+            _ => {
+                if let Some(e) = unknown_option(cmd, arg) {
+                    return Err(e);
+                }
+                if path.replace(arg.as_str()).is_some() {
+                    return Err(format!("Usage: {}", usage));
+                }
+            }
+        }
+    }
+    Ok((count, path))
+}
+
+/// `head [-n lines] [file]`: print the first lines of a file or piped input.
+pub fn cmd_head(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let (count, path) = line_args("head", args)?;
+    let mut source = Source::open(sh, path, "head [-n lines] [file]")?;
+    if count == 0 {
+        return Ok(());
+    }
+    let mut decoder = Utf8Decoder::default();
+    let mut remaining = count;
+    source.for_each_chunk(0, |chunk| {
+        let mut end = chunk.len();
+        for (i, _) in chunk.iter().enumerate().filter(|(_, &b)| b == b'\n') {
+            remaining -= 1;
+            if remaining == 0 {
+                end = i + 1;
+                break;
+            }
+        }
+        let _ = out.write_str(&decoder.decode(&chunk[..end]));
+        Ok(remaining > 0)
+    })?;
+    let _ = out.write_str(decoder.finish());
+    Ok(())
+}
+
+/// Offset where the last `count` lines of `source` begin, found by reading
+/// backwards from the end so large files are not read in full.
+fn tail_start(source: &mut Source, count: u64) -> Result<u64, String> {
+    let len = source.len()?;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut end = len;
+    let mut newlines = 0;
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK_SIZE as u64);
+        let n = source.read_at(start, &mut buf[..(end - start) as usize])?;
+        for i in (0..n).rev() {
+            let pos = start + i as u64;
+            // The newline ending the last line does not start another one
+            if buf[i] != b'\n' || pos + 1 == len {
+                continue;
+            }
+            newlines += 1;
+            if newlines == count {
+                return Ok(pos + 1);
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// `tail [-n lines] [file]`: print the last lines of a file or piped input.
+pub fn cmd_tail(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let (count, path) = line_args("tail", args)?;
+    let mut source = Source::open(sh, path, "tail [-n lines] [file]")?;
+    if count == 0 {
+        return Ok(());
+    }
+    let start = tail_start(&mut source, count)?;
+    let mut decoder = Utf8Decoder::default();
+    source.for_each_chunk(start, |chunk| {
+        let _ = out.write_str(&decoder.decode(chunk));
+        Ok(true)
+    })?;
+    let _ = out.write_str(decoder.finish());
+    Ok(())
+}
+
+#[derive(Default)]
+struct Counts {
+    lines: u64,
+    words: u64,
+    bytes: u64,
+}
+
+impl Counts {
+    fn count(source: &mut Source) -> Result<Self, String> {
+        let mut counts = Counts::default();
+        let mut in_word = false;
+        source.for_each_chunk(0, |chunk| {
+            for &b in chunk {
+                let blank = b.is_ascii_whitespace();
+                if !blank && !in_word {
+                    counts.words += 1;
+                }
+                in_word = !blank;
+                counts.lines += u64::from(b == b'\n');
+            }
+            counts.bytes += chunk.len() as u64;
+            Ok(true)
+        })?;
+        Ok(counts)
+    }
+
+    fn add(&mut self, other: &Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.bytes += other.bytes;
+    }
+}
+
+/// `wc [-lwc] [file...]`: count lines, words and bytes.
+pub fn cmd_wc(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    let (mut lines, mut words, mut bytes) = (false, false, false);
+    let mut paths = Vec::new();
+    for arg in args {
+        match arg.strip_prefix('-').filter(|f| !f.is_empty()) {
+            Some(flags) => {
+                for flag in flags.chars() {
+                    match flag {
+                        'l' => lines = true,
+                        'w' => words = true,
+                        'c' => bytes = true,
+                        _ => return Err(format!("wc: unknown option '-{}'", flag)),
+                    }
+                }
+            }
+            None => paths.push(arg.as_str()),
+        }
+    }
+    if !(lines || words || bytes) {
+        (lines, words, bytes) = (true, true, true);
+    }
+
+    let mut print = |counts: &Counts, name: Option<&str>| {
+        for (shown, value) in [
+            (lines, counts.lines),
+            (words, counts.words),
+            (bytes, counts.bytes),
+        ] {
+            if shown {
+                let _ = write!(out, "{:>8}", value);
+            }
+        }
+        match name {
+            Some(name) => {
+                let _ = writeln!(out, " {}", name);
+            }
+            None => {
+                let _ = writeln!(out);
+            }
+        }
+    };
+
+    if paths.is_empty() {
+        let mut source = Source::open(sh, None, "wc [-lwc] [file...]")?;
+        print(&Counts::count(&mut source)?, None);
+        return Ok(());
+    }
+    let mut total = Counts::default();
+    for path in &paths {
+        let mut source = Source::open(sh, Some(path), "wc [-lwc] [file...]")?;
+        let counts = Counts::count(&mut source)?;
+        print(&counts, Some(path));
+        total.add(&counts);
+    }
+    if paths.len() > 1 {
+        print(&total, Some("total"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(super) enum DumpStyle {
+    /// `hexdump -C` layout: spaced bytes and `|text|`.
+    Canonical,
+    /// `xxd` layout: bytes in pairs and bare text.
+    Xxd,
+}
+
+pub(super) fn format_row(style: DumpStyle, offset: u64, bytes: &[u8]) -> String {
+    let text: String = bytes
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    let mut hex = String::new();
+    match style {
+        DumpStyle::Canonical => {
+            for i in 0..ROW_SIZE {
+                if i % 8 == 0 {
+                    hex.push(' ');
+                }
+                match bytes.get(i) {
+                    Some(b) => {
+                        let _ = write!(hex, "{:02x} ", b);
+                    }
+                    None => hex.push_str("   "),
+                }
+            }
+            format!("{:08x} {} |{}|", offset, hex, text)
+        }
+        DumpStyle::Xxd => {
+            for (i, b) in bytes.iter().enumerate() {
+                let _ = write!(hex, "{:02x}", b);
+                if i % 2 == 1 {
+                    hex.push(' ');
+                }
+            }
+            format!("{:08x}: {:<40} {}", offset, hex, text)
+        }
+    }
+}
+
+/// Shared by `hexdump` and `xxd`: dump `-n` (or `-l`) bytes from offset
+/// `-s` of a file or piped input.
+fn dump(
+    sh: &mut Shell,
+    args: &[String],
+    out: &mut dyn Console,
+    cmd: &str,
+    style: DumpStyle,
+) -> Result<(), String> {
+    let usage = format!("{} [-s offset] [-n length] [file]", cmd);
+    let mut start = 0;
+    let mut length = None;
+    let mut path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-s" => start = parse_number(args.next(), &usage)?,
+            "-n" | "-l" => length = Some(parse_number(args.next(), &usage)?),
+            _ => {
+                if let Some(e) = unknown_option(cmd, arg) {
+                    return Err(e);
+                }
+                if path.replace(arg.as_str()).is_some() {
+                    return Err(format!("Usage: {}", usage));
+                }
+            }
+        }
+    }
+
+    let mut source = Source::open(sh, path, &usage)?;
+    let end = length.map(|n| start.saturating_add(n));
+    let mut row = Vec::with_capacity(ROW_SIZE);
+    let mut row_offset = start;
+    let mut pos = start;
+    source.for_each_chunk(start, |chunk| {
+        let take = match end {
+            Some(end) => (end - pos).min(chunk.len() as u64) as usize,
+            None => chunk.len(),
+        };
+        for &b in &chunk[..take] {
+            row.push(b);
+            if row.len() == ROW_SIZE {
+                let _ = writeln!(out, "{}", format_row(style, row_offset, &row));
+                row_offset += ROW_SIZE as u64;
+                row.clear();
+            }
+        }
+        pos += take as u64;
+        Ok(end.is_none_or(|end| pos < end))
+    })?;
+    if !row.is_empty() {
+        let _ = writeln!(out, "{}", format_row(style, row_offset, &row));
+    }
+    if style == DumpStyle::Canonical {
+        let _ = writeln!(out, "{:08x}", pos);
+    }
+    Ok(())
+}
+
+/// `hexdump [-s offset] [-n length] [file]`: canonical hex and text dump.
+pub fn cmd_hexdump(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    dump(sh, args, out, "hexdump", DumpStyle::Canonical)
+}
+
+/// `xxd [-s offset] [-l length] [file]`: hex dump in `xxd` layout.
+pub fn cmd_xxd(sh: &mut Shell, args: &[String], out: &mut dyn Console) -> Result<(), String> {
+    dump(sh, args, out, "xxd", DumpStyle::Xxd)
+}
@@ -0,0 +1,642 @@
+extern crate alloc;
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use uefi::runtime::{Daylight, Time, TimeParams};
+
+use super::volume::{DirLoc, Volume};
+
+pub const ATTR_READ_ONLY: u8 = 0x01;
+pub const ATTR_VOLUME_ID: u8 = 0x08;
+pub const ATTR_DIRECTORY: u8 = 0x10;
+pub const ATTR_ARCHIVE: u8 = 0x20;
+/// Read-only, hidden, system and volume ID together mark a long-name entry.
+const ATTR_LONG_NAME: u8 = 0x0F;
+
+const ENTRY_SIZE: usize = 32;
+const DELETED: u8 = 0xE5;
+/// UTF-16 units stored in one long-name entry.
+const LFN_CHARS: usize = 13;
+/// Offsets of those units within the entry.
+const LFN_OFFSETS: [usize; LFN_CHARS] = [1, 3, 5, 7, 9, 14, 16, 18, 20, 22, 24, 28, 30];
+/// NT reserved byte flags: the base name or extension is shown in lower case.
+const LOWER_BASE: u8 = 0x08;
+const LOWER_EXT: u8 = 0x10;
+
+/// Characters a long name may not contain.
+const INVALID_CHARS: &[char] = &['"', '*', '/', ':', '<', '>', '?', '\\', '|'];
+/// Characters a short name may contain besides letters and digits.
+const SHORT_NAME_CHARS: &[u8] = b"$%'-_@~`!(){}^#&";
+
+/// A file or directory found in a directory.
+#[derive(Clone)]
+pub struct Entry {
+    pub name: String,
+    /// The 32-byte short entry.
+    pub raw: [u8; ENTRY_SIZE],
+    /// Byte offsets of the long-name entries followed by the short entry.
+    pub slots: Vec<u64>,
+}
+
+impl Entry {
+    /// Byte offset of the short entry.
+    pub fn slot(&self) -> u64 {
+        self.slots[self.slots.len() - 1]
+    }
+
+    pub fn attr(&self) -> u8 {
+        self.raw[11]
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.attr() & ATTR_DIRECTORY != 0
+    }
+
+    pub fn cluster(&self) -> u32 {
+        cluster_of(&self.raw)
+    }
+
+    pub fn size(&self) -> u32 {
+        u32::from_le_bytes([self.raw[28], self.raw[29], self.raw[30], self.raw[31]])
+    }
+
+    pub fn created(&self) -> Time {
+        fat_time(le16(&self.raw, 16), le16(&self.raw, 14), self.raw[13])
+    }
+
+    pub fn modified(&self) -> Time {
+        fat_time(le16(&self.raw, 24), le16(&self.raw, 22), 0)
+    }
+
+    pub fn accessed(&self) -> Time {
+        fat_time(le16(&self.raw, 18), 0, 0)
+    }
+}
+
+fn le16(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+pub fn cluster_of(raw: &[u8]) -> u32 {
+    (le16(raw, 20) as u32) << 16 | le16(raw, 26) as u32
+}
+
+pub fn set_cluster(raw: &mut [u8], cluster: u32) {
+    raw[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+    raw[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+}
+
+pub fn set_size(raw: &mut [u8], size: u32) {
+    raw[28..32].copy_from_slice(&size.to_le_bytes());
+}
+
+// ── Timestamps ──
+
+fn fat_time(date: u16, time: u16, tenths: u8) -> Time {
+    if date == 0 {
+        return Time::invalid();
+    }
+    Time::new(TimeParams {
+        year: 1980 + (date >> 9),
+        month: ((date >> 5) & 0x0F) as u8,
+        day: (date & 0x1F) as u8,
+        hour: (time >> 11) as u8,
+        minute: ((time >> 5) & 0x3F) as u8,
+        second: ((time & 0x1F) * 2) as u8 + tenths / 100,
+        nanosecond: (tenths % 100) as u32 * 10_000_000,
+        time_zone: None,
+        daylight: Daylight::empty(),
+    })
+    .unwrap_or(Time::invalid())
+}
+
+/// `time` as a FAT date and time, or zeros if it is unset or before 1980.
+fn fat_date_time(time: &Time) -> (u16, u16) {
+    if time.year() < 1980 || time.year() > 2107 {
+        return (0, 0);
+    }
+    let date = (time.year() - 1980) << 9 | (time.month() as u16) << 5 | time.day() as u16;
+    let clock =
+        (time.hour() as u16) << 11 | (time.minute() as u16) << 5 | (time.second() as u16 / 2);
+    (date, clock)
+}
+
+/// Set the modification time, and the access date, of a short entry.
+pub fn set_modified(raw: &mut [u8], time: &Time) {
+    let (date, clock) = fat_date_time(time);
+    raw[22..24].copy_from_slice(&clock.to_le_bytes());
+    raw[24..26].copy_from_slice(&date.to_le_bytes());
+    raw[18..20].copy_from_slice(&date.to_le_bytes());
+}
+
+/// A new short entry with all three timestamps set to `now`.
+pub fn new_raw(attr: u8, cluster: u32, now: &Time) -> [u8; ENTRY_SIZE] {
+    let mut raw = [0u8; ENTRY_SIZE];
+    raw[11] = attr;
+    set_cluster(&mut raw, cluster);
+    let (date, clock) = fat_date_time(now);
+    raw[13] = (now.second() % 2) * 100 + (now.nanosecond() / 10_000_000) as u8;
+    raw[14..16].copy_from_slice(&clock.to_le_bytes());
+    raw[16..18].copy_from_slice(&date.to_le_bytes());
+    set_modified(&mut raw, now);
+    raw
+}
+
+/// The `.` and `..` entries that start a new directory at `cluster`
+/// inside `parent` (0 for the root).
+pub fn dot_entries(cluster: u32, parent: u32, now: &Time) -> [u8; 2 * ENTRY_SIZE] {
+    let mut raw = [0u8; 2 * ENTRY_SIZE];
+    for (slot, (name, target)) in raw
+        .chunks_exact_mut(ENTRY_SIZE)
+        .zip([(".", cluster), ("..", parent)])
+    {
+        slot.copy_from_slice(&new_raw(ATTR_DIRECTORY, target, now));
+        slot[..11].fill(b' ');
+        slot[..name.len()].copy_from_slice(name.as_bytes());
+    }
+    raw
+}
+
+// ── Names ──
+
+/// The display form of an 8.3 name, honouring the lower-case flags.
+fn short_display(raw: &[u8]) -> String {
+    let mut base = [0u8; 8];
+    base.copy_from_slice(&raw[..8]);
+    if base[0] == 0x05 {
+        base[0] = DELETED;
+    }
+    let case = raw[12];
+    let text = |bytes: &[u8], lower: bool| -> String {
+        let text: String = bytes
+            .iter()
+            .map(|&b| if lower { b.to_ascii_lowercase() } else { b } as char)
+            .collect();
+        String::from(text.trim_end_matches(' '))
+    };
+    let mut name = text(&base, case & LOWER_BASE != 0);
+    let ext = text(&raw[8..11], case & LOWER_EXT != 0);
+    if !ext.is_empty() {
+        name.push('.');
+        name.push_str(&ext);
+    }
+    name
+}
+
+/// Checksum of a short name, stored in each of its long-name entries.
+fn checksum(short: &[u8]) -> u8 {
+    short[..11]
+        .iter()
+        .fold(0u8, |sum, &b| sum.rotate_right(1).wrapping_add(b))
+}
+
+fn is_short_char(b: u8) -> bool {
+    b.is_ascii_uppercase() || b.is_ascii_digit() || SHORT_NAME_CHARS.contains(&b)
+}
+
+/// The 11-byte short name and case flags for `name` if it fits 8.3
+/// as-is, so that no long-name entries are needed.
+fn exact_short_name(name: &str) -> Option<([u8; 11], u8)> {
+    let (base, ext) = match name.rfind('.') {
+        Some(i) => (&name[..i], &name[i + 1..]),
+        None => (name, ""),
+    };
+    if base.is_empty() || base.len() > 8 || ext.len() > 3 || name.ends_with('.') {
+        return None;
+    }
+    let mut short = [b' '; 11];
+    let mut case = 0;
+    for (part, range, flag) in [(base, 0..8, LOWER_BASE), (ext, 8..11, LOWER_EXT)] {
+        let has_lower = part.bytes().any(|b| b.is_ascii_lowercase());
+        let has_upper = part.bytes().any(|b| b.is_ascii_uppercase());
+        if has_lower && has_upper {
+            return None;
+        }
+        if has_lower {
+            case |= flag;
+        }
+        for (i, b) in part.bytes().enumerate() {
+            let b = b.to_ascii_uppercase();
+            if !is_short_char(b) {
+                return None;
+            }
+            short[range.start + i] = b;
+        }
+    }
+    Some((short, case))
+}
+
+/// Make a short name for a long one: `LONGNA~1.TXT`, numbered past the
+/// names in `taken`.
+fn generate_short_name(name: &str, taken: &[[u8; 11]]) -> Result<[u8; 11], String> {
+    let clean = |text: &str, max: usize| -> Vec<u8> {
+        text.chars()
+            .filter(|&c| c != ' ' && c != '.')
+            .map(|c| {
+                let b = if c.is_ascii() {
+                    c.to_ascii_uppercase() as u8
+                } else {
+                    b'_'
+                };
+                if is_short_char(b) {
+                    b
+                } else {
+                    b'_'
+                }
+            })
+            .take(max)
+            .collect()
+    };
+    let trimmed = name.trim_start_matches('.');
+    let (base, ext) = match trimmed.rfind('.') {
+        Some(i) => (clean(&trimmed[..i], 8), clean(&trimmed[i + 1..], 3)),
+        None => (clean(trimmed, 8), Vec::new()),
+    };
+    let base = if base.is_empty() { vec![b'_'] } else { base };
+
+    for n in 1..1_000_000u32 {
+        let tail = format!("~{}", n);
+        let keep = base.len().min(8 - tail.len());
+        let mut short = [b' '; 11];
+        short[..keep].copy_from_slice(&base[..keep]);
+        short[keep..keep + tail.len()].copy_from_slice(tail.as_bytes());
+        short[8..8 + ext.len()].copy_from_slice(&ext);
+        if !taken.contains(&short) {
+            return Ok(short);
+        }
+    }
+    Err(format!("Cannot make a short name for '{}'", name))
+}
+
+/// Check a name for a new entry.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let invalid = name.is_empty()
+        || name == "."
+        || name == ".."
+        || name.ends_with(['.', ' '])
+        || name.encode_utf16().count() > 255
+        || name
+            .chars()
+            .any(|c| (c as u32) < 0x20 || INVALID_CHARS.contains(&c));
+    if invalid {
+        return Err(format!("Invalid FAT file name '{}'", name));
+    }
+    Ok(())
+}
+
+// ── Directory access ──
+
+/// Call `f` with the byte offset and contents of every 32-byte slot of a
+/// directory, until it returns false.
+fn for_each_slot(
+    vol: &mut Volume,
+    dir: DirLoc,
+    mut f: impl FnMut(u64, &[u8]) -> bool,
+) -> Result<(), String> {
+    for (start, len) in vol.dir_areas(dir)? {
+        let mut data = vec![0u8; len as usize];
+        vol.read_at(start, &mut data)?;
+        for (i, slot) in data.chunks_exact(ENTRY_SIZE).enumerate() {
+            if !f(start + (i * ENTRY_SIZE) as u64, slot) {
+                return Ok(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Every entry of a directory, including `.` and `..`. Long names are
+/// used when their checksum matches the short entry they precede.
+pub fn list(vol: &mut Volume, dir: DirLoc) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    let mut units: Vec<u16> = Vec::new();
+    let mut lfn_slots = Vec::new();
+    // Expected next ordinal and checksum of the long name being read
+    let mut expect: Option<(u8, u8)> = None;
+    for_each_slot(vol, dir, |offset, slot| {
+        match slot[0] {
+            0 => return false,
+            DELETED => {
+                expect = None;
+                return true;
+            }
+            _ => {}
+        }
+        let attr = slot[11];
+        if attr & 0x3F == ATTR_LONG_NAME {
+            let ord = slot[0] & 0x1F;
+            if slot[0] & 0x40 != 0 {
+                units = vec![0xFFFF; ord as usize * LFN_CHARS];
+                lfn_slots.clear();
+                expect = Some((ord, slot[13]));
+            }
+            match expect {
+                Some((want, sum)) if want == ord && ord > 0 && sum == slot[13] => {
+                    let at = (ord as usize - 1) * LFN_CHARS;
+                    for (i, &o) in LFN_OFFSETS.iter().enumerate() {
+                        units[at + i] = le16(slot, o);
+                    }
+                    lfn_slots.push(offset);
+                    expect = Some((ord - 1, sum));
+                }
+                _ => expect = None,
+            }
+            return true;
+        }
+        if attr & ATTR_VOLUME_ID != 0 {
+            expect = None;
+            return true;
+        }
+
+        let mut raw = [0u8; ENTRY_SIZE];
+        raw.copy_from_slice(slot);
+        let mut slots = Vec::new();
+        let name = match expect.take() {
+            Some((0, sum)) if sum == checksum(&raw) => {
+                slots.append(&mut lfn_slots);
+                let end = units.iter().position(|&u| u == 0).unwrap_or(units.len());
+                char::decode_utf16(units[..end].iter().copied())
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+            _ => short_display(&raw),
+        };
+        lfn_slots.clear();
+        slots.push(offset);
+        entries.push(Entry { name, raw, slots });
+        true
+    })?;
+    Ok(entries)
+}
+
+/// The volume label stored in the root directory, if any.
+pub fn root_label(vol: &mut Volume) -> Result<Option<String>, String> {
+    let mut label = None;
+    let root = vol.root();
+    for_each_slot(vol, root, |_, slot| {
+        if slot[0] == 0 {
+            return false;
+        }
+        if slot[0] != DELETED && slot[11] & 0x3F != ATTR_LONG_NAME && slot[11] & ATTR_VOLUME_ID != 0
+        {
+            label = Some(super::volume::label_text(&slot[..11]));
+            return false;
+        }
+        true
+    })?;
+    Ok(label)
+}
+
+/// Find `name` in a directory by its name or its 8.3 alias
+/// (`PROGRA~1.TXT`), ignoring ASCII case.
+pub fn find(vol: &mut Volume, dir: DirLoc, name: &str) -> Result<Option<Entry>, String> {
+    Ok(list(vol, dir)?.into_iter().find(|e| {
+        e.name.eq_ignore_ascii_case(name) || short_display(&e.raw).eq_ignore_ascii_case(name)
+    }))
+}
+
+/// Byte offsets of `count` consecutive free slots, growing the directory
+/// if needed.
+fn free_slots(vol: &mut Volume, dir: DirLoc, count: usize) -> Result<Vec<u64>, String> {
+    let mut run = Vec::new();
+    let mut ended = false;
+    for_each_slot(vol, dir, |offset, slot| {
+        // Everything after the end marker is free
+        ended |= slot[0] == 0;
+        if ended || slot[0] == DELETED {
+            run.push(offset);
+        } else {
+            run.clear();
+        }
+        run.len() < count
+    })?;
+    while run.len() < count {
+        let (start, len) = vol.grow_dir(dir)?;
+        run.extend((0..len / ENTRY_SIZE as u64).map(|i| start + i * ENTRY_SIZE as u64));
+    }
+    run.truncate(count);
+    Ok(run)
+}
+
+/// Add an entry named `name` to a directory. `raw` supplies everything but
+/// the name; long-name entries are written when the name does not fit 8.3
+/// or its 8.3 form is another entry's short name.
+pub fn insert(
+    vol: &mut Volume,
+    dir: DirLoc,
+    name: &str,
+    mut raw: [u8; ENTRY_SIZE],
+) -> Result<Entry, String> {
+    validate_name(name)?;
+    let taken: Vec<[u8; 11]> = list(vol, dir)?
+        .iter()
+        .map(|e| {
+            let mut short = [0u8; 11];
+            short.copy_from_slice(&e.raw[..11]);
+            short
+        })
+        .collect();
+    let units: Vec<u16> = match exact_short_name(name).filter(|(short, _)| !taken.contains(short)) {
+        Some((short, case)) => {
+            raw[..11].copy_from_slice(&short);
+            raw[12] = case;
+            Vec::new()
+        }
+        None => {
+            let short = generate_short_name(name, &taken)?;
+            raw[..11].copy_from_slice(&short);
+            raw[12] = 0;
+            name.encode_utf16().collect()
+        }
+    };
+    if raw[0] == DELETED {
+        raw[0] = 0x05;
+    }
+
+    let lfn_count = units.len().div_ceil(LFN_CHARS);
+    let slots = free_slots(vol, dir, lfn_count + 1)?;
+    let sum = checksum(&raw);
+    for (i, &offset) in slots[..lfn_count].iter().enumerate() {
+        // Long-name entries come last part first
+        let ord = (lfn_count - i) as u8;
+        let mut slot = [0u8; ENTRY_SIZE];
+        slot[0] = if i == 0 { ord | 0x40 } else { ord };
+        slot[11] = ATTR_LONG_NAME;
+        slot[13] = sum;
+        let at = (ord as usize - 1) * LFN_CHARS;
+        for (j, &o) in LFN_OFFSETS.iter().enumerate() {
+            let unit = match (at + j).cmp(&units.len()) {
+                core::cmp::Ordering::Less => units[at + j],
+                core::cmp::Ordering::Equal => 0,
+                core::cmp::Ordering::Greater => 0xFFFF,
+            };
+            slot[o..o + 2].copy_from_slice(&unit.to_le_bytes());
+        }
+        vol.write_at(offset, &slot)?;
+    }
+    vol.write_at(slots[lfn_count], &raw)?;
+    Ok(Entry {
+        name: String::from(name),
+        raw,
+        slots,
+    })
+}
+
+/// Mark every slot of an entry as deleted.
+pub fn remove(vol: &mut Volume, entry: &Entry) -> Result<(), String> {
+    for &offset in &entry.slots {
+        vol.write_at(offset, &[DELETED])?;
+    }
+    Ok(())
+}
+
+/// Write back a changed short entry.
+pub fn update(vol: &mut Volume, entry: &Entry) -> Result<(), String> {
+    vol.write_at(entry.slot(), &entry.raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::fat::tests::{device, format, Image};
+    use crate::vfs::fat::volume::FatType;
+
+    fn setup(fat_type: FatType) -> (Image, Volume) {
+        let image = format(fat_type);
+        let vol = Volume::open(device(&image)).unwrap();
+        (image, vol)
+    }
+
+    fn file() -> [u8; ENTRY_SIZE] {
+        new_raw(ATTR_ARCHIVE, 0, &Time::invalid())
+    }
+
+    fn slot(image: &Image, offset: u64) -> Vec<u8> {
+        let at = offset as usize;
+        image.lock()[at..at + ENTRY_SIZE].to_vec()
+    }
+
+    #[test]
+    fn names_that_fit_use_one_entry() {
+        let (image, mut vol) = setup(FatType::Fat16);
+        let root = vol.root();
+        let entry = insert(&mut vol, root, "readme.txt", file()).unwrap();
+        assert_eq!(entry.slots.len(), 1);
+        let raw = slot(&image, entry.slot());
+        assert_eq!(&raw[..11], b"README  TXT");
+        assert_eq!(raw[12], LOWER_BASE | LOWER_EXT);
+
+        let entries = list(&mut vol, root).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "readme.txt");
+        assert!(find(&mut vol, root, "README.TXT").unwrap().is_some());
+    }
+
+    #[test]
+    fn long_names_round_trip() {
+        let (image, mut vol) = setup(FatType::Fat12);
+        let root = vol.root();
+        let name = "A long file name.text";
+        let entry = insert(&mut vol, root, name, file()).unwrap();
+        assert_eq!(entry.slots.len(), 3);
+
+        let short = slot(&image, entry.slot());
+        assert_eq!(&short[..11], b"ALONGF~1TEX");
+        let sum = checksum(&short);
+        let first = slot(&image, entry.slots[0]);
+        let second = slot(&image, entry.slots[1]);
+        // The last part comes first and is flagged
+        assert_eq!(
+            (first[0], first[11], first[13]),
+            (0x42, ATTR_LONG_NAME, sum)
+        );
+        assert_eq!(
+            (second[0], second[11], second[13]),
+            (0x01, ATTR_LONG_NAME, sum)
+        );
+        assert_eq!(&second[1..5], b"A\0 \0");
+        // Characters 14 to 21, then a terminator and padding
+        assert_eq!(&first[1..3], b"a\0");
+        assert_eq!(&first[20..22], [0, 0]);
+        assert_eq!(&first[30..32], [0xFF, 0xFF]);
+
+        let found = find(&mut vol, root, "a LONG file NAME.TEXT")
+            .unwrap()
+            .unwrap();
+        assert_eq!(found.name, name);
+        assert_eq!(found.slots, entry.slots);
+        let alias = find(&mut vol, root, "alongf~1.tex").unwrap().unwrap();
+        assert_eq!(alias.slot(), entry.slot());
+
+        // Deleting marks every slot
+        remove(&mut vol, &entry).unwrap();
+        for &offset in &entry.slots {
+            assert_eq!(slot(&image, offset)[0], DELETED);
+        }
+        assert!(list(&mut vol, root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unicode_names_round_trip() {
+        let (_, mut vol) = setup(FatType::Fat32);
+        let root = vol.root();
+        let name = "Grüße aus Köln.txt";
+        let entry = insert(&mut vol, root, name, file()).unwrap();
+        assert_eq!(&entry.raw[..11], b"GR__EA~1TXT");
+        assert_eq!(list(&mut vol, root).unwrap()[0].name, name);
+    }
+
+    #[test]
+    fn short_names_never_collide() {
+        let (_, mut vol) = setup(FatType::Fat16);
+        let root = vol.root();
+        let long = insert(&mut vol, root, "Program Files.txt", file()).unwrap();
+        assert_eq!(&long.raw[..11], b"PROGRA~1TXT");
+        // Fits 8.3, but that short name is taken
+        let exact = insert(&mut vol, root, "PROGRA~1.TXT", file()).unwrap();
+        assert_eq!(&exact.raw[..11], b"PROGRA~2TXT");
+        assert_eq!(exact.slots.len(), 2);
+        let third = insert(&mut vol, root, "Program Files 2.txt", file()).unwrap();
+        assert_eq!(&third.raw[..11], b"PROGRA~3TXT");
+
+        let names: Vec<String> = list(&mut vol, root)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(
+            names,
+            ["Program Files.txt", "PROGRA~1.TXT", "Program Files 2.txt"]
+        );
+    }
+
+    #[test]
+    fn directories_grow_but_the_fixed_root_does_not() {
+        let (_, mut vol) = setup(FatType::Fat16);
+        let root = vol.root();
+        for i in 0..512 {
+            insert(&mut vol, root, &format!("F{}", i), file()).unwrap();
+        }
+        assert!(insert(&mut vol, root, "full", file()).is_err());
+
+        let cluster = vol.allocate(None).unwrap();
+        vol.zero_cluster(cluster).unwrap();
+        let dir = DirLoc::Chain(cluster);
+        for i in 0..20 {
+            insert(&mut vol, dir, &format!("file{}", i), file()).unwrap();
+        }
+        // Sixteen entries to a 512-byte cluster
+        assert_eq!(vol.chain(cluster).unwrap().len(), 2);
+        assert_eq!(list(&mut vol, dir).unwrap().len(), 20);
+    }
+
+    #[test]
+    fn names_are_checked() {
+        for name in ["", ".", "..", "a:b", "trailing.", "tab\there", "a*"] {
+            assert!(validate_name(name).is_err(), "{:?}", name);
+        }
+        assert!(validate_name("..hidden").is_ok());
+    }
+}
@@ -0,0 +1,806 @@
+extern crate alloc;
+
+mod dir;
+mod volume;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+use spin::Mutex;
+use uefi::proto::media::file::FileAttribute;
+use uefi::runtime::Time;
+
+use self::dir::Entry;
+use self::volume::{DirLoc, FatType, Volume};
+use super::{now, DirEntry, FileSystem, FsStats, OpenFile, OpenMode};
+use crate::block::BlockDevice;
+use crate::path;
+
+/// Attributes `set_attributes` may change.
+const USER_ATTRS: u8 = 0x27;
+
+/// A FAT12, FAT16 or FAT32 file system read and written directly on a
+/// block device, without the firmware's driver.
+pub struct FatFs {
+    vol: Arc<Mutex<Volume>>,
+    source: String,
+    /// Where it is mounted, for messages.
+    point: String,
+}
+
+impl FatFs {
+    /// Read the FAT file system on `device`; `source` describes the device
+    /// for `mounts`.
+    pub fn mount(device: Box<dyn BlockDevice>, source: &str) -> Result<Self, String> {
+        let mut vol = Volume::open(device)?;
+        if let Some(label) = dir::root_label(&mut vol)? {
+            vol.label = label;
+        }
+        Ok(Self {
+            vol: Arc::new(Mutex::new(vol)),
+            source: String::from(source),
+            point: String::from(path::ROOT),
+        })
+    }
+
+    /// `path` as the full path shown in messages.
+    fn full(&self, path: &str) -> String {
+        super::full_path(&self.point, path)
+    }
+
+    fn check_writable(&self, vol: &Volume, path: &str) -> Result<(), String> {
+        if vol.read_only {
+            return Err(format!(
+                "Cannot change '{}': the volume is read-only",
+                self.full(path)
+            ));
+        }
+        Ok(())
+    }
+
+    /// The directory at `path`.
+    fn find_dir(&self, vol: &mut Volume, path: &str) -> Result<DirLoc, String> {
+        let mut dir = vol.root();
+        for part in path.split(path::SEPARATOR).filter(|p| !p.is_empty()) {
+            match dir::find(vol, dir, part)? {
+                Some(entry) if entry.is_dir() => dir = vol.dir_at(entry.cluster()),
+                Some(_) => return Err(format!("'{}' is not a directory", self.full(path))),
+                None => return Err(format!("Cannot open '{}'", self.full(path))),
+            }
+        }
+        Ok(dir)
+    }
+
+    /// The entry for `path`, which must not be the root.
+    fn lookup(&self, vol: &mut Volume, path: &str) -> Result<Entry, String> {
+        let fail = || format!("Cannot open '{}'", self.full(path));
+        let parent = self.find_dir(vol, path::parent(path)).map_err(|_| fail())?;
+        dir::find(vol, parent, path::file_name(path))?.ok_or_else(fail)
+    }
+}
+
+fn dir_entry(entry: &Entry) -> DirEntry {
+    DirEntry {
+        name: entry.name.clone(),
+        is_dir: entry.is_dir(),
+        size: if entry.is_dir() {
+            0
+        } else {
+            entry.size() as u64
+        },
+        attribute: FileAttribute::from_bits_truncate(entry.attr() as u64),
+        created: entry.created(),
+        modified: entry.modified(),
+        accessed: entry.accessed(),
+    }
+}
+
+fn root_entry(path: &str) -> DirEntry {
+    DirEntry {
+        name: String::from(path::file_name(path)),
+        is_dir: true,
+        size: 0,
+        attribute: FileAttribute::DIRECTORY,
+        created: Time::invalid(),
+        modified: Time::invalid(),
+        accessed: Time::invalid(),
+    }
+}
+
+impl FileSystem for FatFs {
+    fn kind(&self) -> &'static str {
+        match self.vol.lock().fat_type {
+            FatType::Fat12 => "fat12",
+            FatType::Fat16 => "fat16",
+            FatType::Fat32 => "fat32",
+        }
+    }
+
+    fn source(&self) -> String {
+        self.source.clone()
+    }
+
+    fn set_mount_point(&mut self, point: &str) {
+        self.point = String::from(point);
+    }
+
+    fn stats(&self) -> Result<FsStats, String> {
+        let mut vol = self.vol.lock();
+        let cluster_size = vol.cluster_size as u64;
+        Ok(FsStats {
+            label: vol.label.clone(),
+            size: vol.cluster_count as u64 * cluster_size,
+            free: vol.free_clusters()? as u64 * cluster_size,
+            read_only: vol.read_only,
+        })
+    }
+
+    fn open(&self, path: &str, mode: OpenMode) -> Result<Box<dyn OpenFile>, String> {
+        if path::is_root(path) {
+            return Err(format!("'{}' is a directory", self.full(path)));
+        }
+        let mut vol = self.vol.lock();
+        if mode != OpenMode::Read {
+            self.check_writable(&vol, path)?;
+        }
+        let entry = match self.lookup(&mut vol, path) {
+            Ok(entry) => entry,
+            Err(_) if mode == OpenMode::Create => {
+                let parent = self
+                    .find_dir(&mut vol, path::parent(path))
+                    .map_err(|_| format!("Cannot create '{}'", self.full(path)))?;
+                let raw = dir::new_raw(dir::ATTR_ARCHIVE, 0, &now());
+                dir::insert(&mut vol, parent, path::file_name(path), raw)?
+            }
+            Err(e) => return Err(e),
+        };
+        if entry.is_dir() {
+            return Err(format!("'{}' is a directory", self.full(path)));
+        }
+        if mode != OpenMode::Read && entry.attr() & dir::ATTR_READ_ONLY != 0 {
+            return Err(format!("'{}' is read-only", self.full(path)));
+        }
+        Ok(Box::new(FatFile {
+            vol: self.vol.clone(),
+            entry,
+            position: 0,
+            writable: mode != OpenMode::Read,
+            cursor: None,
+        }))
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        let mut vol = self.vol.lock();
+        let dir = self.find_dir(&mut vol, path)?;
+        Ok(dir::list(&mut vol, dir)?.iter().map(dir_entry).collect())
+    }
+
+    fn stat(&self, path: &str) -> Result<DirEntry, String> {
+        if path::is_root(path) {
+            return Ok(root_entry(path));
+        }
+        Ok(dir_entry(&self.lookup(&mut self.vol.lock(), path)?))
+    }
+
+    fn unlink(&self, path: &str) -> Result<(), String> {
+        let mut vol = self.vol.lock();
+        self.check_writable(&vol, path)?;
+        let entry = self.lookup(&mut vol, path)?;
+        if entry.is_dir() {
+            let dir = vol.dir_at(entry.cluster());
+            if dir::list(&mut vol, dir)?
+                .iter()
+                .any(|e| e.name != "." && e.name != "..")
+            {
+                return Err(format!(
+                    "Cannot delete '{}': directory not empty",
+                    self.full(path)
+                ));
+            }
+        }
+        dir::remove(&mut vol, &entry)?;
+        if entry.cluster() != 0 {
+            vol.free_chain(entry.cluster())?;
+        }
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), String> {
+        let mut vol = self.vol.lock();
+        if path::is_root(path) {
+            return Ok(());
+        }
+        match self.lookup(&mut vol, path) {
+            Ok(entry) if entry.is_dir() => return Ok(()),
+            Ok(_) => return Err(format!("'{}' already exists", self.full(path))),
+            Err(_) => {}
+        }
+        self.check_writable(&vol, path)?;
+        let parent = self
+            .find_dir(&mut vol, path::parent(path))
+            .map_err(|_| format!("Cannot create directory '{}'", self.full(path)))?;
+        dir::validate_name(path::file_name(path))?;
+
+        let now = now();
+        let cluster = vol.allocate(None)?;
+        let result = vol.zero_cluster(cluster).and_then(|_| {
+            let dots = dir::dot_entries(cluster, vol.dir_cluster(parent), &now);
+            let offset = vol.cluster_offset(cluster);
+            vol.write_at(offset, &dots)?;
+            let raw = dir::new_raw(dir::ATTR_DIRECTORY, cluster, &now);
+            dir::insert(&mut vol, parent, path::file_name(path), raw)
+        });
+        if let Err(e) = result {
+            let _ = vol.free_chain(cluster);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Writes the entry under its new name and directory, then deletes the
+    /// old one. A file already at `to` is replaced.
+    fn rename(&self, from: &str, to: &str) -> Result<(), String> {
+        let fail = || format!("Cannot rename '{}' to '{}'", self.full(from), self.full(to));
+        // Changing only the case of a name is not a move into itself
+        let into_itself = path::is_within(to, from) && !to.eq_ignore_ascii_case(from);
+        if path::is_root(from) || into_itself {
+            return Err(fail());
+        }
+        let mut vol = self.vol.lock();
+        self.check_writable(&vol, from)?;
+        let entry = self.lookup(&mut vol, from)?;
+        let old_parent = self.find_dir(&mut vol, path::parent(from))?;
+        let new_parent = self
+            .find_dir(&mut vol, path::parent(to))
+            .map_err(|_| fail())?;
+        if let Ok(existing) = self.lookup(&mut vol, to) {
+            // The same entry when only the case of the name changes
+            if existing.slot() != entry.slot() {
+                if existing.is_dir() {
+                    return Err(fail());
+                }
+                dir::remove(&mut vol, &existing)?;
+                if existing.cluster() != 0 {
+                    vol.free_chain(existing.cluster())?;
+                }
+            }
+        }
+        dir::insert(&mut vol, new_parent, path::file_name(to), entry.raw)?;
+        dir::remove(&mut vol, &entry)?;
+
+        // A moved directory's `..` must point at its new parent
+        if entry.is_dir() && new_parent != old_parent && entry.cluster() != 0 {
+            let at = vol.cluster_offset(entry.cluster()) + 32;
+            let mut dot_dot = [0u8; 32];
+            vol.read_at(at, &mut dot_dot)?;
+            dir::set_cluster(&mut dot_dot, vol.dir_cluster(new_parent));
+            vol.write_at(at, &dot_dot)?;
+        }
+        Ok(())
+    }
+
+    fn set_attributes(&self, path: &str, attribute: FileAttribute) -> Result<(), String> {
+        if path::is_root(path) {
+            return Err(format!("Cannot change attributes of '{}'", self.full(path)));
+        }
+        let mut vol = self.vol.lock();
+        self.check_writable(&vol, path)?;
+        let mut entry = self.lookup(&mut vol, path)?;
+        let attr = attribute.bits() as u8 & USER_ATTRS;
+        entry.raw[11] = (entry.attr() & dir::ATTR_DIRECTORY) | attr;
+        dir::update(&mut vol, &entry)
+    }
+
+    fn set_times(&self, path: &str, time: Time) -> Result<(), String> {
+        if path::is_root(path) {
+            return Err(format!("Cannot change times of '{}'", self.full(path)));
+        }
+        let mut vol = self.vol.lock();
+        self.check_writable(&vol, path)?;
+        let mut entry = self.lookup(&mut vol, path)?;
+        dir::set_modified(&mut entry.raw, &time);
+        dir::update(&mut vol, &entry)
+    }
+}
+
+/// An open file on a `FatFs`. Its directory entry is rewritten whenever
+/// the size or first cluster changes.
+struct FatFile {
+    vol: Arc<Mutex<Volume>>,
+    entry: Entry,
+    position: u64,
+    writable: bool,
+    /// The last cluster looked up and its index in the chain, so that
+    /// sequential access does not walk the chain from the start each time.
+    cursor: Option<(u64, u32)>,
+}
+
+impl FatFile {
+    /// The cluster holding byte `index * cluster_size` of the file. With
+    /// `grow`, clusters are added to reach it; otherwise `None` past the end.
+    fn cluster(&mut self, vol: &mut Volume, index: u64, grow: bool) -> Result<Option<u32>, String> {
+        let (mut i, mut cluster) = match self.cursor {
+            Some((i, cluster)) if i <= index => (i, cluster),
+            _ => match self.entry.cluster() {
+                0 if !grow => return Ok(None),
+                0 => {
+                    let first = vol.allocate(None)?;
+                    dir::set_cluster(&mut self.entry.raw, first);
+                    (0, first)
+                }
+                first => (0, first),
+            },
+        };
+        while i < index {
+            cluster = match vol.next_cluster(cluster)? {
+                Some(next) => next,
+                None if grow => vol.allocate(Some(cluster))?,
+                None => return Ok(None),
+            };
+            i += 1;
+        }
+        self.cursor = Some((i, cluster));
+        Ok(Some(cluster))
+    }
+
+    /// Write `data` at byte `offset`, adding clusters as needed. The size in
+    /// the entry is not changed.
+    fn write_data(&mut self, vol: &mut Volume, offset: u64, data: &[u8]) -> Result<(), String> {
+        let cluster_size = vol.cluster_size as u64;
+        let mut done = 0;
+        while done < data.len() {
+            let at = offset + done as u64;
+            let within = at % cluster_size;
+            let n = (data.len() - done).min((cluster_size - within) as usize);
+            let cluster = self
+                .cluster(vol, at / cluster_size, true)?
+                .ok_or_else(|| String::from("Write error"))?;
+            vol.write_at(vol.cluster_offset(cluster) + within, &data[done..done + n])?;
+            done += n;
+        }
+        Ok(())
+    }
+
+    /// Fill the file with zeros from its end up to `len` bytes.
+    fn extend(&mut self, vol: &mut Volume, len: u64) -> Result<(), String> {
+        let zeros = vec![0u8; vol.cluster_size as usize];
+        let mut at = self.entry.size() as u64;
+        while at < len {
+            let n = (len - at).min(zeros.len() as u64) as usize;
+            self.write_data(vol, at, &zeros[..n])?;
+            at += n as u64;
+        }
+        Ok(())
+    }
+
+    /// Free the clusters past the first `len` bytes.
+    fn trim(&mut self, vol: &mut Volume, len: u64) -> Result<(), String> {
+        if self.entry.cluster() == 0 {
+            return Ok(());
+        }
+        let keep = len.div_ceil(vol.cluster_size as u64);
+        if keep == 0 {
+            vol.free_chain(self.entry.cluster())?;
+            dir::set_cluster(&mut self.entry.raw, 0);
+        } else if let Some(last) = self.cluster(vol, keep - 1, false)? {
+            vol.truncate_chain(last)?;
+        }
+        self.cursor = None;
+        Ok(())
+    }
+
+    /// Record a new size and the modification in the directory entry.
+    fn save(&mut self, vol: &mut Volume, size: u64) -> Result<(), String> {
+        dir::set_size(&mut self.entry.raw, size as u32);
+        dir::set_modified(&mut self.entry.raw, &now());
+        self.entry.raw[11] |= dir::ATTR_ARCHIVE;
+        dir::update(vol, &self.entry)
+    }
+
+    fn check_writable(&self) -> Result<(), String> {
+        if !self.writable {
+            return Err(String::from("File is open for reading only"));
+        }
+        Ok(())
+    }
+}
+
+/// FAT stores sizes in 32 bits.
+fn check_size(len: u64) -> Result<(), String> {
+    if len > u32::MAX as u64 {
+        return Err(String::from("File too large for FAT (4 GB limit)"));
+    }
+    Ok(())
+}
+
+impl OpenFile for FatFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+        let vol = self.vol.clone();
+        let mut vol = vol.lock();
+        let cluster_size = vol.cluster_size as u64;
+        let size = self.entry.size() as u64;
+        let total = buf.len().min(size.saturating_sub(self.position) as usize);
+        let mut done = 0;
+        while done < total {
+            let at = self.position + done as u64;
+            let within = at % cluster_size;
+            let n = (total - done).min((cluster_size - within) as usize);
+            let cluster = self
+                .cluster(&mut vol, at / cluster_size, false)?
+                .ok_or_else(|| String::from("Read error: cluster chain too short"))?;
+            let offset = vol.cluster_offset(cluster) + within;
+            vol.read_at(offset, &mut buf[done..done + n])?;
+            done += n;
+        }
+        self.position += total as u64;
+        Ok(total)
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        self.check_writable()?;
+        let end = self.position + data.len() as u64;
+        check_size(end)?;
+        let vol = self.vol.clone();
+        let mut vol = vol.lock();
+        let size = self.entry.size() as u64;
+        let result = if self.position > size {
+            self.extend(&mut vol, self.position)
+        } else {
+            Ok(())
+        };
+        if let Err(e) = result.and_then(|_| self.write_data(&mut vol, self.position, data)) {
+            // Give back clusters allocated past the old end, e.g. when full
+            self.trim(&mut vol, size)?;
+            self.save(&mut vol, size)?;
+            return Err(e);
+        }
+        self.position = end;
+        self.save(&mut vol, end.max(size))
+    }
+
+    fn seek(&mut self, position: u64) -> Result<(), String> {
+        self.position = position;
+        Ok(())
+    }
+
+    fn size(&mut self) -> Result<u64, String> {
+        Ok(self.entry.size() as u64)
+    }
+
+    fn set_len(&mut self, len: u64) -> Result<(), String> {
+        self.check_writable()?;
+        check_size(len)?;
+        let vol = self.vol.clone();
+        let mut vol = vol.lock();
+        let size = self.entry.size() as u64;
+        if len > size {
+            if let Err(e) = self.extend(&mut vol, len) {
+                self.trim(&mut vol, size)?;
+                self.save(&mut vol, size)?;
+                return Err(e);
+            }
+        } else if len < size {
+            self.trim(&mut vol, len)?;
+        }
+        self.save(&mut vol, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::image::ImageDisk;
+
+    /// Disk image bytes shared between mounts.
+    pub type Image = Arc<Mutex<Vec<u8>>>;
+
+    pub const TYPES: [FatType; 3] = [FatType::Fat12, FatType::Fat16, FatType::Fat32];
+
+    /// An image file in memory, for mounting through `ImageDisk`.
+    struct MemFile {
+        image: Image,
+        position: u64,
+    }
+
+    impl OpenFile for MemFile {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+            let image = self.image.lock();
+            let start = (self.position as usize).min(image.len());
+            let n = buf.len().min(image.len() - start);
+            buf[..n].copy_from_slice(&image[start..start + n]);
+            self.position += n as u64;
+            Ok(n)
+        }
+
+        fn write(&mut self, data: &[u8]) -> Result<(), String> {
+            let mut image = self.image.lock();
+            let start = self.position as usize;
+            if image.len() < start + data.len() {
+                image.resize(start + data.len(), 0);
+            }
+            image[start..start + data.len()].copy_from_slice(data);
+            self.position += data.len() as u64;
+            Ok(())
+        }
+
+        fn seek(&mut self, position: u64) -> Result<(), String> {
+            self.position = position;
+            Ok(())
+        }
+
+        fn size(&mut self) -> Result<u64, String> {
+            Ok(self.image.lock().len() as u64)
+        }
+
+        fn set_len(&mut self, len: u64) -> Result<(), String> {
+            self.image.lock().resize(len as usize, 0);
+            Ok(())
+        }
+    }
+
+    /// A freshly formatted image with 512-byte sectors, one per cluster,
+    /// two FATs and the label `TESTVOL`. The sizes are just large enough
+    /// for each type.
+    pub fn format(fat_type: FatType) -> Image {
+        let (sectors, reserved, root_entries, bits) = match fat_type {
+            FatType::Fat12 => (2048u32, 1u32, 512u32, 12u32),
+            FatType::Fat16 => (8192, 1, 512, 16),
+            FatType::Fat32 => (66_700, 32, 0, 32),
+        };
+        let fat_sectors = ((sectors + 2) * bits / 8).div_ceil(512);
+        let root_sectors = root_entries * 32 / 512;
+        let clusters = sectors - reserved - 2 * fat_sectors - root_sectors;
+        let mut image = vec![0u8; sectors as usize * 512];
+
+        let boot = &mut image[..512];
+        boot[..3].copy_from_slice(&[0xEB, 0x58, 0x90]);
+        boot[3..11].copy_from_slice(b"MSWIN4.1");
+        boot[11..13].copy_from_slice(&512u16.to_le_bytes());
+        boot[13] = 1;
+        boot[14..16].copy_from_slice(&(reserved as u16).to_le_bytes());
+        boot[16] = 2;
+        boot[17..19].copy_from_slice(&(root_entries as u16).to_le_bytes());
+        boot[21] = 0xF8;
+        if sectors < 0x10000 {
+            boot[19..21].copy_from_slice(&(sectors as u16).to_le_bytes());
+        } else {
+            boot[32..36].copy_from_slice(&sectors.to_le_bytes());
+        }
+        let label_at = if fat_type == FatType::Fat32 {
+            boot[36..40].copy_from_slice(&fat_sectors.to_le_bytes());
+            // Root directory in cluster 2, FSInfo in sector 1
+            boot[44..48].copy_from_slice(&2u32.to_le_bytes());
+            boot[48..50].copy_from_slice(&1u16.to_le_bytes());
+            66
+        } else {
+            boot[22..24].copy_from_slice(&(fat_sectors as u16).to_le_bytes());
+            38
+        };
+        boot[label_at] = 0x29;
+        boot[label_at + 5..label_at + 16].copy_from_slice(b"TESTVOL    ");
+        boot[510] = 0x55;
+        boot[511] = 0xAA;
+
+        // Media and end-of-chain entries for clusters 0 and 1, and the
+        // FAT32 root directory's cluster
+        let start: &[u8] = match fat_type {
+            FatType::Fat12 => &[0xF8, 0xFF, 0xFF],
+            FatType::Fat16 => &[0xF8, 0xFF, 0xFF, 0xFF],
+            FatType::Fat32 => &[
+                0xF8, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF, 0xFF, 0x0F, 0xFF, 0xFF, 0xFF, 0x0F,
+            ],
+        };
+        for copy in 0..2 {
+            let at = ((reserved + copy * fat_sectors) * 512) as usize;
+            image[at..at + start.len()].copy_from_slice(start);
+        }
+
+        if fat_type == FatType::Fat32 {
+            let info = &mut image[512..1024];
+            info[..4].copy_from_slice(&0x4161_5252u32.to_le_bytes());
+            info[484..488].copy_from_slice(&0x6141_7272u32.to_le_bytes());
+            info[488..492].copy_from_slice(&(clusters - 1).to_le_bytes());
+            info[492..496].copy_from_slice(&3u32.to_le_bytes());
+            info[508..512].copy_from_slice(&0xAA55_0000u32.to_le_bytes());
+        }
+        Arc::new(Mutex::new(image))
+    }
+
+    /// A block device over `image`, as `imgmount` would make.
+    pub fn device(image: &Image) -> Box<dyn BlockDevice> {
+        let file = MemFile {
+            image: image.clone(),
+            position: 0,
+        };
+        Box::new(ImageDisk::new(Box::new(file), false).unwrap())
+    }
+
+    fn mount(image: &Image) -> FatFs {
+        FatFs::mount(device(image), "memory").unwrap()
+    }
+
+    pub fn pattern(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 7 % 251) as u8).collect()
+    }
+
+    fn write_file(fs: &FatFs, path: &str, data: &[u8]) {
+        let mut file = fs.open(path, OpenMode::Create).unwrap();
+        file.write(data).unwrap();
+    }
+
+    fn read_file(fs: &FatFs, path: &str) -> Vec<u8> {
+        let mut file = fs.open(path, OpenMode::Read).unwrap();
+        let mut data = vec![0u8; file.size().unwrap() as usize + 1];
+        let n = file.read(&mut data).unwrap();
+        data.truncate(n);
+        data
+    }
+
+    fn names(fs: &FatFs, path: &str) -> Vec<String> {
+        fs.read_dir(path)
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect()
+    }
+
+    fn used(fs: &FatFs, free: u64) -> u64 {
+        (free - fs.stats().unwrap().free) / 512
+    }
+
+    #[test]
+    fn files_survive_a_remount() {
+        for (fat_type, kind) in TYPES.into_iter().zip(["fat12", "fat16", "fat32"]) {
+            let image = format(fat_type);
+            let fs = mount(&image);
+            assert_eq!(fs.kind(), kind);
+            assert_eq!(fs.stats().unwrap().label, "TESTVOL");
+            let big = pattern(3 * 512 + 100);
+            write_file(&fs, "\\hello.txt", b"Hello, FAT");
+            fs.mkdir("\\Some Directory").unwrap();
+            write_file(&fs, "\\Some Directory\\A long file name.data", &big);
+            drop(fs);
+
+            let fs = mount(&image);
+            assert_eq!(names(&fs, "\\"), ["hello.txt", "Some Directory"]);
+            assert_eq!(
+                names(&fs, "\\Some Directory"),
+                [".", "..", "A long file name.data"]
+            );
+            assert_eq!(read_file(&fs, "\\HELLO.TXT"), b"Hello, FAT");
+            assert_eq!(
+                read_file(&fs, "\\some directory\\a long file name.data"),
+                big
+            );
+            let entry = fs.stat("\\Some Directory\\A long file name.data").unwrap();
+            assert_eq!(entry.size, big.len() as u64);
+            assert_eq!((entry.modified.year(), entry.modified.hour()), (2024, 12));
+        }
+    }
+
+    #[test]
+    fn deleting_gives_clusters_back() {
+        for fat_type in TYPES {
+            let image = format(fat_type);
+            let fs = mount(&image);
+            let free = fs.stats().unwrap().free;
+            fs.mkdir("\\dir").unwrap();
+            write_file(&fs, "\\dir\\file.bin", &pattern(2000));
+            // One cluster for the directory, four for the file
+            assert_eq!(used(&fs, free), 5);
+
+            assert!(fs.unlink("\\dir").is_err());
+            fs.unlink("\\dir\\file.bin").unwrap();
+            assert_eq!(names(&fs, "\\dir"), [".", ".."]);
+            fs.unlink("\\dir").unwrap();
+            assert_eq!(used(&fs, free), 0);
+            drop(fs);
+
+            // Counted again from the table
+            let fs = mount(&image);
+            assert!(names(&fs, "\\").is_empty());
+            assert_eq!(used(&fs, free), 0);
+        }
+    }
+
+    #[test]
+    fn files_grow_and_shrink_by_clusters() {
+        for fat_type in TYPES {
+            let image = format(fat_type);
+            let fs = mount(&image);
+            let free = fs.stats().unwrap().free;
+            let chain = |fs: &FatFs| {
+                let mut vol = fs.vol.lock();
+                let first = fs.lookup(&mut vol, "\\grow.bin").unwrap().cluster();
+                vol.chain(first).unwrap().len()
+            };
+
+            let mut file = fs.open("\\grow.bin", OpenMode::Create).unwrap();
+            file.write(&pattern(1000)).unwrap();
+            assert_eq!((chain(&fs), used(&fs, free)), (2, 2));
+            // Writing past the end fills the gap with zeros
+            file.seek(3000).unwrap();
+            file.write(b"end").unwrap();
+            assert_eq!((chain(&fs), used(&fs, free)), (6, 6));
+            let data = read_file(&fs, "\\grow.bin");
+            assert_eq!(data.len(), 3003);
+            assert_eq!(&data[..1000], &pattern(1000)[..]);
+            assert!(data[1000..3000].iter().all(|&b| b == 0));
+            assert_eq!(&data[3000..], b"end");
+
+            file.set_len(600).unwrap();
+            assert_eq!((chain(&fs), used(&fs, free)), (2, 2));
+            assert_eq!(read_file(&fs, "\\grow.bin"), &pattern(600)[..]);
+            file.set_len(0).unwrap();
+            drop(file);
+            assert_eq!(used(&fs, free), 0);
+            let vol = &mut fs.vol.lock();
+            assert_eq!(fs.lookup(vol, "\\grow.bin").unwrap().cluster(), 0);
+        }
+    }
+
+    #[test]
+    fn moved_directories_point_at_their_new_parent() {
+        for fat_type in TYPES {
+            let image = format(fat_type);
+            let fs = mount(&image);
+            fs.mkdir("\\a").unwrap();
+            fs.mkdir("\\b").unwrap();
+            write_file(&fs, "\\a\\file.txt", b"moved");
+            fs.rename("\\a", "\\b\\a").unwrap();
+            drop(fs);
+
+            let fs = mount(&image);
+            assert_eq!(names(&fs, "\\"), ["b"]);
+            assert_eq!(read_file(&fs, "\\b\\a\\file.txt"), b"moved");
+            let mut vol = fs.vol.lock();
+            let b = fs.lookup(&mut vol, "\\b").unwrap().cluster();
+            let a = fs.lookup(&mut vol, "\\b\\a").unwrap().cluster();
+            let dot_dot = dir::list(&mut vol, DirLoc::Chain(a)).unwrap()[1].cluster();
+            assert_eq!(dot_dot, b);
+            // `..` of a directory in the root is 0, even on FAT32
+            let dot_dot = dir::list(&mut vol, DirLoc::Chain(b)).unwrap()[1].cluster();
+            assert_eq!(dot_dot, 0);
+        }
+    }
+
+    #[test]
+    fn fsinfo_free_count_is_invalidated_by_changes() {
+        let image = format(FatType::Fat32);
+        let fsinfo_free = |image: &Image| {
+            let image = image.lock();
+            u32::from_le_bytes([image[1000], image[1001], image[1002], image[1003]])
+        };
+        let stored = fsinfo_free(&image);
+        let fs = mount(&image);
+        assert_eq!(fs.stats().unwrap().free, stored as u64 * 512);
+        // Reading leaves the count alone
+        fs.read_dir("\\").unwrap();
+        assert_eq!(fsinfo_free(&image), stored);
+
+        write_file(&fs, "\\file.txt", b"data");
+        assert_eq!(fsinfo_free(&image), 0xFFFF_FFFF);
+        assert_eq!(fs.stats().unwrap().free, (stored as u64 - 1) * 512);
+        drop(fs);
+        let fs = mount(&image);
+        assert_eq!(fs.stats().unwrap().free, (stored as u64 - 1) * 512);
+    }
+
+    #[test]
+    fn read_only_images_refuse_changes() {
+        let image = format(FatType::Fat16);
+        let file = MemFile {
+            image: image.clone(),
+            position: 0,
+        };
+        let disk = ImageDisk::new(Box::new(file), true).unwrap();
+        let fs = FatFs::mount(Box::new(disk), "memory").unwrap();
+        assert!(fs.stats().unwrap().read_only);
+        assert!(fs.open("\\new.txt", OpenMode::Create).is_err());
+        assert!(fs.mkdir("\\dir").is_err());
+        assert!(names(&fs, "\\").is_empty());
+    }
+}
@@ -0,0 +1,621 @@
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::block::BlockDevice;
+
+/// Blocks kept in the read cache. Writes go straight to the device.
+const CACHE_BLOCKS: usize = 8;
+
+/// Free cluster counts are only trusted from FSInfo until the first change.
+const FSINFO_UNKNOWN: u32 = 0xFFFF_FFFF;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatType {
+    Fat12,
+    Fat16,
+    Fat32,
+}
+
+/// Where a directory's entries are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DirLoc {
+    /// The fixed root directory of FAT12 and FAT16.
+    FixedRoot,
+    /// A cluster chain, as for every other directory.
+    Chain(u32),
+}
+
+/// A mounted FAT volume: the boot sector geometry, byte-level access to
+/// the device and the allocation table.
+pub struct Volume {
+    device: Box<dyn BlockDevice>,
+    block_size: usize,
+    pub read_only: bool,
+    cache: Vec<(u64, Vec<u8>)>,
+    pub fat_type: FatType,
+    /// Bytes per cluster.
+    pub cluster_size: u32,
+    /// Number of data clusters; valid cluster numbers are `2..cluster_count + 2`.
+    pub cluster_count: u32,
+    /// Byte offset of the first FAT.
+    fat_start: u64,
+    /// Bytes per FAT.
+    fat_size: u64,
+    num_fats: u8,
+    /// Byte offset and size of the FAT12/16 root directory.
+    root_start: u64,
+    root_size: u64,
+    /// First cluster of the FAT32 root directory.
+    root_cluster: u32,
+    /// Byte offset of cluster 2.
+    data_start: u64,
+    /// Byte offset of the FAT32 FSInfo sector, if valid.
+    fsinfo: Option<u64>,
+    /// Cached count of free clusters, counted on first use.
+    free: Option<u32>,
+    /// Where the search for a free cluster starts.
+    next_free: u32,
+    pub label: String,
+}
+
+fn le16(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+fn le32(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+impl Volume {
+    /// Read the boot sector and work out the layout. Fails if the device
+    /// does not hold a FAT file system.
+    pub fn open(device: Box<dyn BlockDevice>) -> Result<Self, String> {
+        let block_size = device.block_size();
+        let read_only = device.read_only();
+        let mut vol = Self {
+            device,
+            block_size,
+            read_only,
+            cache: Vec::new(),
+            fat_type: FatType::Fat12,
+            cluster_size: 0,
+            cluster_count: 0,
+            fat_start: 0,
+            fat_size: 0,
+            num_fats: 0,
+            root_start: 0,
+            root_size: 0,
+            root_cluster: 0,
+            data_start: 0,
+            fsinfo: None,
+            free: None,
+            next_free: 2,
+            label: String::new(),
+        };
+        let not_fat = || String::from("Not a FAT file system");
+        let mut boot = [0u8; 512];
+        vol.read_at(0, &mut boot)?;
+        if boot[510] != 0x55 || boot[511] != 0xAA {
+            return Err(not_fat());
+        }
+
+        let bytes_per_sector = le16(&boot, 11) as u64;
+        let sectors_per_cluster = boot[13] as u64;
+        let reserved = le16(&boot, 14) as u64;
+        let num_fats = boot[16];
+        let root_entries = le16(&boot, 17) as u64;
+        let fat_sectors = match le16(&boot, 22) {
+            0 => le32(&boot, 36) as u64,
+            n => n as u64,
+        };
+        let total_sectors = match le16(&boot, 19) {
+            0 => le32(&boot, 32) as u64,
+            n => n as u64,
+        };
+        if !matches!(bytes_per_sector, 512 | 1024 | 2048 | 4096)
+            || !sectors_per_cluster.is_power_of_two()
+            || reserved == 0
+            || num_fats == 0
+            || fat_sectors == 0
+        {
+            return Err(not_fat());
+        }
+
+        let root_sectors = (root_entries * 32).div_ceil(bytes_per_sector);
+        let data_sector = reserved + num_fats as u64 * fat_sectors + root_sectors;
+        let clusters = total_sectors.saturating_sub(data_sector) / sectors_per_cluster;
+        vol.fat_type = match clusters {
+            0 => return Err(not_fat()),
+            1..4085 => FatType::Fat12,
+            4085..65525 => FatType::Fat16,
+            _ => FatType::Fat32,
+        };
+        vol.cluster_size = (bytes_per_sector * sectors_per_cluster) as u32;
+        vol.cluster_count = u32::try_from(clusters).map_err(|_| not_fat())?;
+        vol.fat_start = reserved * bytes_per_sector;
+        vol.fat_size = fat_sectors * bytes_per_sector;
+        vol.num_fats = num_fats;
+        vol.root_start = vol.fat_start + num_fats as u64 * vol.fat_size;
+        vol.root_size = root_entries * 32;
+        vol.data_start = data_sector * bytes_per_sector;
+
+        // The FAT must have room for every cluster
+        let fat_bytes = match vol.fat_type {
+            FatType::Fat12 => (clusters + 2) * 3 / 2,
+            FatType::Fat16 => (clusters + 2) * 2,
+            FatType::Fat32 => (clusters + 2) * 4,
+        };
+        if fat_bytes > vol.fat_size {
+            return Err(not_fat());
+        }
+
+        let label_at = if vol.fat_type == FatType::Fat32 {
+            vol.root_cluster = le32(&boot, 44);
+            if vol.root_cluster < 2 || vol.root_cluster >= vol.cluster_count + 2 {
+                return Err(not_fat());
+            }
+            let fsinfo = le16(&boot, 48) as u64 * bytes_per_sector;
+            let mut info = [0u8; 512];
+            // Lead and structure signatures
+            if fsinfo != 0
+                && vol.read_at(fsinfo, &mut info).is_ok()
+                && le32(&info, 0) == 0x4161_5252
+                && le32(&info, 484) == 0x6141_7272
+            {
+                vol.fsinfo = Some(fsinfo);
+                let hint = le32(&info, 492);
+                if hint >= 2 && hint < vol.cluster_count + 2 {
+                    vol.next_free = hint;
+                }
+            }
+            (66, 71)
+        } else {
+            (38, 43)
+        };
+        // Extended boot signature, then the label
+        if boot[label_at.0] == 0x29 {
+            vol.label = label_text(&boot[label_at.1..label_at.1 + 11]);
+        }
+        Ok(vol)
+    }
+
+    // ── Byte access ──
+
+    fn read_block(&mut self, lba: u64) -> Result<&[u8], String> {
+        if let Some(i) = self.cache.iter().position(|(cached, _)| *cached == lba) {
+            return Ok(&self.cache[i].1);
+        }
+        let mut data = vec![0u8; self.block_size];
+        self.device.read_blocks(lba, &mut data)?;
+        if self.cache.len() == CACHE_BLOCKS {
+            self.cache.remove(0);
+        }
+        self.cache.push((lba, data));
+        Ok(&self.cache[self.cache.len() - 1].1)
+    }
+
+    /// Read `buf.len()` bytes starting at the byte `offset` on the device.
+    pub fn read_at(&mut self, mut offset: u64, buf: &mut [u8]) -> Result<(), String> {
+        let bs = self.block_size as u64;
+        let mut done = 0;
+        while done < buf.len() {
+            let lba = offset / bs;
+            let within = (offset % bs) as usize;
+            let left = buf.len() - done;
+            // Whole blocks bypass the cache, which never holds stale data
+            let n = if within == 0 && left >= self.block_size {
+                let n = left - left % self.block_size;
+                self.device.read_blocks(lba, &mut buf[done..done + n])?;
+                n
+            } else {
+                let n = left.min(self.block_size - within);
+                let block = self.read_block(lba)?;
+                buf[done..done + n].copy_from_slice(&block[within..within + n]);
+                n
+            };
+            done += n;
+            offset += n as u64;
+        }
+        Ok(())
+    }
+
+    /// Write `data` starting at the byte `offset` on the device.
+    pub fn write_at(&mut self, mut offset: u64, data: &[u8]) -> Result<(), String> {
+        let bs = self.block_size as u64;
+        let mut done = 0;
+        while done < data.len() {
+            let lba = offset / bs;
+            let within = (offset % bs) as usize;
+            let left = data.len() - done;
+            let n = if within == 0 && left >= self.block_size {
+                let n = left - left % self.block_size;
+                self.device.write_blocks(lba, &data[done..done + n])?;
+                let end = lba + (n / self.block_size) as u64;
+                self.cache
+                    .retain(|(cached, _)| *cached < lba || *cached >= end);
+                n
+            } else {
+                let n = left.min(self.block_size - within);
+                let mut block = Vec::from(self.read_block(lba)?);
+                block[within..within + n].copy_from_slice(&data[done..done + n]);
+                self.device.write_blocks(lba, &block)?;
+                if let Some(cached) = self.cache.iter_mut().find(|(cached, _)| *cached == lba) {
+                    cached.1 = block;
+                }
+                n
+            };
+            done += n;
+            offset += n as u64;
+        }
+        Ok(())
+    }
+
+    // ── Allocation table ──
+
+    fn end_of_chain(&self) -> u32 {
+        match self.fat_type {
+            FatType::Fat12 => 0xFFF,
+            FatType::Fat16 => 0xFFFF,
+            FatType::Fat32 => 0x0FFF_FFFF,
+        }
+    }
+
+    fn entry_offset(&self, cluster: u32) -> u64 {
+        let n = cluster as u64;
+        match self.fat_type {
+            FatType::Fat12 => n + n / 2,
+            FatType::Fat16 => n * 2,
+            FatType::Fat32 => n * 4,
+        }
+    }
+
+    fn fat_entry(&mut self, cluster: u32) -> Result<u32, String> {
+        let offset = self.fat_start + self.entry_offset(cluster);
+        Ok(match self.fat_type {
+            FatType::Fat12 => {
+                let mut b = [0u8; 2];
+                self.read_at(offset, &mut b)?;
+                let v = u16::from_le_bytes(b) as u32;
+                if cluster & 1 == 1 {
+                    v >> 4
+                } else {
+                    v & 0xFFF
+                }
+            }
+            FatType::Fat16 => {
+                let mut b = [0u8; 2];
+                self.read_at(offset, &mut b)?;
+                u16::from_le_bytes(b) as u32
+            }
+            FatType::Fat32 => {
+                let mut b = [0u8; 4];
+                self.read_at(offset, &mut b)?;
+                u32::from_le_bytes(b) & 0x0FFF_FFFF
+            }
+        })
+    }
+
+    /// Set an entry in every copy of the FAT.
+    fn set_fat_entry(&mut self, cluster: u32, value: u32) -> Result<(), String> {
+        let offset = self.entry_offset(cluster);
+        for copy in 0..self.num_fats as u64 {
+            let at = self.fat_start + copy * self.fat_size + offset;
+            match self.fat_type {
+                FatType::Fat12 => {
+                    let mut b = [0u8; 2];
+                    self.read_at(at, &mut b)?;
+                    let old = u16::from_le_bytes(b);
+                    let new = if cluster & 1 == 1 {
+                        (old & 0x000F) | ((value as u16) << 4)
+                    } else {
+                        (old & 0xF000) | (value as u16 & 0x0FFF)
+                    };
+                    self.write_at(at, &new.to_le_bytes())?;
+                }
+                FatType::Fat16 => self.write_at(at, &(value as u16).to_le_bytes())?,
+                FatType::Fat32 => {
+                    let mut b = [0u8; 4];
+                    self.read_at(at, &mut b)?;
+                    let new = (u32::from_le_bytes(b) & 0xF000_0000) | (value & 0x0FFF_FFFF);
+                    self.write_at(at, &new.to_le_bytes())?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn is_valid(&self, cluster: u32) -> bool {
+        cluster >= 2 && cluster < self.cluster_count + 2
+    }
+
+    /// The cluster after `cluster` in its chain, or `None` at the end.
+    pub fn next_cluster(&mut self, cluster: u32) -> Result<Option<u32>, String> {
+        let next = self.fat_entry(cluster)?;
+        if next >= self.end_of_chain() - 7 {
+            Ok(None)
+        } else if self.is_valid(next) {
+            Ok(Some(next))
+        } else {
+            Err(format!("Corrupt cluster chain at cluster {}", cluster))
+        }
+    }
+
+    /// Every cluster of the chain starting at `first`.
+    pub fn chain(&mut self, first: u32) -> Result<Vec<u32>, String> {
+        let mut clusters = Vec::new();
+        let mut cluster = Some(first).filter(|&c| c != 0);
+        while let Some(c) = cluster {
+            if !self.is_valid(c) || clusters.len() > self.cluster_count as usize {
+                return Err(format!("Corrupt cluster chain at cluster {}", c));
+            }
+            clusters.push(c);
+            cluster = self.next_cluster(c)?;
+        }
+        Ok(clusters)
+    }
+
+    /// Allocate a cluster and link it after `prev`. Its contents are left
+    /// as they were.
+    pub fn allocate(&mut self, prev: Option<u32>) -> Result<u32, String> {
+        let count = self.cluster_count;
+        let start = self.next_free.clamp(2, count + 1);
+        let mut found = None;
+        for i in 0..count {
+            let cluster = 2 + (start - 2 + i) % count;
+            if self.fat_entry(cluster)? == 0 {
+                found = Some(cluster);
+                break;
+            }
+        }
+        let cluster = found.ok_or_else(|| String::from("No space left on the FAT volume"))?;
+        self.invalidate_fsinfo()?;
+        self.set_fat_entry(cluster, self.end_of_chain())?;
+        if let Some(prev) = prev {
+            self.set_fat_entry(prev, cluster)?;
+        }
+        self.free = self.free.map(|free| free.saturating_sub(1));
+        self.next_free = cluster + 1;
+        Ok(cluster)
+    }
+
+    pub fn zero_cluster(&mut self, cluster: u32) -> Result<(), String> {
+        let zeros = vec![0u8; self.cluster_size as usize];
+        self.write_at(self.cluster_offset(cluster), &zeros)
+    }
+
+    /// Free `first` and every cluster after it.
+    pub fn free_chain(&mut self, first: u32) -> Result<(), String> {
+        let clusters = self.chain(first)?;
+        self.invalidate_fsinfo()?;
+        for &cluster in &clusters {
+            self.set_fat_entry(cluster, 0)?;
+        }
+        self.free = self.free.map(|free| free + clusters.len() as u32);
+        Ok(())
+    }
+
+    /// End the chain at `last`, freeing the clusters after it.
+    pub fn truncate_chain(&mut self, last: u32) -> Result<(), String> {
+        if let Some(next) = self.next_cluster(last)? {
+            self.set_fat_entry(last, self.end_of_chain())?;
+            self.free_chain(next)?;
+        }
+        Ok(())
+    }
+
+    /// Mark the FSInfo free count as unknown before the first change, so
+    /// other systems recount instead of trusting a stale value.
+    fn invalidate_fsinfo(&mut self) -> Result<(), String> {
+        if let Some(fsinfo) = self.fsinfo.take() {
+            self.write_at(fsinfo + 488, &FSINFO_UNKNOWN.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Number of free clusters, counted once and then kept up to date.
+    pub fn free_clusters(&mut self) -> Result<u32, String> {
+        if let Some(free) = self.free {
+            return Ok(free);
+        }
+        let mut free = 0;
+        if self.fat_type == FatType::Fat12 {
+            for cluster in 2..self.cluster_count + 2 {
+                if self.fat_entry(cluster)? == 0 {
+                    free += 1;
+                }
+            }
+        } else {
+            // Read the table in large chunks rather than entry by entry
+            let width = if self.fat_type == FatType::Fat16 {
+                2
+            } else {
+                4
+            };
+            let end = (self.cluster_count as u64 + 2) * width;
+            let mut chunk = vec![0u8; 64 * 1024];
+            let mut offset = 2 * width;
+            while offset < end {
+                let n = chunk.len().min((end - offset) as usize);
+                self.read_at(self.fat_start + offset, &mut chunk[..n])?;
+                free += chunk[..n]
+                    .chunks_exact(width as usize)
+                    .filter(|e| {
+                        if width == 2 {
+                            le16(e, 0) == 0
+                        } else {
+                            le32(e, 0) & 0x0FFF_FFFF == 0
+                        }
+                    })
+                    .count() as u32;
+                offset += n as u64;
+            }
+        }
+        self.free = Some(free);
+        Ok(free)
+    }
+
+    // ── Clusters and directories ──
+
+    pub fn cluster_offset(&self, cluster: u32) -> u64 {
+        self.data_start + (cluster as u64 - 2) * self.cluster_size as u64
+    }
+
+    pub fn root(&self) -> DirLoc {
+        match self.fat_type {
+            FatType::Fat32 => DirLoc::Chain(self.root_cluster),
+            _ => DirLoc::FixedRoot,
+        }
+    }
+
+    /// The directory whose first cluster is `cluster`; 0 means the root,
+    /// as in `..` entries.
+    pub fn dir_at(&self, cluster: u32) -> DirLoc {
+        match cluster {
+            0 => self.root(),
+            c => DirLoc::Chain(c),
+        }
+    }
+
+    /// The first cluster of a directory as stored in `..` entries, where
+    /// the root is 0.
+    pub fn dir_cluster(&self, dir: DirLoc) -> u32 {
+        match dir {
+            DirLoc::Chain(c) if c != self.root_cluster => c,
+            _ => 0,
+        }
+    }
+
+    /// Byte offsets of the areas holding a directory's entries.
+    pub fn dir_areas(&mut self, dir: DirLoc) -> Result<Vec<(u64, u64)>, String> {
+        match dir {
+            DirLoc::FixedRoot => Ok(vec![(self.root_start, self.root_size)]),
+            DirLoc::Chain(first) => Ok(self
+                .chain(first)?
+                .into_iter()
+                .map(|c| (self.cluster_offset(c), self.cluster_size as u64))
+                .collect()),
+        }
+    }
+
+    /// Add a zeroed cluster to a directory. The fixed root cannot grow.
+    pub fn grow_dir(&mut self, dir: DirLoc) -> Result<(u64, u64), String> {
+        let DirLoc::Chain(first) = dir else {
+            return Err(String::from("The root directory is full"));
+        };
+        let last = *self.chain(first)?.last().unwrap_or(&first);
+        let cluster = self.allocate(Some(last))?;
+        self.zero_cluster(cluster)?;
+        Ok((self.cluster_offset(cluster), self.cluster_size as u64))
+    }
+}
+
+/// An 11-byte space-padded label or short name as text.
+pub fn label_text(raw: &[u8]) -> String {
+    let text: String = raw.iter().map(|&b| b as char).collect();
+    let text = text.trim_end();
+    if text == "NO NAME" {
+        String::new()
+    } else {
+        String::from(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::fat::tests::{device, format, Image, TYPES};
+
+    fn open(image: &Image) -> Volume {
+        Volume::open(device(image)).unwrap()
+    }
+
+    /// The first `len` bytes of each FAT copy.
+    fn fats(vol: &Volume, image: &Image, len: usize) -> [Vec<u8>; 2] {
+        let image = image.lock();
+        [0, 1].map(|copy| {
+            let at = (vol.fat_start + copy * vol.fat_size) as usize;
+            image[at..at + len].to_vec()
+        })
+    }
+
+    #[test]
+    fn layouts_are_recognised() {
+        for (fat_type, clusters) in TYPES.into_iter().zip([2001, 8093, 65624]) {
+            let vol = open(&format(fat_type));
+            assert_eq!(vol.fat_type, fat_type);
+            assert_eq!(vol.cluster_count, clusters);
+            assert_eq!(vol.cluster_size, 512);
+            assert_eq!(vol.label, "TESTVOL");
+            assert!(!vol.read_only);
+        }
+        let image = format(FatType::Fat16);
+        image.lock()[510] = 0;
+        assert!(Volume::open(device(&image)).is_err());
+    }
+
+    #[test]
+    fn fat12_entries_share_bytes() {
+        let image = format(FatType::Fat12);
+        let mut vol = open(&image);
+        let first = vol.allocate(None).unwrap();
+        let second = vol.allocate(Some(first)).unwrap();
+        let third = vol.allocate(Some(second)).unwrap();
+        assert_eq!(vol.chain(first).unwrap(), [2, 3, 4]);
+        assert_eq!(third, 4);
+        // Entries 0 to 4: FF8 FFF 003 004 FFF
+        let bytes = [0xF8, 0xFF, 0xFF, 0x03, 0x40, 0x00, 0xFF, 0x0F];
+        assert_eq!(fats(&vol, &image, 8), [bytes.to_vec(), bytes.to_vec()]);
+
+        vol.truncate_chain(first).unwrap();
+        let bytes = [0xF8, 0xFF, 0xFF, 0xFF, 0x0F, 0x00, 0x00, 0x00];
+        assert_eq!(fats(&vol, &image, 8), [bytes.to_vec(), bytes.to_vec()]);
+        assert_eq!(vol.chain(first).unwrap(), [2]);
+    }
+
+    #[test]
+    fn chains_grow_and_shrink() {
+        for fat_type in TYPES {
+            let image = format(fat_type);
+            let mut vol = open(&image);
+            let free = vol.free_clusters().unwrap();
+            let first = vol.allocate(None).unwrap();
+            let mut last = first;
+            for _ in 0..4 {
+                last = vol.allocate(Some(last)).unwrap();
+            }
+            let chain = vol.chain(first).unwrap();
+            assert_eq!(chain.len(), 5);
+            assert_eq!(vol.next_cluster(last).unwrap(), None);
+            assert_eq!(vol.free_clusters().unwrap(), free - 5);
+
+            vol.truncate_chain(chain[1]).unwrap();
+            assert_eq!(vol.chain(first).unwrap(), chain[..2]);
+            assert_eq!(vol.free_clusters().unwrap(), free - 2);
+            let grown = vol.allocate(Some(chain[1])).unwrap();
+            assert_eq!(vol.chain(first).unwrap(), [chain[0], chain[1], grown]);
+
+            vol.free_chain(first).unwrap();
+            assert_eq!(vol.free_clusters().unwrap(), free);
+            let [main, backup] = fats(&vol, &image, vol.fat_size as usize);
+            assert_eq!(main, backup);
+            // A fresh count from the table agrees
+            assert_eq!(open(&image).free_clusters().unwrap(), free);
+        }
+    }
+
+    #[test]
+    fn fat32_entries_keep_their_reserved_bits() {
+        let image = format(FatType::Fat32);
+        let mut vol = open(&image);
+        let at = vol.fat_start as usize + 3 * 4;
+        image.lock()[at + 3] = 0xA0;
+        // The FSInfo hint skips the root directory's cluster
+        assert_eq!(vol.allocate(None).unwrap(), 3);
+        assert_eq!(image.lock()[at..at + 4], [0xFF, 0xFF, 0xFF, 0xAF]);
+    }
+}
@@ -0,0 +1,451 @@
+extern crate alloc;
+
+pub mod fat;
+pub mod ramfs;
+pub mod uefi_fs;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use spin::Mutex;
+use uefi::proto::media::file::FileAttribute;
+use uefi::runtime::{self, Time};
+use uefi::Handle;
+
+use crate::path;
+
+// ── Backend interface ──
+
+/// How `FileSystem::open` opens a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenMode {
+    /// Read an existing file.
+    Read,
+    /// Read and write an existing file.
+    ReadWrite,
+    /// Read and write, creating the file if it does not exist.
+    Create,
+}
+
+/// A file or directory as listed by `read_dir` or returned by `stat`.
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    pub attribute: FileAttribute,
+    pub created: Time,
+    pub modified: Time,
+    pub accessed: Time,
+}
+
+impl DirEntry {
+    /// `.` and `..` entries.
+    pub fn is_dot(&self) -> bool {
+        self.name == "." || self.name == ".."
+    }
+}
+
+/// Size and label of a mounted file system.
+pub struct FsStats {
+    pub label: String,
+    pub size: u64,
+    pub free: u64,
+    pub read_only: bool,
+}
+
+/// An open regular file.
+pub trait OpenFile: Send {
+    /// Read from the current position; 0 means the end of the file.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, String>;
+    /// Write all of `data` at the current position.
+    fn write(&mut self, data: &[u8]) -> Result<(), String>;
+    /// Move to the byte offset `position`.
+    fn seek(&mut self, position: u64) -> Result<(), String>;
+    /// Length of the file in bytes.
+    fn size(&mut self) -> Result<u64, String>;
+    /// Truncate or extend the file to `len` bytes.
+    fn set_len(&mut self, len: u64) -> Result<(), String>;
+}
+
+/// A file system backend. Paths are absolute within the file system
+/// (`\dir\file`), already normalized and without a volume prefix.
+pub trait FileSystem {
+    /// Short name of the backend, as shown by `mounts`.
+    fn kind(&self) -> &'static str;
+    /// Where the data comes from, e.g. a device path.
+    fn source(&self) -> String;
+    fn stats(&self) -> Result<FsStats, String>;
+
+    fn open(&self, path: &str, mode: OpenMode) -> Result<Box<dyn OpenFile>, String>;
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, String>;
+    fn stat(&self, path: &str) -> Result<DirEntry, String>;
+    /// Delete a file or an empty directory.
+    fn unlink(&self, path: &str) -> Result<(), String>;
+    /// Create a directory; an existing one is not an error.
+    fn mkdir(&self, path: &str) -> Result<(), String>;
+
+    /// Rename or move within this file system.
+    fn rename(&self, _from: &str, _to: &str) -> Result<(), String> {
+        Err(format!("Cannot rename files on {}", self.kind()))
+    }
+
+    /// Replace the attributes a user may change (read-only, hidden, system,
+    /// archive), keeping the directory flag.
+    fn set_attributes(&self, _path: &str, _attribute: FileAttribute) -> Result<(), String> {
+        Err(format!("Cannot change attributes on {}", self.kind()))
+    }
+
+    /// Set the modification and access times.
+    fn set_times(&self, _path: &str, _time: Time) -> Result<(), String> {
+        Err(format!("Cannot change times on {}", self.kind()))
+    }
+
+    /// The firmware device the file system lives on, for handing its files
+    /// to other UEFI programs. `None` for file systems VOS implements.
+    fn device(&self) -> Option<Handle> {
+        None
+    }
+
+    /// Tell the file system where it is mounted, e.g. `\tmp`, so that its
+    /// messages can give paths in full.
+    fn set_mount_point(&mut self, point: &str);
+}
+
+/// The current time for new and changed files, or an invalid time if the
+/// clock cannot be read.
+#[cfg(not(test))]
+fn now() -> Time {
+    runtime::get_time().unwrap_or(Time::invalid())
+}
+
+/// Host tests have no firmware clock, so files get a fixed time.
+#[cfg(test)]
+fn now() -> Time {
+    Time::new(runtime::TimeParams {
+        year: 2024,
+        month: 5,
+        day: 17,
+        hour: 12,
+        minute: 34,
+        second: 56,
+        nanosecond: 0,
+        time_zone: None,
+        daylight: runtime::Daylight::empty(),
+    })
+    .unwrap()
+}
+
+// ── Mount table ──
+
+/// A mounted backend, shared so it can be used without holding the mount
+/// table's lock.
+type SharedFs = Arc<dyn FileSystem + Send + Sync>;
+
+struct Mount {
+    /// Normalized path the file system appears at, e.g. `\` or `fs1:\`.
+    point: String,
+    fs: SharedFs,
+}
+
+static MOUNTS: Mutex<Vec<Mount>> = Mutex::new(Vec::new());
+
+/// A mount table entry as listed by `mounts`.
+pub struct MountInfo {
+    pub point: String,
+    pub kind: &'static str,
+    pub source: String,
+}
+
+/// Where the scratch ramfs is mounted.
+pub const TMP: &str = "\\tmp";
+
+/// Mount the UEFI volumes (the boot volume at `\` and every volume at its
+/// `fsN:` prefix) and a ramfs at `\tmp`.
+pub fn init() {
+    let _ = uefi_fs::mount_volumes();
+    mount(
+        TMP,
+        Box::new(ramfs::RamFs::new(crate::memory::ramfs_limit())),
+    );
+}
+
+/// Mount `fs` at `point`, replacing whatever was mounted there. Operations
+/// already running on a replaced file system finish on it.
+pub fn mount(point: &str, mut fs: Box<dyn FileSystem + Send + Sync>) {
+    let point = path::normalize(point);
+    fs.set_mount_point(&point);
+    let mut mounts = MOUNTS.lock();
+    mounts.retain(|m| !m.point.eq_ignore_ascii_case(&point));
+    mounts.push(Mount {
+        point,
+        fs: Arc::from(fs),
+    });
+}
+
+pub fn unmount(point: &str) -> Result<(), String> {
+    let point = path::normalize(point);
+    let mut mounts = MOUNTS.lock();
+    let before = mounts.len();
+    mounts.retain(|m| !m.point.eq_ignore_ascii_case(&point));
+    if mounts.len() == before {
+        return Err(format!("'{}' is not mounted", point));
+    }
+    Ok(())
+}
+
+/// The mount table, sorted by mount point.
+pub fn mounts() -> Vec<MountInfo> {
+    let mounts: Vec<(String, SharedFs)> = MOUNTS
+        .lock()
+        .iter()
+        .map(|m| (m.point.clone(), m.fs.clone()))
+        .collect();
+    let mut list: Vec<MountInfo> = mounts
+        .into_iter()
+        .map(|(point, fs)| MountInfo {
+            point,
+            kind: fs.kind(),
+            source: fs.source(),
+        })
+        .collect();
+    list.sort_by(|a, b| a.point.cmp(&b.point));
+    list
+}
+
+/// The mount `path` (normalized) is on: the one with the longest matching
+/// point, so `\tmp` can sit on `\`.
+fn find<'a>(mounts: &'a [Mount], path: &str) -> Option<&'a Mount> {
+    mounts
+        .iter()
+        .filter(|m| path::is_within(path, &m.point))
+        .max_by_key(|m| m.point.len())
+}
+
+/// A path resolved to the file system it is on.
+struct Target {
+    fs: SharedFs,
+    /// The path within `fs`.
+    inner: String,
+}
+
+/// Find the file system `path` is on. The mount table is only locked while
+/// looking, so backends run unlocked.
+fn resolve(path: &str) -> Result<Target, String> {
+    let full = path::normalize(path);
+    let mounts = MOUNTS.lock();
+    let mount = find(&mounts, &full).ok_or_else(|| match path::split_volume(&full).0 {
+        Some(volume) => format!("No volume '{}:' (see vol)", volume),
+        None => String::from("Nothing is mounted at the root"),
+    })?;
+    let point = mount.point.trim_end_matches(path::SEPARATOR);
+    Ok(Target {
+        fs: mount.fs.clone(),
+        inner: path::normalize(&full[point.len()..]),
+    })
+}
+
+/// `inner`, a path within the file system mounted at `point`, as a full
+/// path.
+pub fn full_path(point: &str, inner: &str) -> String {
+    path::normalize(&path::join(point, inner))
+}
+
+/// Run `f` with the file system `path` is on and the path within it.
+fn with_fs<R>(
+    path: &str,
+    f: impl FnOnce(&dyn FileSystem, &str) -> Result<R, String>,
+) -> Result<R, String> {
+    let target = resolve(path)?;
+    f(target.fs.as_ref(), &target.inner)
+}
+
+/// Mount points directly inside the directory `dir`.
+fn child_mounts(dir: &str) -> Vec<String> {
+    let dir = path::normalize(dir);
+    MOUNTS
+        .lock()
+        .iter()
+        .filter(|m| !path::is_root(&m.point) && path::parent(&m.point).eq_ignore_ascii_case(&dir))
+        .map(|m| String::from(path::file_name(&m.point)))
+        .collect()
+}
+
+/// True if both targets are on one file system: one mount, or two mounts
+/// of one firmware volume, such as `\` and the boot volume's `fsN:`.
+fn on_same_volume(a: &Target, b: &Target) -> bool {
+    Arc::ptr_eq(&a.fs, &b.fs)
+        || matches!((a.fs.device(), b.fs.device()), (Some(x), Some(y)) if x == y)
+}
+
+/// True if `a` and `b` are on the same file system, so one can be renamed
+/// to the other.
+pub fn same_volume(a: &str, b: &str) -> bool {
+    match (resolve(a), resolve(b)) {
+        (Ok(a), Ok(b)) => on_same_volume(&a, &b),
+        _ => false,
+    }
+}
+
+/// True if `a` and `b` name the same file: the same path, ignoring case,
+/// on the same file system.
+pub fn same_file(a: &str, b: &str) -> bool {
+    match (resolve(a), resolve(b)) {
+        (Ok(a), Ok(b)) => on_same_volume(&a, &b) && a.inner.eq_ignore_ascii_case(&b.inner),
+        _ => false,
+    }
+}
+
+/// The firmware device `path` is on and the path within it, if it is on a
+/// firmware volume rather than one VOS implements.
+pub fn device_of(path: &str) -> Option<(Handle, String)> {
+    with_fs(path, |fs, inner| {
+        fs.device()
+            .map(|handle| (handle, String::from(inner)))
+            .ok_or_else(String::new)
+    })
+    .ok()
+}
+
+// ── Operations by path ──
+
+pub fn open(path: &str, mode: OpenMode) -> Result<Box<dyn OpenFile>, String> {
+    with_fs(path, |fs, inner| fs.open(inner, mode))
+}
+
+/// List a directory, including the mount points inside it.
+pub fn read_dir(path: &str) -> Result<Vec<DirEntry>, String> {
+    let mut entries = with_fs(path, |fs, inner| fs.read_dir(inner))?;
+    for name in child_mounts(path) {
+        if !entries.iter().any(|e| e.name.eq_ignore_ascii_case(&name)) {
+            let mut entry = stat(&path::join(path, &name))?;
+            entry.name = name;
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Size and label of the file system `path` is on.
+pub fn stats(path: &str) -> Result<FsStats, String> {
+    with_fs(path, |fs, _| fs.stats())
+}
+
+pub fn stat(path: &str) -> Result<DirEntry, String> {
+    with_fs(path, |fs, inner| fs.stat(inner))
+}
+
+/// Delete a file or an empty directory; a mount point cannot be deleted.
+pub fn unlink(path: &str) -> Result<(), String> {
+    with_fs(path, |fs, inner| {
+        if path::is_root(inner) {
+            return Err(format!(
+                "Cannot delete '{}': it is a mount point",
+                path::normalize(path)
+            ));
+        }
+        fs.unlink(inner)
+    })
+}
+
+pub fn mkdir(path: &str) -> Result<(), String> {
+    with_fs(path, |fs, inner| fs.mkdir(inner))
+}
+
+/// Rename within one file system; moving between file systems is an error.
+pub fn rename(from: &str, to: &str) -> Result<(), String> {
+    let target = resolve(to)?;
+    if !on_same_volume(&resolve(from)?, &target) {
+        return Err(format!("Cannot rename '{}' to another file system", from));
+    }
+    with_fs(from, |fs, inner| fs.rename(inner, &target.inner))
+}
+
+pub fn set_attributes(path: &str, attribute: FileAttribute) -> Result<(), String> {
+    with_fs(path, |fs, inner| fs.set_attributes(inner, attribute))
+}
+
+pub fn set_times(path: &str, time: Time) -> Result<(), String> {
+    with_fs(path, |fs, inner| fs.set_times(inner, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Mount an empty ramfs at `point`; each test uses its own points, as
+    /// the mount table is shared.
+    fn mount_ram(point: &str) {
+        mount(point, Box::new(ramfs::RamFs::new(64 * 1024)));
+    }
+
+    fn create(path: &str) {
+        open(path, OpenMode::Create).unwrap();
+    }
+
+    #[test]
+    fn mount_points_ignore_case() {
+        mount_ram("\\point");
+        mount_ram("\\POINT");
+        let count = |point: &str| {
+            mounts()
+                .iter()
+                .filter(|m| m.point.eq_ignore_ascii_case(point))
+                .count()
+        };
+        assert_eq!(count("\\point"), 1);
+        unmount("\\Point").unwrap();
+        assert_eq!(count("\\point"), 0);
+        assert!(unmount("\\point").is_err());
+    }
+
+    #[test]
+    fn same_file_and_volume() {
+        mount_ram("\\same1");
+        mount_ram("\\same2");
+        create("\\same1\\a.txt");
+        assert!(same_file("\\same1\\a.txt", "\\SAME1\\A.TXT"));
+        assert!(!same_file("\\same1\\a.txt", "\\same1\\b.txt"));
+        assert!(!same_file("\\same1\\a.txt", "\\same2\\a.txt"));
+        assert!(same_volume("\\same1\\a.txt", "\\same1\\b.txt"));
+        assert!(!same_volume("\\same1\\a.txt", "\\same2\\a.txt"));
+        assert!(rename("\\same1\\a.txt", "\\same2\\a.txt").is_err());
+    }
+
+    #[test]
+    fn rename_may_change_only_case() {
+        mount_ram("\\case");
+        create("\\case\\readme.txt");
+        mkdir("\\case\\docs").unwrap();
+        rename("\\case\\readme.txt", "\\case\\README.TXT").unwrap();
+        rename("\\case\\docs", "\\case\\Docs").unwrap();
+        let mut names: Vec<String> = read_dir("\\case")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        names.sort();
+        assert_eq!(names, ["Docs", "README.TXT"]);
+        assert!(rename("\\case\\Docs", "\\case\\docs\\inner").is_err());
+    }
+
+    #[test]
+    fn messages_give_full_paths() {
+        mount_ram("\\quote");
+        let error = |result: Result<Box<dyn OpenFile>, String>| result.err().unwrap();
+        assert_eq!(
+            error(open("\\quote\\it's.txt", OpenMode::Read)),
+            "Cannot open '\\quote\\it's.txt'"
+        );
+        mkdir("\\quote\\dir").unwrap();
+        assert_eq!(
+            error(open("\\QUOTE\\dir", OpenMode::Read)),
+            "'\\quote\\dir' is a directory"
+        );
+        assert_eq!(
+            unlink("\\quote").unwrap_err(),
+            "Cannot delete '\\quote': it is a mount point"
+        );
+    }
+}
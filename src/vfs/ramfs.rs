@@ -0,0 +1,479 @@
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::String;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicU64, Ordering};
+use spin::Mutex;
+use uefi::proto::media::file::FileAttribute;
+use uefi::runtime::Time;
+
+use super::{now, DirEntry, FileSystem, FsStats, OpenFile, OpenMode};
+use crate::path;
+
+/// Backend name, as shown by `mounts`.
+pub const KIND: &str = "ramfs";
+
+type NodeRef = Arc<Mutex<Node>>;
+
+enum Content {
+    File(Vec<u8>),
+    /// Children with their names as created.
+    Dir(Vec<(String, NodeRef)>),
+}
+
+struct Node {
+    /// Without the directory flag, which follows from `content`.
+    attribute: FileAttribute,
+    created: Time,
+    modified: Time,
+    accessed: Time,
+    content: Content,
+}
+
+impl Node {
+    fn new(content: Content) -> NodeRef {
+        let now = now();
+        Arc::new(Mutex::new(Node {
+            attribute: FileAttribute::empty(),
+            created: now,
+            modified: now,
+            accessed: now,
+            content,
+        }))
+    }
+
+    fn entry(&self, name: &str) -> DirEntry {
+        let (is_dir, size) = match &self.content {
+            Content::File(data) => (false, data.len() as u64),
+            Content::Dir(_) => (true, 0),
+        };
+        DirEntry {
+            name: String::from(name),
+            is_dir,
+            size,
+            attribute: if is_dir {
+                self.attribute | FileAttribute::DIRECTORY
+            } else {
+                self.attribute
+            },
+            created: self.created,
+            modified: self.modified,
+            accessed: self.accessed,
+        }
+    }
+}
+
+/// Bytes of file data stored, against the limit set at mount time.
+struct Space {
+    used: AtomicU64,
+    limit: u64,
+}
+
+impl Space {
+    /// Account for a file changing from `old` to `new` bytes.
+    fn resize(&self, old: usize, new: usize) -> Result<(), String> {
+        let (old, new) = (old as u64, new as u64);
+        if new > old {
+            let grow = new - old;
+            self.used
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |used| {
+                    (used + grow <= self.limit).then_some(used + grow)
+                })
+                .map_err(|_| format!("ramfs is full ({} KB limit)", self.limit / 1024))?;
+        } else {
+            self.used.fetch_sub(old - new, Ordering::SeqCst);
+        }
+        Ok(())
+    }
+}
+
+/// A file system kept in memory, e.g. for scratch files in `\tmp`. File
+/// data lives on the heap and is lost on reboot; names are matched ignoring
+/// ASCII case, as on FAT.
+pub struct RamFs {
+    root: NodeRef,
+    space: Arc<Space>,
+    /// Where it is mounted, for messages.
+    point: String,
+}
+
+impl RamFs {
+    /// An empty file system holding at most `limit` bytes of file data.
+    pub fn new(limit: u64) -> Self {
+        Self {
+            root: Node::new(Content::Dir(Vec::new())),
+            space: Arc::new(Space {
+                used: AtomicU64::new(0),
+                limit,
+            }),
+            point: String::from(path::ROOT),
+        }
+    }
+
+    /// `path` as the full path shown in messages.
+    fn full(&self, path: &str) -> String {
+        super::full_path(&self.point, path)
+    }
+
+    fn lookup(&self, path: &str) -> Result<NodeRef, String> {
+        let mut node = self.root.clone();
+        for part in path.split(path::SEPARATOR).filter(|p| !p.is_empty()) {
+            let child = match &node.lock().content {
+                Content::Dir(children) => children
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(part))
+                    .map(|(_, child)| child.clone()),
+                Content::File(_) => None,
+            };
+            node = child.ok_or_else(|| format!("Cannot open '{}'", self.full(path)))?;
+        }
+        Ok(node)
+    }
+
+    /// Add `node` to the directory holding `path`, under the last component
+    /// of `path`. An existing entry of that name is an error.
+    fn insert(&self, path: &str, node: NodeRef) -> Result<(), String> {
+        let parent = self.lookup(path::parent(path))?;
+        let mut parent = parent.lock();
+        let Content::Dir(children) = &mut parent.content else {
+            return Err(format!(
+                "'{}' is not a directory",
+                self.full(path::parent(path))
+            ));
+        };
+        let name = path::file_name(path);
+        if children.iter().any(|(n, _)| n.eq_ignore_ascii_case(name)) {
+            return Err(format!("'{}' already exists", self.full(path)));
+        }
+        children.push((String::from(name), node));
+        parent.modified = now();
+        Ok(())
+    }
+
+    /// Take the entry for `path` out of its directory.
+    fn detach(&self, path: &str) -> Result<NodeRef, String> {
+        let parent = self.lookup(path::parent(path))?;
+        let mut parent = parent.lock();
+        let Content::Dir(children) = &mut parent.content else {
+            return Err(format!("Cannot open '{}'", self.full(path)));
+        };
+        let name = path::file_name(path);
+        let index = children
+            .iter()
+            .position(|(n, _)| n.eq_ignore_ascii_case(name))
+            .ok_or_else(|| format!("Cannot open '{}'", self.full(path)))?;
+        let (_, node) = children.remove(index);
+        parent.modified = now();
+        Ok(node)
+    }
+
+    /// Free the data of a file that is being removed.
+    fn release(&self, node: &NodeRef) {
+        if let Content::File(data) = &mut node.lock().content {
+            let _ = self.space.resize(data.len(), 0);
+            *data = Vec::new();
+        }
+    }
+}
+
+impl FileSystem for RamFs {
+    fn kind(&self) -> &'static str {
+        KIND
+    }
+
+    fn set_mount_point(&mut self, point: &str) {
+        self.point = String::from(point);
+    }
+
+    fn source(&self) -> String {
+        String::from("memory")
+    }
+
+    fn stats(&self) -> Result<FsStats, String> {
+        let used = self.space.used.load(Ordering::SeqCst);
+        Ok(FsStats {
+            label: String::from(KIND),
+            size: self.space.limit,
+            free: self.space.limit.saturating_sub(used),
+            read_only: false,
+        })
+    }
+
+    fn open(&self, path: &str, mode: OpenMode) -> Result<Box<dyn OpenFile>, String> {
+        let node = match self.lookup(path) {
+            Ok(node) => node,
+            Err(_) if mode == OpenMode::Create && !path::is_root(path) => {
+                let node = Node::new(Content::File(Vec::new()));
+                self.insert(path, node.clone())
+                    .map_err(|_| format!("Cannot create '{}'", self.full(path)))?;
+                node
+            }
+            Err(e) => return Err(e),
+        };
+        {
+            let mut locked = node.lock();
+            if matches!(locked.content, Content::Dir(_)) {
+                return Err(format!("'{}' is a directory", self.full(path)));
+            }
+            if mode != OpenMode::Read && locked.attribute.contains(FileAttribute::READ_ONLY) {
+                return Err(format!("'{}' is read-only", self.full(path)));
+            }
+            locked.accessed = now();
+        }
+        Ok(Box::new(RamFile {
+            node,
+            position: 0,
+            writable: mode != OpenMode::Read,
+            space: self.space.clone(),
+        }))
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        let node = self.lookup(path)?;
+        let node = node.lock();
+        let Content::Dir(children) = &node.content else {
+            return Err(format!("'{}' is not a directory", self.full(path)));
+        };
+        Ok(children
+            .iter()
+            .map(|(name, child)| child.lock().entry(name))
+            .collect())
+    }
+
+    fn stat(&self, path: &str) -> Result<DirEntry, String> {
+        Ok(self.lookup(path)?.lock().entry(path::file_name(path)))
+    }
+
+    fn unlink(&self, path: &str) -> Result<(), String> {
+        let node = self.lookup(path)?;
+        if matches!(&node.lock().content, Content::Dir(children) if !children.is_empty()) {
+            return Err(format!(
+                "Cannot delete '{}': directory not empty",
+                self.full(path)
+            ));
+        }
+        self.detach(path)?;
+        self.release(&node);
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), String> {
+        match self.lookup(path) {
+            Ok(node) if matches!(node.lock().content, Content::Dir(_)) => Ok(()),
+            Ok(_) => Err(format!("'{}' already exists", self.full(path))),
+            Err(_) => self
+                .insert(path, Node::new(Content::Dir(Vec::new())))
+                .map_err(|_| format!("Cannot create directory '{}'", self.full(path))),
+        }
+    }
+
+    /// Moves the entry to its new directory and name, replacing a file
+    /// already there.
+    fn rename(&self, from: &str, to: &str) -> Result<(), String> {
+        let fail = || format!("Cannot rename '{}' to '{}'", self.full(from), self.full(to));
+        // Changing only the case of a name is not a move into itself
+        let into_itself = path::is_within(to, from) && !to.eq_ignore_ascii_case(from);
+        if path::is_root(from) || into_itself {
+            return Err(fail());
+        }
+        let node = self.lookup(from)?;
+        let parent_is_dir = self
+            .lookup(path::parent(to))
+            .is_ok_and(|parent| matches!(parent.lock().content, Content::Dir(_)));
+        if !parent_is_dir {
+            return Err(fail());
+        }
+        if let Ok(existing) = self.lookup(to) {
+            // The same entry when only the case of the name changes
+            if !Arc::ptr_eq(&existing, &node) {
+                if matches!(existing.lock().content, Content::Dir(_)) {
+                    return Err(fail());
+                }
+                self.detach(to)?;
+                self.release(&existing);
+            }
+        }
+        let node = self.detach(from)?;
+        self.insert(to, node)
+    }
+
+    fn set_attributes(&self, path: &str, attribute: FileAttribute) -> Result<(), String> {
+        self.lookup(path)?.lock().attribute = attribute - FileAttribute::DIRECTORY;
+        Ok(())
+    }
+
+    fn set_times(&self, path: &str, time: Time) -> Result<(), String> {
+        let node = self.lookup(path)?;
+        let mut node = node.lock();
+        node.modified = time;
+        node.accessed = time;
+        Ok(())
+    }
+}
+
+/// An open file on a `RamFs`.
+struct RamFile {
+    node: NodeRef,
+    position: u64,
+    writable: bool,
+    space: Arc<Space>,
+}
+
+impl RamFile {
+    /// Resize the file's data to `len` bytes, within the space limit.
+    fn resize(&mut self, len: usize) -> Result<(), String> {
+        if !self.writable {
+            return Err(String::from("File is open for reading only"));
+        }
+        let mut node = self.node.lock();
+        let Content::File(data) = &mut node.content else {
+            return Err(String::from("Not a file"));
+        };
+        self.space.resize(data.len(), len)?;
+        data.resize(len, 0);
+        node.modified = now();
+        Ok(())
+    }
+}
+
+impl OpenFile for RamFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+        let node = self.node.lock();
+        let Content::File(data) = &node.content else {
+            return Err(String::from("Read error"));
+        };
+        let start = usize::try_from(self.position).map_or(data.len(), |p| p.min(data.len()));
+        let n = buf.len().min(data.len() - start);
+        buf[..n].copy_from_slice(&data[start..start + n]);
+        self.position += n as u64;
+        Ok(n)
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        let start = usize::try_from(self.position).map_err(|_| String::from("Seek error"))?;
+        let end = start + bytes.len();
+        if end > self.size()? as usize {
+            self.resize(end)?;
+        } else if !self.writable {
+            return Err(String::from("File is open for reading only"));
+        }
+        let mut node = self.node.lock();
+        if let Content::File(data) = &mut node.content {
+            data[start..end].copy_from_slice(bytes);
+        }
+        node.modified = now();
+        self.position = end as u64;
+        Ok(())
+    }
+
+    fn seek(&mut self, position: u64) -> Result<(), String> {
+        self.position = position;
+        Ok(())
+    }
+
+    fn size(&mut self) -> Result<u64, String> {
+        match &self.node.lock().content {
+            Content::File(data) => Ok(data.len() as u64),
+            Content::Dir(_) => Err(String::from("Not a file")),
+        }
+    }
+
+    fn set_len(&mut self, len: u64) -> Result<(), String> {
+        self.resize(usize::try_from(len).map_err(|_| String::from("File too large"))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_file(fs: &RamFs, path: &str, data: &[u8]) -> Result<(), String> {
+        fs.open(path, OpenMode::Create)?.write(data)
+    }
+
+    fn read_file(fs: &RamFs, path: &str) -> Vec<u8> {
+        let mut file = fs.open(path, OpenMode::Read).unwrap();
+        let mut data = vec![0; file.size().unwrap() as usize];
+        assert_eq!(file.read(&mut data).unwrap(), data.len());
+        data
+    }
+
+    fn used(fs: &RamFs) -> u64 {
+        let stats = fs.stats().unwrap();
+        stats.size - stats.free
+    }
+
+    #[test]
+    fn files_are_created_written_and_read() {
+        let fs = RamFs::new(1024);
+        fs.mkdir("\\dir").unwrap();
+        write_file(&fs, "\\dir\\a.txt", b"hello").unwrap();
+        let mut file = fs.open("\\DIR\\A.TXT", OpenMode::ReadWrite).unwrap();
+        file.seek(5).unwrap();
+        file.write(b", world").unwrap();
+        drop(file);
+        assert_eq!(read_file(&fs, "\\dir\\a.txt"), b"hello, world");
+        assert_eq!(fs.stat("\\dir\\a.txt").unwrap().size, 12);
+        assert!(fs.open("\\dir\\b.txt", OpenMode::Read).is_err());
+        assert!(fs.open("\\dir", OpenMode::Read).is_err());
+    }
+
+    #[test]
+    fn the_size_limit_is_kept() {
+        let fs = RamFs::new(1024);
+        write_file(&fs, "\\a", &[1; 1000]).unwrap();
+        assert_eq!(used(&fs), 1000);
+        let error = write_file(&fs, "\\b", &[2; 100]).unwrap_err();
+        assert_eq!(error, "ramfs is full (1 KB limit)");
+        assert_eq!(used(&fs), 1000);
+
+        // Deleting a file gives its space back
+        fs.unlink("\\a").unwrap();
+        assert_eq!(used(&fs), 0);
+        write_file(&fs, "\\b", &[2; 1024]).unwrap();
+
+        // So does truncating one
+        let mut file = fs.open("\\b", OpenMode::ReadWrite).unwrap();
+        file.set_len(24).unwrap();
+        drop(file);
+        assert_eq!(used(&fs), 24);
+        write_file(&fs, "\\c", &[3; 1000]).unwrap();
+        assert_eq!(used(&fs), 1024);
+    }
+
+    #[test]
+    fn rename_replaces_an_existing_file() {
+        let fs = RamFs::new(1024);
+        write_file(&fs, "\\old", b"new data").unwrap();
+        write_file(&fs, "\\target", &[0; 500]).unwrap();
+        fs.rename("\\old", "\\target").unwrap();
+        assert_eq!(read_file(&fs, "\\target"), b"new data");
+        assert!(fs.stat("\\old").is_err());
+        // The replaced file's data is released
+        assert_eq!(used(&fs), 8);
+    }
+
+    #[test]
+    fn directories_cannot_move_into_themselves() {
+        let fs = RamFs::new(1024);
+        fs.mkdir("\\a").unwrap();
+        fs.mkdir("\\a\\b").unwrap();
+        assert_eq!(
+            fs.rename("\\a", "\\a\\b\\a").unwrap_err(),
+            "Cannot rename '\\a' to '\\a\\b\\a'"
+        );
+        assert!(fs.rename("\\a", "\\a").is_ok());
+        fs.rename("\\a", "\\A").unwrap();
+        let names: Vec<String> = fs
+            .read_dir("\\")
+            .unwrap()
+            .into_iter()
+            .map(|e| e.name)
+            .collect();
+        assert_eq!(names, ["A"]);
+        assert!(fs.stat("\\A\\b").unwrap().is_dir);
+    }
+}
@@ -0,0 +1,386 @@
+extern crate alloc;
+
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use uefi::boot::{self, SearchType};
+use uefi::data_types::Align;
+use uefi::proto::device_path::text::{AllowShortcuts, DisplayOnly};
+use uefi::proto::device_path::DevicePath;
+use uefi::proto::loaded_image::LoadedImage;
+use uefi::proto::media::file::{
+    Directory, File, FileAttribute, FileHandle, FileInfo, FileMode, FileSystemInfo, RegularFile,
+};
+use uefi::proto::media::fs::SimpleFileSystem;
+use uefi::runtime::Time;
+use uefi::{CString16, Handle, Identify};
+
+use super::{DirEntry, FileSystem, FsStats, OpenFile, OpenMode};
+use crate::path;
+
+// ── Volumes ──
+
+/// Every handle with a `SimpleFileSystem`, in firmware order; handle `N`
+/// is the volume `fsN:`.
+fn volume_handles() -> Result<Vec<Handle>, String> {
+    boot::locate_handle_buffer(SearchType::ByProtocol(&SimpleFileSystem::GUID))
+        .map(|handles| handles.to_vec())
+        .map_err(|_| String::from("No file systems found"))
+}
+
+/// Handle of the device the running image was loaded from.
+fn boot_device() -> Option<Handle> {
+    boot::open_protocol_exclusive::<LoadedImage>(boot::image_handle())
+        .ok()?
+        .device()
+}
+
+/// True if the volume named `fsN` is the one the running image was
+/// loaded from.
+pub fn is_boot_volume(name: &str) -> bool {
+    let index = name
+        .strip_prefix("fs")
+        .and_then(|n| n.parse::<usize>().ok());
+    let handle = index.and_then(|i| volume_handles().ok()?.get(i).copied());
+    handle.is_some() && handle == boot_device()
+}
+
+/// The device path of `handle` as text, or empty if it has none.
+pub fn device_path_text(handle: Handle) -> String {
+    boot::open_protocol_exclusive::<DevicePath>(handle)
+        .ok()
+        .and_then(|path| path.to_string(DisplayOnly(true), AllowShortcuts(true)).ok())
+        .map(|text| String::from(&text))
+        .unwrap_or_default()
+}
+
+/// A `SimpleFileSystem` volume as listed by `vol`.
+pub struct Volume {
+    /// `fsN`, as used in path prefixes.
+    pub name: String,
+    pub label: String,
+    pub size: u64,
+    pub free: u64,
+    pub read_only: bool,
+    /// Device path as text, e.g. `PciRoot(0x0)/Pci(0x1,0x1)/Ata(0x0)/HD(1,...)`.
+    pub device: String,
+    /// The volume the running image was loaded from.
+    pub boot: bool,
+}
+
+/// Describe every volume. Details that cannot be read are left empty
+/// rather than failing the whole list.
+pub fn volumes() -> Result<Vec<Volume>, String> {
+    let boot_device = boot_device();
+    let mut list = Vec::new();
+    for (index, handle) in volume_handles()?.into_iter().enumerate() {
+        let stats = UefiFs::on(handle).stats().ok();
+        list.push(Volume {
+            name: format!("fs{}", index),
+            label: stats.as_ref().map(|s| s.label.clone()).unwrap_or_default(),
+            size: stats.as_ref().map_or(0, |s| s.size),
+            free: stats.as_ref().map_or(0, |s| s.free),
+            read_only: stats.as_ref().is_some_and(|s| s.read_only),
+            device: device_path_text(handle),
+            boot: boot_device == Some(handle),
+        });
+    }
+    Ok(list)
+}
+
+/// Mount the boot volume at `\` and every volume at `fsN:\`, dropping the
+/// `fsN:` mounts of volumes that have gone away.
+pub fn mount_volumes() -> Result<(), String> {
+    let handles = volume_handles()?;
+    for mount in super::mounts() {
+        if mount.kind == KIND && path::split_volume(&mount.point).0.is_some() {
+            super::unmount(&mount.point)?;
+        }
+    }
+    super::mount(path::ROOT, Box::new(UefiFs::new(None)));
+    for (index, handle) in handles.into_iter().enumerate() {
+        super::mount(&format!("fs{}:", index), Box::new(UefiFs::on(handle)));
+    }
+    Ok(())
+}
+
+// ── Backend ──
+
+const KIND: &str = "uefi";
+
+/// A volume accessed through the firmware's `SimpleFileSystem` protocol.
+/// Only usable while boot services are running.
+pub struct UefiFs {
+    /// `None` for the volume the running image was loaded from.
+    handle: Option<Handle>,
+    /// Where it is mounted, for messages.
+    point: String,
+}
+
+// SAFETY: boot services run on a single processor, and the handle is only
+// used through them.
+unsafe impl Send for UefiFs {}
+unsafe impl Sync for UefiFs {}
+
+impl UefiFs {
+    fn new(handle: Option<Handle>) -> Self {
+        Self {
+            handle,
+            point: String::from(path::ROOT),
+        }
+    }
+
+    fn on(handle: Handle) -> Self {
+        Self::new(Some(handle))
+    }
+
+    /// `path` as the full path shown in messages.
+    fn full(&self, path: &str) -> String {
+        super::full_path(&self.point, path)
+    }
+
+    fn root(&self) -> Result<Directory, String> {
+        let mut fs = match self.handle {
+            None => boot::get_image_file_system(boot::image_handle()),
+            Some(handle) => boot::open_protocol_exclusive::<SimpleFileSystem>(handle),
+        }
+        .map_err(|_| String::from("Failed to open FS"))?;
+        fs.open_volume()
+            .map_err(|_| String::from("Failed to open volume"))
+    }
+
+    fn open_handle(
+        &self,
+        path: &str,
+        mode: FileMode,
+        attribute: FileAttribute,
+    ) -> Result<FileHandle, String> {
+        let path_cstr = CString16::try_from(path).map_err(|_| String::from("Invalid path"))?;
+        self.root()?
+            .open(&path_cstr, mode, attribute)
+            .map_err(|_| match mode {
+                FileMode::CreateReadWrite => format!("Cannot create '{}'", self.full(path)),
+                _ => format!("Cannot open '{}'", self.full(path)),
+            })
+    }
+
+    /// Open a file or directory and update its `FileInfo` with `change`.
+    fn update_info(&self, path: &str, change: impl FnOnce(&mut InfoUpdate)) -> Result<(), String> {
+        let mut handle = self.open_handle(path, FileMode::ReadWrite, FileAttribute::empty())?;
+        change_info(&mut handle, &self.full(path), change)
+    }
+}
+
+fn entry(info: &FileInfo) -> DirEntry {
+    DirEntry {
+        name: info.file_name().to_string(),
+        is_dir: info.is_directory(),
+        size: info.file_size(),
+        attribute: info.attribute(),
+        created: *info.create_time(),
+        modified: *info.modification_time(),
+        accessed: *info.last_access_time(),
+    }
+}
+
+impl FileSystem for UefiFs {
+    fn kind(&self) -> &'static str {
+        KIND
+    }
+
+    fn source(&self) -> String {
+        match self.handle {
+            Some(handle) => device_path_text(handle),
+            None => boot_device().map(device_path_text).unwrap_or_default(),
+        }
+    }
+
+    fn device(&self) -> Option<Handle> {
+        self.handle.or_else(boot_device)
+    }
+
+    fn set_mount_point(&mut self, point: &str) {
+        self.point = String::from(point);
+    }
+
+    fn stats(&self) -> Result<FsStats, String> {
+        let info = self
+            .root()?
+            .get_boxed_info::<FileSystemInfo>()
+            .map_err(|_| String::from("Cannot read volume info"))?;
+        Ok(FsStats {
+            label: info.volume_label().to_string(),
+            size: info.volume_size(),
+            free: info.free_space(),
+            read_only: info.read_only(),
+        })
+    }
+
+    fn open(&self, path: &str, mode: OpenMode) -> Result<Box<dyn OpenFile>, String> {
+        let file_mode = match mode {
+            OpenMode::Read => FileMode::Read,
+            OpenMode::ReadWrite => FileMode::ReadWrite,
+            OpenMode::Create => FileMode::CreateReadWrite,
+        };
+        let file = self
+            .open_handle(path, file_mode, FileAttribute::empty())?
+            .into_regular_file()
+            .ok_or_else(|| format!("'{}' is a directory", self.full(path)))?;
+        Ok(Box::new(UefiFile {
+            file,
+            path: self.full(path),
+        }))
+    }
+
+    fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, String> {
+        let mut dir = self
+            .open_handle(path, FileMode::Read, FileAttribute::empty())?
+            .into_directory()
+            .ok_or_else(|| format!("'{}' is not a directory", self.full(path)))?;
+
+        let mut entries = Vec::new();
+        loop {
+            match dir.read_entry_boxed() {
+                Ok(Some(info)) => entries.push(entry(&info)),
+                Ok(None) => break,
+                Err(_) => return Err(String::from("Error reading directory")),
+            }
+        }
+        Ok(entries)
+    }
+
+    fn stat(&self, path: &str) -> Result<DirEntry, String> {
+        let info = self
+            .open_handle(path, FileMode::Read, FileAttribute::empty())?
+            .get_boxed_info::<FileInfo>()
+            .map_err(|_| format!("Cannot read info for '{}'", self.full(path)))?;
+        Ok(entry(&info))
+    }
+
+    fn unlink(&self, path: &str) -> Result<(), String> {
+        self.open_handle(path, FileMode::ReadWrite, FileAttribute::empty())?
+            .delete()
+            .map_err(|_| format!("Cannot delete '{}'", self.full(path)))
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), String> {
+        self.open_handle(path, FileMode::CreateReadWrite, FileAttribute::DIRECTORY)
+            .map(|_| ())
+            .map_err(|_| format!("Cannot create directory '{}'", self.full(path)))
+    }
+
+    /// Sets a new name in the `FileInfo`. Firmware may refuse, e.g. when
+    /// moving between directories.
+    fn rename(&self, from: &str, to: &str) -> Result<(), String> {
+        let new_name = CString16::try_from(to).map_err(|_| String::from("Invalid path"))?;
+        self.update_info(from, |info| info.name = new_name)
+            .map_err(|_| format!("Cannot rename '{}' to '{}'", self.full(from), self.full(to)))
+    }
+
+    fn set_attributes(&self, path: &str, attribute: FileAttribute) -> Result<(), String> {
+        self.update_info(path, |info| {
+            info.attribute = (info.attribute & FileAttribute::DIRECTORY)
+                | (attribute - FileAttribute::DIRECTORY);
+        })
+    }
+
+    fn set_times(&self, path: &str, time: Time) -> Result<(), String> {
+        self.update_info(path, |info| {
+            info.modified = time;
+            info.accessed = time;
+        })
+    }
+}
+
+/// An open file on a `UefiFs` volume.
+struct UefiFile {
+    file: RegularFile,
+    /// For error messages.
+    path: String,
+}
+
+// SAFETY: as for `UefiFs`, the file protocol is only used from the single
+// processor boot services run on.
+unsafe impl Send for UefiFile {}
+
+impl OpenFile for UefiFile {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, String> {
+        self.file.read(buf).map_err(|_| String::from("Read error"))
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), String> {
+        self.file
+            .write(data)
+            .map_err(|_| format!("Write error on '{}'", self.path))
+    }
+
+    /// Reading at a position past the end is an error in UEFI.
+    fn seek(&mut self, position: u64) -> Result<(), String> {
+        self.file
+            .set_position(position)
+            .map_err(|_| String::from("Seek error"))
+    }
+
+    fn size(&mut self) -> Result<u64, String> {
+        self.file
+            .get_boxed_info::<FileInfo>()
+            .map(|info| info.file_size())
+            .map_err(|_| format!("Cannot read info for '{}'", self.path))
+    }
+
+    fn set_len(&mut self, len: u64) -> Result<(), String> {
+        change_info(&mut self.file, &self.path, |info| info.size = len)
+    }
+}
+
+/// The parts of a file's `FileInfo` that `set_info` may change.
+struct InfoUpdate {
+    name: CString16,
+    /// Setting a smaller size truncates the file.
+    size: u64,
+    attribute: FileAttribute,
+    accessed: Time,
+    modified: Time,
+}
+
+/// Read the `FileInfo` of an open file, let `change` adjust it and write
+/// it back.
+fn change_info(
+    handle: &mut impl File,
+    path: &str,
+    change: impl FnOnce(&mut InfoUpdate),
+) -> Result<(), String> {
+    let info = handle
+        .get_boxed_info::<FileInfo>()
+        .map_err(|_| format!("Cannot read info for '{}'", path))?;
+
+    let mut update = InfoUpdate {
+        name: CString16::from(info.file_name()),
+        size: info.file_size(),
+        attribute: info.attribute(),
+        accessed: *info.last_access_time(),
+        modified: *info.modification_time(),
+    };
+    change(&mut update);
+
+    // Fixed FileInfo fields, the UCS-2 name and alignment slack
+    let mut storage = vec![0u8; 128 + 2 * update.name.as_slice_with_nul().len()];
+    let buf = FileInfo::align_buf(&mut storage).ok_or_else(|| String::from("Out of memory"))?;
+    let new_info = FileInfo::new(
+        buf,
+        update.size,
+        info.physical_size(),
+        *info.create_time(),
+        update.accessed,
+        update.modified,
+        update.attribute,
+        &update.name,
+    )
+    .map_err(|_| String::from("Invalid file name"))?;
+
+    handle
+        .set_info(new_info)
+        .map_err(|_| format!("Cannot update '{}'", path))
+}